@@ -0,0 +1,112 @@
+use std::fmt::Write;
+use std::net::IpAddr;
+use ring::digest;
+use time::Duration;
+use crate::{Certificate, CertificateParams, CustomExtension, DistinguishedName, DnType,
+	RcgenError, SanType, OID_PE_ACME};
+
+/// An identifier that is validated with an ACME challenge
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum AcmeIdentifier {
+	/// A domain name, as specified in
+	/// [RFC 8555 section 9.7.7](https://tools.ietf.org/html/rfc8555#section-9.7.7)
+	Dns(String),
+	/// An IP address, as specified in [RFC 8738](https://tools.ietf.org/html/rfc8738)
+	Ip(IpAddr),
+}
+
+impl AcmeIdentifier {
+	/// The server name the ACME server sends in the TLS SNI extension
+	/// when validating the identifier with the TLS-ALPN-01 challenge
+	///
+	/// For IP addresses, this is the reverse DNS name as specified in
+	/// [RFC 8738 section 6](https://tools.ietf.org/html/rfc8738#section-6).
+	///
+	/// ```
+	/// # use rcgen::AcmeIdentifier;
+	/// let identifier = AcmeIdentifier::Ip("192.0.2.1".parse().unwrap());
+	/// assert_eq!(identifier.tls_alpn_01_server_name(), "1.2.0.192.in-addr.arpa");
+	/// ```
+	pub fn tls_alpn_01_server_name(&self) -> String {
+		match self {
+			AcmeIdentifier::Dns(name) => name.clone(),
+			AcmeIdentifier::Ip(IpAddr::V4(addr)) => {
+				let mut name = String::new();
+				for octet in addr.octets().iter().rev() {
+					write!(name, "{}.", octet).unwrap();
+				}
+				name + "in-addr.arpa"
+			},
+			AcmeIdentifier::Ip(IpAddr::V6(addr)) => {
+				let mut name = String::new();
+				for octet in addr.octets().iter().rev() {
+					write!(name, "{:x}.{:x}.", octet & 0xf, octet >> 4).unwrap();
+				}
+				name + "ip6.arpa"
+			},
+		}
+	}
+}
+
+impl CustomExtension {
+	/// Creates a new acmeIdentifier extension for ACME TLS-ALPN-01
+	/// as specified in [RFC 8737](https://tools.ietf.org/html/rfc8737#section-3)
+	///
+	/// Unlike [`new_acme_identifier`](Self::new_acme_identifier), fails with
+	/// [`RcgenError::InvalidAcmeIdentifierDigest`] instead of panicking if the
+	/// `sha_digest` parameter doesn't hold 32 bytes (256 bits).
+	pub fn try_new_acme_identifier(sha_digest :&[u8]) -> Result<Self, RcgenError> {
+		if sha_digest.len() != 32 {
+			return Err(RcgenError::InvalidAcmeIdentifierDigest);
+		}
+		let content = yasna::construct_der(|writer| {
+			writer.write_bytes(sha_digest);
+		});
+		Ok(Self {
+			oid : OID_PE_ACME.to_owned(),
+			critical : true,
+			content,
+		})
+	}
+}
+
+impl CertificateParams {
+	/// Parameters for the self signed certificate of the ACME TLS-ALPN-01 challenge
+	///
+	/// As specified in [RFC 8737 section 3](https://tools.ietf.org/html/rfc8737#section-3),
+	/// the certificate contains the identifier as its only subject alternative name,
+	/// and the SHA-256 digest of the key authorization in the critical acmeIdentifier
+	/// extension. IP address identifiers follow [RFC 8738](https://tools.ietf.org/html/rfc8738).
+	/// The certificate is valid for 7 days from now on.
+	pub fn tls_alpn_01(identifier :&AcmeIdentifier, key_authorization :&str) -> Result<Self, RcgenError> {
+		let (name, san) = match identifier {
+			AcmeIdentifier::Dns(name) => (name.clone(), SanType::DnsName(name.clone())),
+			AcmeIdentifier::Ip(addr) => (addr.to_string(), SanType::IpAddress(*addr)),
+		};
+		let key_authorization_digest = digest::digest(&digest::SHA256, key_authorization.as_bytes());
+		let mut params = CertificateParams::new(Vec::new());
+		params.distinguished_name = DistinguishedName::new();
+		params.distinguished_name.push(DnType::CommonName, name);
+		params.subject_alt_names = vec![san];
+		params.custom_extensions = vec![CustomExtension::try_new_acme_identifier(key_authorization_digest.as_ref())?];
+		params.valid_for(Duration::days(7));
+		Ok(params)
+	}
+}
+
+/// Generates the self signed certificate for the ACME TLS-ALPN-01 challenge
+///
+/// See [`CertificateParams::tls_alpn_01`] for the contents of the certificate.
+/// Serve it to TLS clients that offer the `acme-tls/1` ALPN protocol
+/// and the [server name](AcmeIdentifier::tls_alpn_01_server_name) of the identifier.
+///
+/// ```
+/// # use rcgen::{generate_tls_alpn_01_certificate, AcmeIdentifier};
+/// let identifier = AcmeIdentifier::Dns("crabs.crabs".to_string());
+/// let cert = generate_tls_alpn_01_certificate(&identifier,
+/// 	"evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA.9jg46WB3rR_AHD-EBXdN7cBkH1WOu0tA3M9fm21mqTI").unwrap();
+/// println!("{}", cert.serialize_pem().unwrap());
+/// ```
+pub fn generate_tls_alpn_01_certificate(identifier :&AcmeIdentifier, key_authorization :&str) -> Result<Certificate, RcgenError> {
+	Certificate::from_params(CertificateParams::tls_alpn_01(identifier, key_authorization)?)
+}
@@ -0,0 +1,899 @@
+//! In-process ACME ([RFC 8555](https://tools.ietf.org/html/rfc8555)) server for tests
+//!
+//! The server speaks plain HTTP on a loopback port, and implements the parts
+//! of the protocol that clients need to obtain certificates: nonces, accounts,
+//! orders, authorizations, challenges, finalization and certificate download.
+//! Requests are authenticated with JWS using ES256, ES384, EdDSA or RS256.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration as StdDuration;
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde_json::{json, Value};
+use time::{Duration, OffsetDateTime};
+use time::format_description::well_known::Rfc3339;
+use crate::{random_serial_number, AcmeIdentifier, Certificate, CertificateSigningRequest,
+	DistinguishedName, DnType, ExtendedKeyUsagePurpose, KeyUsagePurpose, SanType};
+
+/// How the [`AcmeTestServer`] validates challenges
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum ChallengeValidation {
+	/// All challenges are considered valid as soon as the client responds to them
+	Automatic,
+	/// http-01 challenges are validated by fetching the key authorization
+	/// from the given port on the loopback interface
+	///
+	/// Other challenge types can't be validated and become invalid.
+	Loopback {
+		/// The port the client serves http-01 challenge responses on
+		http_port :u16,
+	},
+}
+
+/// Options for the [`AcmeTestServer`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AcmeTestServerOptions {
+	/// How challenges are validated, defaults to [`ChallengeValidation::Automatic`]
+	pub challenge_validation :ChallengeValidation,
+	/// Validity of issued certificates, defaults to 90 days
+	pub validity :Duration,
+}
+
+impl Default for AcmeTestServerOptions {
+	fn default() -> Self {
+		AcmeTestServerOptions {
+			challenge_validation : ChallengeValidation::Automatic,
+			validity : Duration::days(90),
+		}
+	}
+}
+
+/// An ACME server running in a background thread, as a stand-in for
+/// public CAs or Pebble in tests of ACME clients
+///
+/// Certificates are issued by the given CA, which is assumed to be self signed.
+/// The server is stopped when dropped.
+///
+/// ```
+/// # use rcgen::{AcmeTestServer, Certificate, CertificateParams};
+/// let ca = Certificate::from_params(CertificateParams::root_ca("Crab ACME CA")).unwrap();
+/// let server = AcmeTestServer::start(ca, Default::default()).unwrap();
+/// println!("Directory at {}", server.directory_url());
+/// ```
+///
+/// *This type is only available if rcgen is built with the "acme-server" feature*
+pub struct AcmeTestServer {
+	addr :SocketAddr,
+	ca_der :Vec<u8>,
+	shutdown :Arc<AtomicBool>,
+	thread :Option<JoinHandle<()>>,
+}
+
+impl AcmeTestServer {
+	/// Starts the server on a random port of the loopback interface
+	pub fn start(ca :Certificate, options :AcmeTestServerOptions) -> io::Result<Self> {
+		let ca_der = ca.serialize_der()
+			.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+		let listener = TcpListener::bind(("127.0.0.1", 0))?;
+		let addr = listener.local_addr()?;
+		let state = Arc::new(Mutex::new(State {
+			base_url : format!("http://{}", addr),
+			ca,
+			ca_der : ca_der.clone(),
+			options,
+			nonces : HashSet::new(),
+			next_id : 1,
+			accounts : HashMap::new(),
+			orders : HashMap::new(),
+			authorizations : HashMap::new(),
+			challenges : HashMap::new(),
+			certificates : HashMap::new(),
+		}));
+		let shutdown = Arc::new(AtomicBool::new(false));
+		let thread_shutdown = shutdown.clone();
+		let thread = std::thread::spawn(move || {
+			for stream in listener.incoming() {
+				if thread_shutdown.load(Ordering::SeqCst) {
+					break;
+				}
+				if let Ok(stream) = stream {
+					let state = state.clone();
+					std::thread::spawn(move || {
+						// Errors only affect the single connection
+						let _ = handle_connection(stream, &state);
+					});
+				}
+			}
+		});
+		Ok(AcmeTestServer {
+			addr,
+			ca_der,
+			shutdown,
+			thread : Some(thread),
+		})
+	}
+	/// The address the server listens on
+	pub fn addr(&self) -> SocketAddr {
+		self.addr
+	}
+	/// The URL of the ACME directory, which clients are configured with
+	pub fn directory_url(&self) -> String {
+		format!("http://{}/directory", self.addr)
+	}
+	/// The CA certificate in DER format, which clients have to trust
+	pub fn ca_certificate_der(&self) -> &[u8] {
+		&self.ca_der
+	}
+}
+
+impl Drop for AcmeTestServer {
+	fn drop(&mut self) {
+		self.shutdown.store(true, Ordering::SeqCst);
+		// Wake up the accept loop so that it notices the shutdown
+		let _ = TcpStream::connect(self.addr);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+struct State {
+	base_url :String,
+	ca :Certificate,
+	ca_der :Vec<u8>,
+	options :AcmeTestServerOptions,
+	nonces :HashSet<String>,
+	next_id :u64,
+	accounts :HashMap<u64, Account>,
+	orders :HashMap<u64, Order>,
+	authorizations :HashMap<u64, Authorization>,
+	challenges :HashMap<u64, Challenge>,
+	certificates :HashMap<u64, (u64, String)>,
+}
+
+struct Account {
+	jwk :Value,
+	thumbprint :String,
+	contact :Value,
+}
+
+struct Order {
+	account :u64,
+	status :&'static str,
+	expires :OffsetDateTime,
+	identifiers :Vec<AcmeIdentifier>,
+	authorizations :Vec<u64>,
+	certificate :Option<u64>,
+}
+
+struct Authorization {
+	account :u64,
+	status :&'static str,
+	expires :OffsetDateTime,
+	identifier :AcmeIdentifier,
+	challenges :Vec<u64>,
+}
+
+struct Challenge {
+	authorization :u64,
+	kind :&'static str,
+	token :String,
+	status :&'static str,
+	validated :Option<OffsetDateTime>,
+	error :Option<Problem>,
+}
+
+/// An error document as specified in RFC 8555 section 6.7
+#[derive(Clone)]
+struct Problem {
+	status :u16,
+	kind :&'static str,
+	detail :String,
+}
+
+impl Problem {
+	fn new(status :u16, kind :&'static str, detail :impl Into<String>) -> Self {
+		Problem {
+			status,
+			kind,
+			detail : detail.into(),
+		}
+	}
+	fn malformed(detail :impl Into<String>) -> Self {
+		Problem::new(400, "malformed", detail)
+	}
+	fn to_json(&self) -> Value {
+		json!({
+			"type" : format!("urn:ietf:params:acme:error:{}", self.kind),
+			"detail" : self.detail,
+			"status" : self.status,
+		})
+	}
+}
+
+struct Request {
+	method :String,
+	path :String,
+	body :Vec<u8>,
+}
+
+struct Response {
+	status :u16,
+	headers :Vec<(&'static str, String)>,
+	content_type :&'static str,
+	body :Vec<u8>,
+}
+
+impl Response {
+	fn json(status :u16, value :Value) -> Self {
+		Response {
+			status,
+			headers : Vec::new(),
+			content_type : "application/json",
+			body : value.to_string().into_bytes(),
+		}
+	}
+	fn empty(status :u16) -> Self {
+		Response {
+			status,
+			headers : Vec::new(),
+			content_type : "text/plain",
+			body : Vec::new(),
+		}
+	}
+	fn header(mut self, name :&'static str, value :String) -> Self {
+		self.headers.push((name, value));
+		self
+	}
+}
+
+impl From<Problem> for Response {
+	fn from(problem :Problem) -> Self {
+		Response {
+			status : problem.status,
+			headers : Vec::new(),
+			content_type : "application/problem+json",
+			body : problem.to_json().to_string().into_bytes(),
+		}
+	}
+}
+
+fn handle_connection(stream :TcpStream, state :&Mutex<State>) -> io::Result<()> {
+	stream.set_read_timeout(Some(StdDuration::from_secs(10)))?;
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let request = match read_request(&mut reader)? {
+		Some(request) => request,
+		None => return Ok(()),
+	};
+	let mut response = match handle_request(&request, state) {
+		Ok(response) => response,
+		Err(problem) => problem.into(),
+	};
+	let nonce = state.lock().unwrap_or_else(|e| e.into_inner()).new_nonce();
+	response.headers.push(("Replay-Nonce", nonce));
+	write_response(stream, &request, response)
+}
+
+fn handle_request(request :&Request, state :&Mutex<State>) -> Result<Response, Problem> {
+	let lock = || state.lock().unwrap_or_else(|e| e.into_inner());
+	let validation = match lock().handle(request)? {
+		Handled::Response(response) => return Ok(response),
+		Handled::Validate(validation) => validation,
+	};
+	// Fetching the key authorization can take a while, so the lock
+	// is released to let other requests through meanwhile
+	let result = validate_challenge(&validation);
+	Ok(lock().finish_validation(&validation, result))
+}
+
+fn read_request(reader :&mut impl BufRead) -> io::Result<Option<Request>> {
+	let mut line = String::new();
+	if reader.read_line(&mut line)? == 0 {
+		return Ok(None);
+	}
+	let mut parts = line.split_whitespace();
+	let (method, path) = match (parts.next(), parts.next()) {
+		(Some(method), Some(path)) => (method.to_string(), path.to_string()),
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid request line")),
+	};
+	let mut content_length = 0;
+	loop {
+		let mut line = String::new();
+		reader.read_line(&mut line)?;
+		let line = line.trim_end();
+		if line.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = line.split_once(':') {
+			if name.eq_ignore_ascii_case("content-length") {
+				content_length = value.trim().parse()
+					.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid content length"))?;
+			}
+		}
+	}
+	let mut body = vec![0; content_length];
+	reader.read_exact(&mut body)?;
+	Ok(Some(Request { method, path, body }))
+}
+
+fn write_response(mut stream :TcpStream, request :&Request, response :Response) -> io::Result<()> {
+	let mut head = format!("HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+		Cache-Control: no-store\r\nConnection: close\r\n",
+		response.status, reason_phrase(response.status), response.content_type, response.body.len());
+	for (name, value) in response.headers.iter() {
+		head += &format!("{}: {}\r\n", name, value);
+	}
+	head += "\r\n";
+	stream.write_all(head.as_bytes())?;
+	if request.method != "HEAD" {
+		stream.write_all(&response.body)?;
+	}
+	stream.flush()
+}
+
+fn reason_phrase(status :u16) -> &'static str {
+	match status {
+		200 => "OK",
+		201 => "Created",
+		204 => "No Content",
+		400 => "Bad Request",
+		401 => "Unauthorized",
+		403 => "Forbidden",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		_ => "Internal Server Error",
+	}
+}
+
+enum Handled {
+	Response(Response),
+	/// A challenge has to be validated before responding
+	Validate(Validation),
+}
+
+/// A challenge that is being validated
+struct Validation {
+	challenge :u64,
+	method :ChallengeValidation,
+	kind :&'static str,
+	identifier :AcmeIdentifier,
+	token :String,
+	key_authorization :String,
+}
+
+/// A verified JWS request body
+struct Jws {
+	account :Option<u64>,
+	jwk :Option<Value>,
+	/// `None` for POST-as-GET requests
+	payload :Option<Value>,
+}
+
+impl State {
+	fn handle(&mut self, request :&Request) -> Result<Handled, Problem> {
+		let path = request.path.as_str();
+		match (request.method.as_str(), path) {
+			("GET", "/directory") => return Ok(Handled::Response(Response::json(200, self.directory()))),
+			("HEAD", "/new-nonce") => return Ok(Handled::Response(Response::empty(200))),
+			("GET", "/new-nonce") => return Ok(Handled::Response(Response::empty(204))),
+			("POST", _) => (),
+			_ => return Err(Problem::new(405, "malformed", format!("{} {} is not supported", request.method, path))),
+		}
+		let jws = self.verify_jws(path, &request.body)?;
+		let (resource, id) = match path[1..].split_once('/') {
+			Some((resource, id)) => (resource, id.parse::<u64>()
+				.map_err(|_| Problem::new(404, "malformed", "not found"))?),
+			None => (&path[1..], 0),
+		};
+		let response = match resource {
+			"new-account" => self.new_account(jws),
+			"account" => self.account(jws, id),
+			"orders" => self.account_orders(jws, id),
+			"new-order" => self.new_order(jws),
+			"order" => self.order(jws, id),
+			"authz" => self.authorization(jws, id),
+			"chall" => return self.challenge(jws, id),
+			"finalize" => self.finalize(jws, id),
+			"cert" => self.certificate(jws, id),
+			_ => Err(Problem::new(404, "malformed", "not found")),
+		};
+		response.map(Handled::Response)
+	}
+	fn url(&self, resource :&str, id :u64) -> String {
+		format!("{}/{}/{}", self.base_url, resource, id)
+	}
+	fn next_id(&mut self) -> u64 {
+		self.next_id += 1;
+		self.next_id
+	}
+	fn new_nonce(&mut self) -> String {
+		let nonce = random_token();
+		self.nonces.insert(nonce.clone());
+		nonce
+	}
+	fn directory(&self) -> Value {
+		json!({
+			"newNonce" : format!("{}/new-nonce", self.base_url),
+			"newAccount" : format!("{}/new-account", self.base_url),
+			"newOrder" : format!("{}/new-order", self.base_url),
+			"meta" : {},
+		})
+	}
+	fn verify_jws(&mut self, path :&str, body :&[u8]) -> Result<Jws, Problem> {
+		let body :Value = serde_json::from_slice(body)
+			.map_err(|_| Problem::malformed("request body is not JSON"))?;
+		let field = |name| body[name].as_str()
+			.ok_or_else(|| Problem::malformed(format!("JWS field {} is missing", name)));
+		let (protected_b64, payload_b64, signature_b64) = (field("protected")?, field("payload")?, field("signature")?);
+		let protected :Value = serde_json::from_slice(&base64url_decode(protected_b64)?)
+			.map_err(|_| Problem::malformed("protected header is not JSON"))?;
+
+		let nonce = protected["nonce"].as_str().unwrap_or_default();
+		if !self.nonces.remove(nonce) {
+			return Err(Problem::new(400, "badNonce", "unknown or reused nonce"));
+		}
+		if protected["url"].as_str() != Some(&format!("{}{}", self.base_url, path)) {
+			return Err(Problem::new(401, "unauthorized", "url in protected header doesn't match"));
+		}
+		let (account, jwk) = match (&protected["jwk"], protected["kid"].as_str()) {
+			(Value::Object(_), None) => (None, protected["jwk"].clone()),
+			(Value::Null, Some(kid)) => {
+				let id = kid.strip_prefix(&format!("{}/account/", self.base_url))
+					.and_then(|id| id.parse::<u64>().ok())
+					.filter(|id| self.accounts.contains_key(id))
+					.ok_or_else(|| Problem::new(400, "accountDoesNotExist", "unknown account"))?;
+				(Some(id), self.accounts[&id].jwk.clone())
+			},
+			_ => return Err(Problem::malformed("exactly one of jwk and kid has to be given")),
+		};
+		let alg = protected["alg"].as_str().unwrap_or_default();
+		let message = format!("{}.{}", protected_b64, payload_b64);
+		verify_signature(alg, &jwk, message.as_bytes(), &base64url_decode(signature_b64)?)?;
+
+		let payload = if payload_b64.is_empty() {
+			None
+		} else {
+			Some(serde_json::from_slice(&base64url_decode(payload_b64)?)
+				.map_err(|_| Problem::malformed("payload is not JSON"))?)
+		};
+		Ok(Jws {
+			account,
+			jwk : if account.is_none() { Some(jwk) } else { None },
+			payload,
+		})
+	}
+	fn new_account(&mut self, jws :Jws) -> Result<Response, Problem> {
+		let jwk = jws.jwk.ok_or_else(|| Problem::malformed("new accounts have to be requested with jwk"))?;
+		let payload = jws.payload.unwrap_or_default();
+		let thumbprint = jwk_thumbprint(&jwk)?;
+		let existing = self.accounts.iter()
+			.find(|(_, account)| account.thumbprint == thumbprint)
+			.map(|(id, _)| *id);
+		let (status, id) = match existing {
+			Some(id) => (200, id),
+			None if payload["onlyReturnExisting"] == Value::Bool(true) => {
+				return Err(Problem::new(400, "accountDoesNotExist", "no account exists for the key"));
+			},
+			None => {
+				let id = self.next_id();
+				self.accounts.insert(id, Account {
+					jwk,
+					thumbprint,
+					contact : payload.get("contact").cloned().unwrap_or_else(|| json!([])),
+				});
+				(201, id)
+			},
+		};
+		Ok(Response::json(status, self.account_json(id))
+			.header("Location", self.url("account", id)))
+	}
+	fn account(&mut self, jws :Jws, id :u64) -> Result<Response, Problem> {
+		if jws.account != Some(id) {
+			return Err(Problem::new(403, "unauthorized", "account doesn't belong to the key"));
+		}
+		Ok(Response::json(200, self.account_json(id)))
+	}
+	/// The orders list of RFC 8555 section 7.1.2.1, without pagination
+	fn account_orders(&mut self, jws :Jws, id :u64) -> Result<Response, Problem> {
+		if jws.account != Some(id) {
+			return Err(Problem::new(403, "unauthorized", "account doesn't belong to the key"));
+		}
+		let mut orders = self.orders.iter()
+			.filter(|(_, order)| order.account == id)
+			.map(|(order_id, _)| *order_id)
+			.collect::<Vec<_>>();
+		orders.sort_unstable();
+		let orders = orders.into_iter()
+			.map(|order_id| self.url("order", order_id))
+			.collect::<Vec<_>>();
+		Ok(Response::json(200, json!({ "orders" : orders })))
+	}
+	fn account_json(&self, id :u64) -> Value {
+		json!({
+			"status" : "valid",
+			"contact" : self.accounts[&id].contact,
+			"orders" : self.url("orders", id),
+		})
+	}
+	fn new_order(&mut self, jws :Jws) -> Result<Response, Problem> {
+		let account = jws.account.ok_or_else(|| Problem::malformed("orders have to be requested with kid"))?;
+		let payload = jws.payload.unwrap_or_default();
+		let identifiers = payload["identifiers"].as_array()
+			.filter(|identifiers| !identifiers.is_empty())
+			.ok_or_else(|| Problem::malformed("identifiers are missing"))?
+			.iter()
+			.map(identifier_from_json)
+			.collect::<Result<Vec<_>, _>>()?;
+		let expires = OffsetDateTime::now_utc() + Duration::days(7);
+		let mut authorizations = Vec::new();
+		for identifier in identifiers.iter() {
+			let authorization = self.next_id();
+			let kinds :&[&'static str] = match identifier {
+				AcmeIdentifier::Dns(name) if name.starts_with("*.") => &["dns-01"],
+				AcmeIdentifier::Dns(_) => &["http-01", "dns-01", "tls-alpn-01"],
+				AcmeIdentifier::Ip(_) => &["http-01", "tls-alpn-01"],
+			};
+			let mut challenges = Vec::new();
+			for kind in kinds {
+				let challenge = self.next_id();
+				self.challenges.insert(challenge, Challenge {
+					authorization,
+					kind,
+					token : random_token(),
+					status : "pending",
+					validated : None,
+					error : None,
+				});
+				challenges.push(challenge);
+			}
+			self.authorizations.insert(authorization, Authorization {
+				account,
+				status : "pending",
+				expires,
+				identifier : identifier.clone(),
+				challenges,
+			});
+			authorizations.push(authorization);
+		}
+		let id = self.next_id();
+		self.orders.insert(id, Order {
+			account,
+			status : "pending",
+			expires,
+			identifiers,
+			authorizations,
+			certificate : None,
+		});
+		Ok(Response::json(201, self.order_json(id))
+			.header("Location", self.url("order", id)))
+	}
+	fn order(&mut self, jws :Jws, id :u64) -> Result<Response, Problem> {
+		self.check_order(&jws, id)?;
+		Ok(Response::json(200, self.order_json(id)))
+	}
+	fn check_order(&self, jws :&Jws, id :u64) -> Result<(), Problem> {
+		match self.orders.get(&id) {
+			Some(order) if Some(order.account) == jws.account => Ok(()),
+			Some(_) => Err(Problem::new(403, "unauthorized", "order belongs to another account")),
+			None => Err(Problem::new(404, "malformed", "unknown order")),
+		}
+	}
+	fn order_json(&self, id :u64) -> Value {
+		let order = &self.orders[&id];
+		let mut value = json!({
+			"status" : order.status,
+			"expires" : format_time(order.expires),
+			"identifiers" : order.identifiers.iter().map(identifier_to_json).collect::<Vec<_>>(),
+			"authorizations" : order.authorizations.iter()
+				.map(|id| self.url("authz", *id))
+				.collect::<Vec<_>>(),
+			"finalize" : self.url("finalize", id),
+		});
+		if let Some(certificate) = order.certificate {
+			value["certificate"] = Value::String(self.url("cert", certificate));
+		}
+		value
+	}
+	fn authorization(&mut self, jws :Jws, id :u64) -> Result<Response, Problem> {
+		match self.authorizations.get(&id) {
+			Some(authorization) if Some(authorization.account) == jws.account => (),
+			Some(_) => return Err(Problem::new(403, "unauthorized", "authorization belongs to another account")),
+			None => return Err(Problem::new(404, "malformed", "unknown authorization")),
+		}
+		if jws.payload.as_ref().map(|payload| payload["status"] == "deactivated") == Some(true) {
+			self.authorizations.get_mut(&id).unwrap().status = "deactivated";
+		}
+		Ok(Response::json(200, self.authorization_json(id)))
+	}
+	fn authorization_json(&self, id :u64) -> Value {
+		let authorization = &self.authorizations[&id];
+		let mut value = json!({
+			"status" : authorization.status,
+			"expires" : format_time(authorization.expires),
+			"identifier" : identifier_to_json(&authorization.identifier),
+			"challenges" : authorization.challenges.iter()
+				.map(|id| self.challenge_json(*id))
+				.collect::<Vec<_>>(),
+		});
+		if let AcmeIdentifier::Dns(name) = &authorization.identifier {
+			if let Some(name) = name.strip_prefix("*.") {
+				value["identifier"]["value"] = Value::String(name.to_string());
+				value["wildcard"] = Value::Bool(true);
+			}
+		}
+		value
+	}
+	fn challenge(&mut self, jws :Jws, id :u64) -> Result<Handled, Problem> {
+		let authorization_id = match self.challenges.get(&id) {
+			Some(challenge) => challenge.authorization,
+			None => return Err(Problem::new(404, "malformed", "unknown challenge")),
+		};
+		let authorization = &self.authorizations[&authorization_id];
+		if Some(authorization.account) != jws.account {
+			return Err(Problem::new(403, "unauthorized", "challenge belongs to another account"));
+		}
+		// Only pending challenges of pending authorizations can be responded to
+		let challenge = &self.challenges[&id];
+		if jws.payload.is_some() && challenge.status == "pending" && authorization.status == "pending" {
+			let thumbprint = &self.accounts[&authorization.account].thumbprint;
+			let validation = Validation {
+				challenge : id,
+				method : self.options.challenge_validation,
+				kind : challenge.kind,
+				identifier : authorization.identifier.clone(),
+				token : challenge.token.clone(),
+				key_authorization : format!("{}.{}", challenge.token, thumbprint),
+			};
+			self.challenges.get_mut(&id).unwrap().status = "processing";
+			return Ok(Handled::Validate(validation));
+		}
+		Ok(Handled::Response(self.challenge_response(id)))
+	}
+	/// Records the result of validating a challenge and responds with the challenge
+	fn finish_validation(&mut self, validation :&Validation, result :Result<(), Problem>) -> Response {
+		let id = validation.challenge;
+		let challenge = self.challenges.get_mut(&id).unwrap();
+		let authorization = self.authorizations.get_mut(&challenge.authorization).unwrap();
+		match result {
+			Ok(()) => {
+				challenge.status = "valid";
+				challenge.validated = Some(OffsetDateTime::now_utc());
+			},
+			Err(problem) => {
+				challenge.status = "invalid";
+				challenge.error = Some(problem);
+			},
+		}
+		// Another challenge of the authorization might have finished in the meantime
+		if authorization.status == "pending" {
+			authorization.status = challenge.status;
+		}
+		self.update_orders();
+		self.challenge_response(id)
+	}
+	fn challenge_response(&self, id :u64) -> Response {
+		let authorization_id = self.challenges[&id].authorization;
+		Response::json(200, self.challenge_json(id))
+			.header("Link", format!("<{}>;rel=\"up\"", self.url("authz", authorization_id)))
+	}
+	fn challenge_json(&self, id :u64) -> Value {
+		let challenge = &self.challenges[&id];
+		let mut value = json!({
+			"type" : challenge.kind,
+			"url" : self.url("chall", id),
+			"status" : challenge.status,
+			"token" : challenge.token,
+		});
+		if let Some(validated) = challenge.validated {
+			value["validated"] = Value::String(format_time(validated));
+		}
+		if let Some(error) = &challenge.error {
+			value["error"] = error.to_json();
+		}
+		value
+	}
+	/// Moves pending orders to ready or invalid, depending on their authorizations
+	fn update_orders(&mut self) {
+		let authorizations = &self.authorizations;
+		for order in self.orders.values_mut().filter(|order| order.status == "pending") {
+			let statuses = order.authorizations.iter()
+				.map(|id| authorizations[id].status)
+				.collect::<Vec<_>>();
+			if statuses.iter().all(|status| *status == "valid") {
+				order.status = "ready";
+			} else if statuses.iter().any(|status| *status == "invalid" || *status == "deactivated") {
+				order.status = "invalid";
+			}
+		}
+	}
+	fn finalize(&mut self, jws :Jws, id :u64) -> Result<Response, Problem> {
+		self.check_order(&jws, id)?;
+		if self.orders[&id].status != "ready" {
+			return Err(Problem::new(403, "orderNotReady", "the order is not ready for finalization"));
+		}
+		let csr = jws.payload.as_ref()
+			.and_then(|payload| payload["csr"].as_str())
+			.ok_or_else(|| Problem::malformed("csr is missing"))?;
+		let csr = CertificateSigningRequest::from_der(&base64url_decode(csr)?)
+			.map_err(|e| Problem::new(400, "badCSR", e.to_string()))?;
+
+		let order = &self.orders[&id];
+		let mut requested = csr.params.subject_alt_names.clone();
+		let mut ordered = order.identifiers.iter()
+			.map(|identifier| match identifier {
+				AcmeIdentifier::Dns(name) => SanType::DnsName(name.clone()),
+				AcmeIdentifier::Ip(addr) => SanType::IpAddress(*addr),
+			})
+			.collect::<Vec<_>>();
+		requested.sort_by_key(|san| format!("{:?}", san));
+		requested.dedup();
+		ordered.sort_by_key(|san| format!("{:?}", san));
+		ordered.dedup();
+		if requested != ordered {
+			return Err(Problem::new(400, "badCSR", "the CSR's names don't match the order's identifiers"));
+		}
+
+		let mut csr = csr;
+		let params = &mut csr.params;
+		params.distinguished_name = DistinguishedName::new();
+		if let Some(AcmeIdentifier::Dns(name)) = order.identifiers.first() {
+			params.distinguished_name.push(DnType::CommonName, name.clone());
+		}
+		params.valid_for(self.options.validity);
+		params.serial_number = Some(random_serial_number()
+			.map_err(|_| Problem::new(500, "serverInternal", "could not generate serial number"))?);
+		params.key_usages = vec![KeyUsagePurpose::DigitalSignature, KeyUsagePurpose::KeyEncipherment];
+		params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth];
+		params.use_authority_key_identifier_extension = true;
+		let der = csr.serialize_der_with_signer(&self.ca)
+			.map_err(|e| Problem::new(500, "serverInternal", e.to_string()))?;
+		let chain = pem::encode_many(&[
+			pem::Pem { tag : "CERTIFICATE".to_string(), contents : der },
+			pem::Pem { tag : "CERTIFICATE".to_string(), contents : self.ca_der.clone() },
+		]);
+
+		let certificate = self.next_id();
+		self.certificates.insert(certificate, (jws.account.unwrap_or_default(), chain));
+		let order = self.orders.get_mut(&id).unwrap();
+		order.status = "valid";
+		order.certificate = Some(certificate);
+		Ok(Response::json(200, self.order_json(id))
+			.header("Location", self.url("order", id)))
+	}
+	fn certificate(&mut self, jws :Jws, id :u64) -> Result<Response, Problem> {
+		match self.certificates.get(&id) {
+			Some((account, chain)) if Some(*account) == jws.account => Ok(Response {
+				status : 200,
+				headers : Vec::new(),
+				content_type : "application/pem-certificate-chain",
+				body : chain.clone().into_bytes(),
+			}),
+			Some(_) => Err(Problem::new(403, "unauthorized", "certificate belongs to another account")),
+			None => Err(Problem::new(404, "malformed", "unknown certificate")),
+		}
+	}
+}
+
+fn validate_challenge(validation :&Validation) -> Result<(), Problem> {
+	let http_port = match validation.method {
+		ChallengeValidation::Automatic => return Ok(()),
+		ChallengeValidation::Loopback { http_port } => http_port,
+	};
+	if validation.kind != "http-01" {
+		return Err(Problem::new(400, "unauthorized",
+			format!("{} challenges can't be validated via loopback", validation.kind)));
+	}
+	let host = match &validation.identifier {
+		AcmeIdentifier::Dns(name) => name.clone(),
+		AcmeIdentifier::Ip(IpAddr::V4(addr)) => addr.to_string(),
+		AcmeIdentifier::Ip(IpAddr::V6(addr)) => format!("[{}]", addr),
+	};
+	let connection_problem = |e :io::Error| Problem::new(400, "connection",
+		format!("could not fetch the key authorization: {}", e));
+	let mut stream = TcpStream::connect(("127.0.0.1", http_port)).map_err(connection_problem)?;
+	stream.set_read_timeout(Some(StdDuration::from_secs(10))).map_err(connection_problem)?;
+	write!(stream, "GET /.well-known/acme-challenge/{} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+		validation.token, host).map_err(connection_problem)?;
+	let mut response = String::new();
+	stream.read_to_string(&mut response).map_err(connection_problem)?;
+	let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+	if !head.starts_with("HTTP/1.1 200") && !head.starts_with("HTTP/1.0 200") {
+		return Err(Problem::new(403, "unauthorized", format!("unexpected response {:?}", head.lines().next())));
+	}
+	if body.trim_end() != validation.key_authorization {
+		return Err(Problem::new(403, "unauthorized", "the key authorization doesn't match"));
+	}
+	Ok(())
+}
+
+fn verify_signature(alg :&str, jwk :&Value, message :&[u8], signature :&[u8]) -> Result<(), Problem> {
+	let bad_key = || Problem::new(400, "badPublicKey", "unsupported or invalid JWK");
+	let component = |name :&str| jwk[name].as_str()
+		.ok_or_else(bad_key)
+		.and_then(|value| base64url_decode(value).map_err(|_| bad_key()));
+	let kty = jwk["kty"].as_str().unwrap_or_default();
+	let crv = jwk["crv"].as_str().unwrap_or_default();
+	let result = match (alg, kty, crv) {
+		("ES256", "EC", "P-256") | ("ES384", "EC", "P-384") => {
+			let verification_alg = if alg == "ES256" {
+				&signature::ECDSA_P256_SHA256_FIXED
+			} else {
+				&signature::ECDSA_P384_SHA384_FIXED
+			};
+			let mut point = vec![0x04];
+			point.extend(component("x")?);
+			point.extend(component("y")?);
+			UnparsedPublicKey::new(verification_alg, point).verify(message, signature)
+		},
+		("EdDSA", "OKP", "Ed25519") => {
+			UnparsedPublicKey::new(&signature::ED25519, component("x")?).verify(message, signature)
+		},
+		("RS256", "RSA", _) => {
+			let key = RsaPublicKeyComponents { n : component("n")?, e : component("e")? };
+			key.verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+		},
+		_ => return Err(Problem::new(400, "badSignatureAlgorithm",
+			format!("unsupported algorithm {:?} for key type {:?}", alg, kty))),
+	};
+	result.map_err(|_| Problem::malformed("invalid JWS signature"))
+}
+
+/// Computes the JWK thumbprint as specified in RFC 7638
+fn jwk_thumbprint(jwk :&Value) -> Result<String, Problem> {
+	let member = |name :&str| jwk[name].as_str()
+		.ok_or_else(|| Problem::new(400, "badPublicKey", format!("JWK member {} is missing", name)));
+	// Only the required members, in lexicographic order and without whitespace
+	let canonical = match member("kty")? {
+		"EC" => format!(r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#, member("crv")?, member("x")?, member("y")?),
+		"OKP" => format!(r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#, member("crv")?, member("x")?),
+		"RSA" => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, member("e")?, member("n")?),
+		kty => return Err(Problem::new(400, "badPublicKey", format!("unsupported key type {:?}", kty))),
+	};
+	Ok(base64url_encode(digest::digest(&digest::SHA256, canonical.as_bytes()).as_ref()))
+}
+
+fn identifier_from_json(value :&Value) -> Result<AcmeIdentifier, Problem> {
+	let identifier_value = value["value"].as_str()
+		.ok_or_else(|| Problem::malformed("identifier value is missing"))?;
+	match value["type"].as_str() {
+		Some("dns") => Ok(AcmeIdentifier::Dns(identifier_value.to_string())),
+		Some("ip") => identifier_value.parse()
+			.map(AcmeIdentifier::Ip)
+			.map_err(|_| Problem::new(400, "rejectedIdentifier", "invalid IP address")),
+		_ => Err(Problem::new(400, "unsupportedIdentifier", "unsupported identifier type")),
+	}
+}
+
+fn identifier_to_json(identifier :&AcmeIdentifier) -> Value {
+	match identifier {
+		AcmeIdentifier::Dns(name) => json!({ "type" : "dns", "value" : name }),
+		AcmeIdentifier::Ip(addr) => json!({ "type" : "ip", "value" : addr.to_string() }),
+	}
+}
+
+fn format_time(time :OffsetDateTime) -> String {
+	time.format(&Rfc3339).unwrap_or_default()
+}
+
+fn random_token() -> String {
+	let mut bytes = [0u8; 16];
+	SystemRandom::new().fill(&mut bytes).expect("could not generate random token");
+	base64url_encode(&bytes)
+}
+
+fn base64url_encode(data :&[u8]) -> String {
+	base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn base64url_decode(data :&str) -> Result<Vec<u8>, Problem> {
+	base64::decode_config(data, base64::URL_SAFE_NO_PAD)
+		.map_err(|_| Problem::malformed("invalid base64url encoding"))
+}
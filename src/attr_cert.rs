@@ -0,0 +1,305 @@
+use time::OffsetDateTime;
+use yasna::{DERWriter, Tag};
+use yasna::models::ObjectIdentifier;
+#[cfg(feature = "pem")]
+use pem::Pem;
+use crate::{dt_to_generalized, write_distinguished_name, write_general_name, Certificate,
+	CertificateParams, CustomExtension, DistinguishedName, RcgenError, SanType};
+use crate::{OID_AT_ROLE, OID_ACA_GROUP, OID_AUTHORITY_KEY_IDENTIFIER};
+
+/// Parameters used for attribute certificate generation
+///
+/// The attribute certificate is signed by, and issued in the name of, the
+/// attribute authority passed to [`AttributeCertificate::serialize_der_with_signer`].
+#[allow(missing_docs)]
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AttributeCertificateParams {
+	/// The entity the attributes are assigned to
+	pub holder :AttributeCertificateHolder,
+	pub serial_number :u64,
+	pub not_before :OffsetDateTime,
+	pub not_after :OffsetDateTime,
+	pub attributes :Vec<CertificateAttribute>,
+	pub custom_extensions :Vec<CustomExtension>,
+	/// If `true`, the 'Authority Key Identifier' extension of the
+	/// attribute authority is added to the attribute certificate
+	///
+	/// Defaults to `true`.
+	pub use_authority_key_identifier_extension :bool,
+}
+
+impl AttributeCertificateParams {
+	/// Creates parameters for an attribute certificate without attributes
+	pub fn new(holder :AttributeCertificateHolder, serial_number :u64,
+			not_before :OffsetDateTime, not_after :OffsetDateTime) -> Self {
+		AttributeCertificateParams {
+			holder,
+			serial_number,
+			not_before,
+			not_after,
+			attributes : Vec::new(),
+			custom_extensions : Vec::new(),
+			use_authority_key_identifier_extension : true,
+		}
+	}
+}
+
+/// The holder of an attribute certificate, as specified in
+/// [RFC 5755 section 4.2.2](https://tools.ietf.org/html/rfc5755#section-4.2.2)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AttributeCertificateHolder {
+	/// The holder's public key certificate, identified by its issuer and serial number
+	///
+	/// The holder has to authenticate with the key of that certificate.
+	BaseCertificateId {
+		/// The subject of the CA that issued the holder's certificate
+		issuer :DistinguishedName,
+		/// The serial number of the holder's certificate
+		serial_number :u64,
+	},
+	/// The holder's name, as found in the subject of its public key certificates
+	EntityName(DistinguishedName),
+}
+
+impl AttributeCertificateHolder {
+	/// Identifies the holder by the issuer and serial number of its DER encoded certificate
+	///
+	/// Fails with [`RcgenError::CouldNotParseCertificate`] for serial numbers
+	/// longer than 64 bits, as they can't be represented.
+	///
+	/// *This function is only available if rcgen is built with the "x509-parser" feature*
+	#[cfg(feature = "x509-parser")]
+	pub fn from_certificate_der(der :&[u8]) -> Result<Self, RcgenError> {
+		let (_remainder, cert) = x509_parser::parse_x509_certificate(der)
+			.or(Err(RcgenError::CouldNotParseCertificate))?;
+		let serial = cert.raw_serial();
+		let first_nonzero = serial.iter().position(|b| *b != 0).unwrap_or(serial.len());
+		let serial = &serial[first_nonzero..];
+		if serial.len() > 8 {
+			return Err(RcgenError::CouldNotParseCertificate);
+		}
+		Ok(AttributeCertificateHolder::BaseCertificateId {
+			issuer : DistinguishedName::from_name(cert.issuer())?,
+			serial_number : serial.iter().fold(0, |serial, b| (serial << 8) | *b as u64),
+		})
+	}
+}
+
+/// An attribute that an attribute certificate assigns to its holder
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub enum CertificateAttribute {
+	/// A role, as specified in [RFC 5755 section 4.4.5](https://tools.ietf.org/html/rfc5755#section-4.4.5)
+	Role {
+		/// The authorities that define the role, can be empty
+		authority :Vec<SanType>,
+		/// The name of the role, usually a URI
+		name :SanType,
+	},
+	/// Group memberships, as specified in
+	/// [RFC 5755 section 4.4.4](https://tools.ietf.org/html/rfc5755#section-4.4.4)
+	Group(Vec<String>),
+	/// Any other attribute, given by its OID and its DER encoded values
+	Custom {
+		/// The OID of the attribute type
+		oid :Vec<u64>,
+		/// The DER encoded values
+		values :Vec<Vec<u8>>,
+	},
+}
+
+/// An attribute certificate, as specified in
+/// [RFC 5755](https://tools.ietf.org/html/rfc5755)
+///
+/// ```
+/// # use rcgen::{AttributeCertificate, AttributeCertificateHolder, AttributeCertificateParams,
+/// # 	CertificateAttribute, Certificate, CertificateParams, DistinguishedName, DnType, SanType};
+/// let authority = Certificate::from_params(CertificateParams::new(Vec::new())).unwrap();
+/// let mut holder = DistinguishedName::new();
+/// holder.push(DnType::CommonName, "Ferris");
+/// let now = time::OffsetDateTime::now_utc();
+/// let mut params = AttributeCertificateParams::new(AttributeCertificateHolder::EntityName(holder),
+/// 	1, now, now + time::Duration::days(1));
+/// params.attributes.push(CertificateAttribute::Role {
+/// 	authority : Vec::new(),
+/// 	name : SanType::URI("urn:crabs:admin".to_string()),
+/// });
+/// let ac = AttributeCertificate::from_params(params).unwrap();
+/// println!("{}", ac.serialize_pem_with_signer(&authority).unwrap());
+/// ```
+pub struct AttributeCertificate {
+	params :AttributeCertificateParams,
+}
+
+impl AttributeCertificate {
+	/// Creates an attribute certificate from the given parameters
+	///
+	/// Fails with [`RcgenError::InvalidAttributeCertificateValidity`]
+	/// if `not_after` lies before `not_before`.
+	pub fn from_params(params :AttributeCertificateParams) -> Result<Self, RcgenError> {
+		if params.not_after < params.not_before {
+			return Err(RcgenError::InvalidAttributeCertificateValidity);
+		}
+		Ok(AttributeCertificate { params })
+	}
+	/// Returns the parameters the attribute certificate was created from
+	pub fn get_params(&self) -> &AttributeCertificateParams {
+		&self.params
+	}
+	/// Serializes the attribute certificate, signed by the given
+	/// attribute authority, in binary DER format
+	pub fn serialize_der_with_signer(&self, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+		yasna::try_construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				let acinfo_serialized = yasna::construct_der(|writer| {
+					self.write_acinfo(writer, ca);
+				});
+				writer.next().write_der(&acinfo_serialized);
+
+				// Write signatureAlgorithm
+				ca.params.alg.write_alg_ident(writer.next());
+
+				// Write signature
+				ca.key_pair.sign(&acinfo_serialized, writer.next())?;
+
+				Ok(())
+			})
+		})
+	}
+	/// Serializes the attribute certificate, signed by the given
+	/// attribute authority, to the ASCII PEM format
+	///
+	/// *This function is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn serialize_pem_with_signer(&self, ca :&Certificate) -> Result<String, RcgenError> {
+		let p = Pem {
+			tag : "ATTRIBUTE CERTIFICATE".to_string(),
+			contents : self.serialize_der_with_signer(ca)?,
+		};
+		Ok(pem::encode(&p))
+	}
+	fn write_acinfo(&self, writer :DERWriter, ca :&Certificate) {
+		writer.write_sequence(|writer| {
+			// Write version, v2 is the only allowed one
+			writer.next().write_u8(1);
+			// Write holder
+			writer.next().write_sequence(|writer| {
+				match &self.params.holder {
+					AttributeCertificateHolder::BaseCertificateId { issuer, serial_number } => {
+						writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+							writer.write_sequence(|writer| {
+								write_directory_names(writer.next(), issuer);
+								writer.next().write_u64(*serial_number);
+							});
+						});
+					},
+					AttributeCertificateHolder::EntityName(name) => {
+						writer.next().write_tagged_implicit(Tag::context(1), |writer| {
+							write_directory_names(writer, name);
+						});
+					},
+				}
+			});
+			// Write issuer, using the v2Form that RFC 5755 requires
+			writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+				writer.write_sequence(|writer| {
+					write_directory_names(writer.next(), &ca.params.distinguished_name);
+				});
+			});
+			// Write signature
+			ca.params.alg.write_alg_ident(writer.next());
+			// Write serialNumber
+			writer.next().write_u64(self.params.serial_number);
+			// Write attrCertValidityPeriod, which always uses GeneralizedTime
+			writer.next().write_sequence(|writer| {
+				writer.next().write_generalized_time(&dt_to_generalized(self.params.not_before));
+				writer.next().write_generalized_time(&dt_to_generalized(self.params.not_after));
+			});
+			// Write attributes
+			writer.next().write_sequence(|writer| {
+				for attribute in self.params.attributes.iter() {
+					write_attribute(writer.next(), attribute);
+				}
+			});
+			// Write extensions
+			if self.params.use_authority_key_identifier_extension || !self.params.custom_extensions.is_empty() {
+				writer.next().write_sequence(|writer| {
+					if self.params.use_authority_key_identifier_extension {
+						CertificateParams::write_extension(writer.next(), OID_AUTHORITY_KEY_IDENTIFIER, false, |writer| {
+							writer.write_sequence(|writer| {
+								writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+									writer.write_bytes(ca.get_key_identifier().as_ref())
+								})
+							});
+						});
+					}
+					for ext in self.params.custom_extensions.iter() {
+						writer.next().write_sequence(|writer| {
+							writer.next().write_oid(&ObjectIdentifier::from_slice(&ext.oid));
+							if ext.critical {
+								writer.next().write_bool(true);
+							}
+							writer.next().write_bytes(&ext.content);
+						});
+					}
+				});
+			}
+		});
+	}
+}
+
+/// Writes GeneralNames holding the name as its only directoryName
+fn write_directory_names(writer :DERWriter, name :&DistinguishedName) {
+	writer.write_sequence(|writer| {
+		writer.next().write_tagged(Tag::context(4), |writer| {
+			write_distinguished_name(writer, name);
+		});
+	});
+}
+
+fn write_attribute(writer :DERWriter, attribute :&CertificateAttribute) {
+	writer.write_sequence(|writer| {
+		match attribute {
+			CertificateAttribute::Role { authority, name } => {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(OID_AT_ROLE));
+				writer.next().write_set(|writer| {
+					writer.next().write_sequence(|writer| {
+						if !authority.is_empty() {
+							writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+								writer.write_sequence(|writer| {
+									for name in authority.iter() {
+										write_general_name(writer.next(), name);
+									}
+								});
+							});
+						}
+						writer.next().write_tagged(Tag::context(1), |writer| {
+							write_general_name(writer, name);
+						});
+					});
+				});
+			},
+			CertificateAttribute::Group(groups) => {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(OID_ACA_GROUP));
+				writer.next().write_set(|writer| {
+					writer.next().write_sequence(|writer| {
+						writer.next().write_sequence(|writer| {
+							for group in groups.iter() {
+								writer.next().write_utf8_string(group);
+							}
+						});
+					});
+				});
+			},
+			CertificateAttribute::Custom { oid, values } => {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(oid));
+				writer.next().write_set(|writer| {
+					for value in values.iter() {
+						writer.next().write_der(value);
+					}
+				});
+			},
+		}
+	});
+}
@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "x509-parser")]
+use std::fs;
+#[cfg(feature = "x509-parser")]
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+#[cfg(feature = "pem")]
+use pem::Pem;
+use crate::{random_serial_number, Certificate, CertificateParams, CertificateRevocationList,
+	CertificateRevocationListParams, DistinguishedName, PublicKey, RcgenError, RevocationReason,
+	RevokedCertParams, SigningPolicy};
+#[cfg(feature = "x509-parser")]
+use crate::{hex, unhex, write_distinguished_name, CertificateSigningRequest};
+
+/// The record of a certificate issued by a [`CertificateAuthority`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IssuedCertificate {
+	/// Serial number of the certificate, unique among the CA's certificates
+	pub serial_number :u64,
+	/// Subject of the certificate
+	pub subject :DistinguishedName,
+	/// Expiry date of the certificate
+	pub not_after :OffsetDateTime,
+	/// The revocation of the certificate, `None` if it is not revoked
+	pub revocation :Option<Revocation>,
+}
+
+impl IssuedCertificate {
+	/// Returns the CRL entry for the certificate if it is revoked
+	pub fn revoked_cert_params(&self) -> Option<RevokedCertParams> {
+		self.revocation.as_ref().map(|revocation| RevokedCertParams {
+			serial_number : self.serial_number,
+			revocation_time : revocation.time,
+			reason_code : revocation.reason,
+		})
+	}
+}
+
+/// The revocation state of a revoked certificate
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Revocation {
+	/// Time at which the certificate was revoked
+	pub time :OffsetDateTime,
+	/// Optional reason of the revocation
+	pub reason :Option<RevocationReason>,
+}
+
+/// Storage for the records of a [`CertificateAuthority`]
+///
+/// rcgen provides [`MemoryCertificateStore`] and, with the "x509-parser"
+/// feature, `FileCertificateStore`.
+pub trait CertificateStore {
+	/// Adds the record of a newly issued certificate
+	///
+	/// Fails with [`RcgenError::DuplicateSerialNumber`] if there is
+	/// already a record with the same serial number.
+	fn insert(&mut self, certificate :IssuedCertificate) -> Result<(), RcgenError>;
+
+	/// Replaces the record with the same serial number
+	///
+	/// Fails with [`RcgenError::UnknownSerialNumber`] if there is no such record.
+	fn update(&mut self, certificate :IssuedCertificate) -> Result<(), RcgenError>;
+
+	/// Returns the record with the given serial number
+	fn get(&self, serial_number :u64) -> Result<Option<IssuedCertificate>, RcgenError>;
+
+	/// Returns all records, ordered by serial number
+	fn certificates(&self) -> Result<Vec<IssuedCertificate>, RcgenError>;
+
+	/// Returns the number for the next CRL, increasing with every call
+	fn next_crl_number(&mut self) -> Result<u64, RcgenError>;
+}
+
+/// A [`CertificateStore`] that keeps the records in memory
+#[derive(Debug, Default, Clone)]
+pub struct MemoryCertificateStore {
+	certificates :BTreeMap<u64, IssuedCertificate>,
+	crl_number :u64,
+}
+
+impl MemoryCertificateStore {
+	/// Creates an empty store
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl CertificateStore for MemoryCertificateStore {
+	fn insert(&mut self, certificate :IssuedCertificate) -> Result<(), RcgenError> {
+		if self.certificates.contains_key(&certificate.serial_number) {
+			return Err(RcgenError::DuplicateSerialNumber(certificate.serial_number));
+		}
+		self.certificates.insert(certificate.serial_number, certificate);
+		Ok(())
+	}
+	fn update(&mut self, certificate :IssuedCertificate) -> Result<(), RcgenError> {
+		match self.certificates.get_mut(&certificate.serial_number) {
+			Some(record) => *record = certificate,
+			None => return Err(RcgenError::UnknownSerialNumber(certificate.serial_number)),
+		}
+		Ok(())
+	}
+	fn get(&self, serial_number :u64) -> Result<Option<IssuedCertificate>, RcgenError> {
+		Ok(self.certificates.get(&serial_number).cloned())
+	}
+	fn certificates(&self) -> Result<Vec<IssuedCertificate>, RcgenError> {
+		Ok(self.certificates.values().cloned().collect())
+	}
+	fn next_crl_number(&mut self) -> Result<u64, RcgenError> {
+		self.crl_number += 1;
+		Ok(self.crl_number)
+	}
+}
+
+/// A [`CertificateStore`] that keeps the records in a text file
+///
+/// The file is read when the store is opened, and rewritten after each change.
+/// It has one line per certificate, with the hexadecimal serial number, the
+/// expiry as UNIX timestamp, the revocation time as UNIX timestamp and the
+/// revocation reason code, each `-` if the certificate is not revoked,
+/// and the hexadecimal DER encoded subject.
+///
+/// *This type is only available if rcgen is built with the "x509-parser" feature*
+#[cfg(feature = "x509-parser")]
+#[derive(Debug)]
+pub struct FileCertificateStore {
+	path :PathBuf,
+	store :MemoryCertificateStore,
+}
+
+#[cfg(feature = "x509-parser")]
+impl FileCertificateStore {
+	/// Opens the store in the given file, which is created on the first change
+	/// if it doesn't exist
+	///
+	/// Fails with [`RcgenError::CertificateStoreError`] if the file can't be read or parsed.
+	pub fn open(path :impl AsRef<Path>) -> Result<Self, RcgenError> {
+		let path = path.as_ref().to_path_buf();
+		let mut store = MemoryCertificateStore::new();
+		match fs::read_to_string(&path) {
+			Ok(contents) => {
+				for (i, line) in contents.lines().enumerate() {
+					let invalid = || RcgenError::CertificateStoreError(
+						format!("{}:{}: invalid record", path.display(), i + 1));
+					if let Some(crl_number) = line.strip_prefix("crl_number ") {
+						store.crl_number = crl_number.parse().map_err(|_| invalid())?;
+					} else if !line.is_empty() {
+						store.insert(parse_record(line).ok_or_else(invalid)?)?;
+					}
+				}
+			},
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+			Err(e) => return Err(RcgenError::CertificateStoreError(
+				format!("{}: {}", path.display(), e))),
+		}
+		Ok(FileCertificateStore { path, store })
+	}
+	fn save(&self) -> Result<(), RcgenError> {
+		let mut contents = format!("crl_number {}\n", self.store.crl_number);
+		for certificate in self.store.certificates.values() {
+			let (time, reason) = match &certificate.revocation {
+				Some(revocation) => (revocation.time.unix_timestamp().to_string(),
+					revocation.reason.map(|reason| (reason as u8).to_string()).unwrap_or_else(|| "-".to_string())),
+				None => ("-".to_string(), "-".to_string()),
+			};
+			let subject = yasna::construct_der(|writer| {
+				write_distinguished_name(writer, &certificate.subject);
+			});
+			contents += &format!("{:x} {} {} {} {}\n", certificate.serial_number,
+				certificate.not_after.unix_timestamp(), time, reason, hex(&subject, ""));
+		}
+		// Replace the file atomically, so that it stays intact if writing fails
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, contents)
+			.and_then(|()| fs::rename(&tmp_path, &self.path))
+			.map_err(|e| RcgenError::CertificateStoreError(format!("{}: {}", self.path.display(), e)))
+	}
+}
+
+#[cfg(feature = "x509-parser")]
+impl CertificateStore for FileCertificateStore {
+	fn insert(&mut self, certificate :IssuedCertificate) -> Result<(), RcgenError> {
+		self.store.insert(certificate)?;
+		self.save()
+	}
+	fn update(&mut self, certificate :IssuedCertificate) -> Result<(), RcgenError> {
+		self.store.update(certificate)?;
+		self.save()
+	}
+	fn get(&self, serial_number :u64) -> Result<Option<IssuedCertificate>, RcgenError> {
+		self.store.get(serial_number)
+	}
+	fn certificates(&self) -> Result<Vec<IssuedCertificate>, RcgenError> {
+		self.store.certificates()
+	}
+	fn next_crl_number(&mut self) -> Result<u64, RcgenError> {
+		let crl_number = self.store.next_crl_number()?;
+		self.save()?;
+		Ok(crl_number)
+	}
+}
+
+#[cfg(feature = "x509-parser")]
+fn parse_record(line :&str) -> Option<IssuedCertificate> {
+	use x509_parser::prelude::FromDer;
+
+	let fields = line.split(' ').collect::<Vec<_>>();
+	if fields.len() != 5 {
+		return None;
+	}
+	let revocation = match (fields[2], fields[3]) {
+		("-", "-") => None,
+		(time, reason) => Some(Revocation {
+			time : OffsetDateTime::from_unix_timestamp(time.parse().ok()?).ok()?,
+			reason : match reason {
+				"-" => None,
+				code => Some(RevocationReason::from_code(code.parse().ok()?)?),
+			},
+		}),
+	};
+	let subject = unhex(fields[4])?;
+	let (_, subject) = x509_parser::x509::X509Name::from_der(&subject).ok()?;
+	Some(IssuedCertificate {
+		serial_number : u64::from_str_radix(fields[0], 16).ok()?,
+		subject : DistinguishedName::from_name(&subject).ok()?,
+		not_after : OffsetDateTime::from_unix_timestamp(fields[1].parse().ok()?).ok()?,
+		revocation,
+	})
+}
+
+/// A CA that keeps a record of the certificates it issues
+///
+/// Serial numbers are assigned randomly if the parameters don't specify one,
+/// and are unique among the certificates recorded in the store. Revoked
+/// certificates are included in the CRLs of the CA until they expire.
+/// CSRs are checked by the [signing policy](CertificateAuthority::set_signing_policy), if one is set.
+///
+/// ```
+/// # use rcgen::{Certificate, CertificateAuthority, CertificateParams, MemoryCertificateStore};
+/// let ca = Certificate::from_params(CertificateParams::root_ca("Crab CA")).unwrap();
+/// let mut ca = CertificateAuthority::new(ca, Box::new(MemoryCertificateStore::new()));
+/// let (_cert, _cert_der) = ca.issue(CertificateParams::new(vec!["crabs.crabs".to_string()])).unwrap();
+///
+/// let serial_number = ca.store().certificates().unwrap()[0].serial_number;
+/// let now = time::OffsetDateTime::now_utc();
+/// ca.revoke(serial_number, now, None).unwrap();
+/// let crl = ca.serialize_crl_der(now, now + time::Duration::days(7)).unwrap();
+/// ```
+pub struct CertificateAuthority {
+	certificate :Certificate,
+	store :Box<dyn CertificateStore + Send + Sync>,
+	signing_policy :Option<Box<dyn SigningPolicy + Send + Sync>>,
+}
+
+impl CertificateAuthority {
+	/// Creates a CA issuing certificates with the given CA certificate,
+	/// recording them in the store
+	pub fn new(certificate :Certificate, store :Box<dyn CertificateStore + Send + Sync>) -> Self {
+		CertificateAuthority {
+			certificate,
+			store,
+			signing_policy : None,
+		}
+	}
+	/// Sets the policy that the parameters of CSRs have to pass
+	/// before [`sign_request`](Self::sign_request) signs them
+	pub fn set_signing_policy(&mut self, policy :Box<dyn SigningPolicy + Send + Sync>) {
+		self.signing_policy = Some(policy);
+	}
+	/// Returns the CA certificate
+	pub fn certificate(&self) -> &Certificate {
+		&self.certificate
+	}
+	/// Returns the store with the records of the issued certificates
+	pub fn store(&self) -> &(dyn CertificateStore + Send + Sync) {
+		self.store.as_ref()
+	}
+	/// Creates a certificate with a new key pair from the parameters,
+	/// and issues it
+	///
+	/// Returns the certificate and its DER encoding, signed by the CA.
+	/// Fails with [`RcgenError::DuplicateSerialNumber`] if the serial number
+	/// in the parameters was already used.
+	pub fn issue(&mut self, mut params :CertificateParams) -> Result<(Certificate, Vec<u8>), RcgenError> {
+		self.assign_serial_number(&mut params)?;
+		let certificate = Certificate::from_params(params)?;
+		let der = certificate.serialize_der_with_signer(&self.certificate)?;
+		self.record(&certificate.params)?;
+		Ok((certificate, der))
+	}
+	/// Issues a certificate for the given public key, in binary DER format
+	///
+	/// See [`CertificateParams::serialize_der_with_signer`] for the handling of the parameters.
+	pub fn issue_for_public_key(&mut self, mut params :CertificateParams, public_key :&PublicKey) -> Result<Vec<u8>, RcgenError> {
+		self.assign_serial_number(&mut params)?;
+		let der = params.serialize_der_with_signer(public_key, &self.certificate)?;
+		self.record(&params)?;
+		Ok(der)
+	}
+	/// Issues the certificate requested by the CSR, in binary DER format
+	///
+	/// The signing policy is applied before the serial number is assigned,
+	/// so serial numbers set by the policy are checked for uniqueness as well.
+	///
+	/// *This function is only available if rcgen is built with the "x509-parser" feature*
+	#[cfg(feature = "x509-parser")]
+	pub fn sign_request(&mut self, mut csr :CertificateSigningRequest) -> Result<Vec<u8>, RcgenError> {
+		if let Some(policy) = &self.signing_policy {
+			csr.apply_policy(policy.as_ref())?;
+		}
+		self.assign_serial_number(&mut csr.params)?;
+		let der = csr.serialize_der_with_signer(&self.certificate)?;
+		self.record(&csr.params)?;
+		Ok(der)
+	}
+	/// Marks the certificate with the given serial number as revoked
+	///
+	/// Revoking a certificate again replaces the revocation time and reason.
+	/// Fails with [`RcgenError::UnknownSerialNumber`] if the CA didn't issue
+	/// a certificate with that serial number.
+	pub fn revoke(&mut self, serial_number :u64, time :OffsetDateTime, reason :Option<RevocationReason>) -> Result<(), RcgenError> {
+		let mut certificate = self.store.get(serial_number)?
+			.ok_or(RcgenError::UnknownSerialNumber(serial_number))?;
+		certificate.revocation = Some(Revocation { time, reason });
+		self.store.update(certificate)
+	}
+	/// Creates the CRL parameters for the given validity, using the next CRL
+	/// number of the store
+	///
+	/// The CRL lists all revoked certificates that haven't expired at `this_update`.
+	pub fn crl_params(&mut self, this_update :OffsetDateTime, next_update :OffsetDateTime) -> Result<CertificateRevocationListParams, RcgenError> {
+		let mut params = CertificateRevocationListParams::new(this_update, next_update,
+			self.store.next_crl_number()?);
+		params.revoked_certs = self.store.certificates()?.iter()
+			.filter(|certificate| certificate.not_after >= this_update)
+			.filter_map(|certificate| certificate.revoked_cert_params())
+			.collect();
+		Ok(params)
+	}
+	/// Serializes a new CRL of the CA in binary DER format
+	///
+	/// See [`crl_params`](Self::crl_params) for details.
+	pub fn serialize_crl_der(&mut self, this_update :OffsetDateTime, next_update :OffsetDateTime) -> Result<Vec<u8>, RcgenError> {
+		let params = self.crl_params(this_update, next_update)?;
+		CertificateRevocationList::from_params(params)?.serialize_der_with_signer(&self.certificate)
+	}
+	/// Serializes a new CRL of the CA to the ASCII PEM format
+	///
+	/// See [`crl_params`](Self::crl_params) for details.
+	///
+	/// *This function is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn serialize_crl_pem(&mut self, this_update :OffsetDateTime, next_update :OffsetDateTime) -> Result<String, RcgenError> {
+		let p = Pem {
+			tag : "X509 CRL".to_string(),
+			contents : self.serialize_crl_der(this_update, next_update)?,
+		};
+		Ok(pem::encode(&p))
+	}
+	fn assign_serial_number(&self, params :&mut CertificateParams) -> Result<(), RcgenError> {
+		let serial_number = match params.serial_number {
+			Some(serial_number) if self.store.get(serial_number)?.is_some() => {
+				return Err(RcgenError::DuplicateSerialNumber(serial_number));
+			},
+			Some(serial_number) => serial_number,
+			None => loop {
+				let serial_number = random_serial_number()?;
+				if self.store.get(serial_number)?.is_none() {
+					break serial_number;
+				}
+			},
+		};
+		params.serial_number = Some(serial_number);
+		Ok(())
+	}
+	fn record(&mut self, params :&CertificateParams) -> Result<(), RcgenError> {
+		self.store.insert(IssuedCertificate {
+			// Always set by assign_serial_number
+			serial_number : params.serial_number.unwrap_or_default(),
+			subject : params.distinguished_name.clone(),
+			not_after : params.not_after,
+			revocation : None,
+		})
+	}
+}
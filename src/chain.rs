@@ -0,0 +1,269 @@
+use time::OffsetDateTime;
+#[cfg(feature = "pem")]
+use pem::Pem;
+use crate::{BasicConstraints, Certificate, CertificateParams, DnType,
+	ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, RcgenError,
+	SignatureAlgorithm, PKCS_ECDSA_P256_SHA256};
+
+/// Builder for multi-level test PKIs
+///
+/// Creates a root CA, an arbitrary number of intermediate CAs and a leaf
+/// certificate, each one signed by the level above it. The CA certificates
+/// get `keyCertSign` and `cRLSign` key usages and a path length constraint
+/// matching the number of CAs below them, and every non-root certificate
+/// carries an authority key identifier.
+///
+/// ```
+/// # use rcgen::PkiBuilder;
+/// let chain = PkiBuilder::new()
+/// 	.intermediates(2)
+/// 	.leaf_subject_alt_names(vec!["localhost".to_string()])
+/// 	.build()
+/// 	.unwrap();
+/// assert_eq!(chain.intermediates().len(), 2);
+/// // Leaf and intermediates, ready to be served by a TLS server
+/// assert_eq!(chain.chain_der().len(), 3);
+/// ```
+pub struct PkiBuilder {
+	alg :&'static SignatureAlgorithm,
+	intermediates :usize,
+	not_before :Option<OffsetDateTime>,
+	not_after :Option<OffsetDateTime>,
+	leaf_subject_alt_names :Vec<String>,
+	leaf_params :Option<CertificateParams>,
+}
+
+impl PkiBuilder {
+	/// Creates a builder for a root CA, a single intermediate CA and a leaf certificate
+	///
+	/// All keys use ECDSA P-256 with SHA-256 by default.
+	pub fn new() -> Self {
+		PkiBuilder {
+			alg : &PKCS_ECDSA_P256_SHA256,
+			intermediates : 1,
+			not_before : None,
+			not_after : None,
+			leaf_subject_alt_names : Vec::new(),
+			leaf_params : None,
+		}
+	}
+	/// Sets the signature algorithm used for the keys of all CA levels and the default leaf
+	pub fn alg(mut self, alg :&'static SignatureAlgorithm) -> Self {
+		self.alg = alg;
+		self
+	}
+	/// Sets the number of intermediate CAs between the root and the leaf
+	///
+	/// Zero means that the leaf is issued directly by the root.
+	pub fn intermediates(mut self, count :usize) -> Self {
+		self.intermediates = count;
+		self
+	}
+	/// Sets the validity period of all certificates in the chain
+	///
+	/// Defaults to the validity period of [`CertificateParams::default`].
+	pub fn validity(mut self, not_before :OffsetDateTime, not_after :OffsetDateTime) -> Self {
+		self.not_before = Some(not_before);
+		self.not_after = Some(not_after);
+		self
+	}
+	/// Sets the DNS names the default leaf certificate is valid for
+	///
+	/// The first name is also used as the leaf's common name.
+	/// Ignored if [`leaf_params`](Self::leaf_params) is used.
+	pub fn leaf_subject_alt_names(mut self, subject_alt_names :impl Into<Vec<String>>) -> Self {
+		self.leaf_subject_alt_names = subject_alt_names.into();
+		self
+	}
+	/// Uses the given parameters for the leaf certificate instead of the defaults
+	///
+	/// The parameters are taken as they are, except that the authority key
+	/// identifier extension is always enabled, and the validity period is
+	/// overridden if [`validity`](Self::validity) was called.
+	pub fn leaf_params(mut self, params :CertificateParams) -> Self {
+		self.leaf_params = Some(params);
+		self
+	}
+	/// Generates all keys and certificates of the chain
+	pub fn build(mut self) -> Result<CertificateChain, RcgenError> {
+		let root = self.ca_certificate("rcgen root CA", self.intermediates)?;
+		let root = ChainCertificate {
+			der : root.serialize_der()?,
+			certificate : root,
+		};
+
+		let mut intermediates :Vec<ChainCertificate> = Vec::with_capacity(self.intermediates);
+		for level in 1..=self.intermediates {
+			let name = format!("rcgen intermediate CA {}", level);
+			let certificate = self.ca_certificate(&name, self.intermediates - level)?;
+			let issuer = intermediates.last().unwrap_or(&root);
+			intermediates.push(ChainCertificate {
+				der : certificate.serialize_der_with_signer(&issuer.certificate)?,
+				certificate,
+			});
+		}
+
+		let leaf_params = self.leaf_params.take();
+		let leaf = Certificate::from_params(self.leaf_certificate_params(leaf_params))?;
+		let issuer = intermediates.last().unwrap_or(&root);
+		let leaf = ChainCertificate {
+			der : leaf.serialize_der_with_signer(&issuer.certificate)?,
+			certificate : leaf,
+		};
+
+		Ok(CertificateChain {
+			root,
+			intermediates,
+			leaf,
+		})
+	}
+	fn ca_certificate(&self, common_name :&str, cas_below :usize) -> Result<Certificate, RcgenError> {
+		let mut params = self.base_params();
+		params.distinguished_name.push(DnType::CommonName, common_name);
+		let path_len = cas_below.min(u8::MAX as usize) as u8;
+		params.is_ca = IsCa::Ca(BasicConstraints::Constrained(path_len));
+		params.key_usages = vec![
+			KeyUsagePurpose::DigitalSignature,
+			KeyUsagePurpose::KeyCertSign,
+			KeyUsagePurpose::CrlSign,
+		];
+		params.key_pair = Some(KeyPair::generate(self.alg)?);
+		Certificate::from_params(params)
+	}
+	fn leaf_certificate_params(&self, leaf_params :Option<CertificateParams>) -> CertificateParams {
+		let mut params = if let Some(mut params) = leaf_params {
+			if let Some(not_before) = self.not_before {
+				params.not_before = not_before;
+			}
+			if let Some(not_after) = self.not_after {
+				params.not_after = not_after;
+			}
+			params
+		} else {
+			let mut params = self.base_params();
+			params.subject_alt_names = CertificateParams::new(self.leaf_subject_alt_names.clone())
+				.subject_alt_names;
+			let common_name = self.leaf_subject_alt_names.first()
+				.map(|name| name.as_str())
+				.unwrap_or("rcgen leaf certificate");
+			params.distinguished_name.push(DnType::CommonName, common_name);
+			params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+			params.extended_key_usages = vec![
+				ExtendedKeyUsagePurpose::ServerAuth,
+				ExtendedKeyUsagePurpose::ClientAuth,
+			];
+			params
+		};
+		params.use_authority_key_identifier_extension = true;
+		params
+	}
+	fn base_params(&self) -> CertificateParams {
+		let mut params = CertificateParams::default();
+		params.alg = self.alg;
+		params.distinguished_name = crate::DistinguishedName::new();
+		params.use_authority_key_identifier_extension = true;
+		if let Some(not_before) = self.not_before {
+			params.not_before = not_before;
+		}
+		if let Some(not_after) = self.not_after {
+			params.not_after = not_after;
+		}
+		params
+	}
+}
+
+impl Default for PkiBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// A certificate of a [`CertificateChain`] together with its signed DER encoding
+pub struct ChainCertificate {
+	pub(crate) certificate :Certificate,
+	pub(crate) der :Vec<u8>,
+}
+
+impl ChainCertificate {
+	/// Returns the certificate, e.g. to sign further certificates with it
+	pub fn certificate(&self) -> &Certificate {
+		&self.certificate
+	}
+	/// Returns the certificate's key pair
+	pub fn key_pair(&self) -> &KeyPair {
+		self.certificate.get_key_pair()
+	}
+	/// Returns the certificate in binary DER format, as signed by its issuer
+	pub fn der(&self) -> &[u8] {
+		&self.der
+	}
+	/// Returns the certificate in the ASCII PEM format, as signed by its issuer
+	///
+	/// *This function is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn pem(&self) -> String {
+		pem::encode(&certificate_pem(&self.der))
+	}
+}
+
+/// A root CA, its intermediate CAs and a leaf certificate, as created by [`PkiBuilder`]
+pub struct CertificateChain {
+	root :ChainCertificate,
+	intermediates :Vec<ChainCertificate>,
+	leaf :ChainCertificate,
+}
+
+impl CertificateChain {
+	/// Returns the self signed root CA, i.e. the trust anchor of the chain
+	pub fn root(&self) -> &ChainCertificate {
+		&self.root
+	}
+	/// Returns the intermediate CAs, starting with the one issued by the root
+	pub fn intermediates(&self) -> &[ChainCertificate] {
+		&self.intermediates
+	}
+	/// Returns the leaf certificate
+	pub fn leaf(&self) -> &ChainCertificate {
+		&self.leaf
+	}
+	/// Returns all certificates, starting with the leaf and ending with the root
+	pub fn certificates(&self) -> Vec<&ChainCertificate> {
+		let mut certificates = vec![&self.leaf];
+		certificates.extend(self.intermediates.iter().rev());
+		certificates.push(&self.root);
+		certificates
+	}
+	/// Returns the DER encodings of the leaf followed by the intermediate CAs
+	///
+	/// This is the chain a server is expected to present, e.g. during a TLS
+	/// handshake. The root is omitted as clients already need to trust it.
+	pub fn chain_der(&self) -> Vec<Vec<u8>> {
+		let mut certificates = self.certificates();
+		certificates.pop();
+		certificates.into_iter()
+			.map(|certificate| certificate.der.clone())
+			.collect()
+	}
+	/// Returns the leaf followed by the intermediate CAs as a PEM bundle
+	///
+	/// This is the format most servers expect for their "full chain"
+	/// certificate file. Like with [`chain_der`](Self::chain_der), the root is omitted.
+	///
+	/// *This function is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn full_chain_pem(&self) -> String {
+		let pems = self.chain_der()
+			.iter()
+			.map(|der| certificate_pem(der))
+			.collect::<Vec<_>>();
+		pem::encode_many(&pems)
+	}
+}
+
+#[cfg(feature = "pem")]
+fn certificate_pem(der :&[u8]) -> Pem {
+	Pem {
+		tag : "CERTIFICATE".to_string(),
+		contents : der.to_vec(),
+	}
+}
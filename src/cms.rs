@@ -0,0 +1,252 @@
+//! CMS SignedData as per [RFC 5652](https://tools.ietf.org/html/rfc5652)
+
+use ring::digest;
+use time::OffsetDateTime;
+use yasna::{DERWriter, Tag};
+use yasna::models::ObjectIdentifier;
+#[cfg(feature = "pem")]
+use pem::Pem;
+use crate::{write_dt_utc_or_generalized, KeyPair, RcgenError, SignatureAlgorithm,
+	PKCS_ECDSA_P384_SHA384, PKCS_ED25519, PKCS_RSA_SHA384, PKCS_RSA_SHA512};
+
+// Content types in RFC 5652 section 4 and 5.1
+const OID_DATA :&[u64] = &[1, 2, 840, 113549, 1, 7, 1];
+const OID_SIGNED_DATA :&[u64] = &[1, 2, 840, 113549, 1, 7, 2];
+
+// Signed attributes in RFC 5652 section 11
+const OID_CONTENT_TYPE :&[u64] = &[1, 2, 840, 113549, 1, 9, 3];
+const OID_MESSAGE_DIGEST :&[u64] = &[1, 2, 840, 113549, 1, 9, 4];
+const OID_SIGNING_TIME :&[u64] = &[1, 2, 840, 113549, 1, 9, 5];
+
+// Digest algorithms in RFC 5754 section 2
+pub(crate) const OID_SHA256 :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+pub(crate) const OID_SHA384 :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 2];
+pub(crate) const OID_SHA512 :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 3];
+
+/// Parameters for the creation of CMS SignedData
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct CmsSignedDataParams {
+	/// If `true`, the content is left out of the SignedData,
+	/// creating a detached signature
+	pub detached :bool,
+	/// DER encoded certificates to include in addition to the signer's
+	/// certificate, usually its issuer chain
+	pub ca_certificates :Vec<Vec<u8>>,
+	/// Value of the signing time attribute, the current time if `None`
+	pub signing_time :Option<OffsetDateTime>,
+}
+
+impl KeyPair {
+	/// Signs the content, creating a CMS SignedData structure in DER format
+	///
+	/// `certificate_der` is the DER encoded certificate belonging to the key pair,
+	/// for example the output of [`Certificate::serialize_der_with_signer`](crate::Certificate::serialize_der_with_signer).
+	/// The signer is identified by the issuer and serial number of that certificate,
+	/// and the signature covers the content type, message digest and signing
+	/// time attributes. The digest algorithm is chosen to match the key pair's
+	/// signature algorithm, with SHA-512 for Ed25519 as specified in RFC 8419.
+	///
+	/// The output can be verified with `openssl cms -verify -inform DER`.
+	///
+	/// ```
+	/// # use rcgen::CmsSignedDataParams;
+	/// let cert = rcgen::generate_simple_self_signed(vec!["crabs.crabs".to_string()]).unwrap();
+	/// let cert_der = cert.serialize_der().unwrap();
+	/// let signed = cert.get_key_pair()
+	/// 	.sign_cms_der(&cert_der, b"Hello crabs", &CmsSignedDataParams::default())
+	/// 	.unwrap();
+	/// ```
+	pub fn sign_cms_der(&self, certificate_der :&[u8], content :&[u8], params :&CmsSignedDataParams) -> Result<Vec<u8>, RcgenError> {
+		let signing_time = params.signing_time.unwrap_or_else(OffsetDateTime::now_utc);
+		let signing_time = yasna::construct_der(|writer| {
+			write_dt_utc_or_generalized(writer, signing_time);
+		});
+		let mut certificates = vec![certificate_der];
+		certificates.extend(params.ca_certificates.iter().map(|cert| cert.as_slice()));
+		sign_signed_data(self, certificate_der, OID_DATA, content, params.detached,
+			&certificates, &[(OID_SIGNING_TIME, signing_time)])
+	}
+}
+
+/// Creates a ContentInfo with SignedData over the content, signed by a single signer
+///
+/// The signed attributes consist of the content type and message digest
+/// attributes and the given attributes with their DER encoded values.
+/// Only the given certificates are included, which may be none.
+pub(crate) fn sign_signed_data(key_pair :&KeyPair, certificate_der :&[u8], content_type :&[u64],
+		content :&[u8], detached :bool, certificates :&[&[u8]],
+		attributes :&[(&[u64], Vec<u8>)]) -> Result<Vec<u8>, RcgenError> {
+	let (issuer, serial) = parse_issuer_and_serial(certificate_der)
+		.or(Err(RcgenError::CouldNotParseCertificate))?;
+	let (digest_alg, digest_oid) = digest_algorithm(key_pair.alg);
+	let message_digest = digest::digest(digest_alg, content);
+
+	// The signature is calculated over the DER encoding of the SET OF
+	// attributes, not over the implicitly tagged version in SignerInfo
+	let signed_attrs = yasna::construct_der(|writer| {
+		writer.write_set_of(|writer| {
+			write_attribute(writer.next(), OID_CONTENT_TYPE, |writer| {
+				writer.write_oid(&ObjectIdentifier::from_slice(content_type));
+			});
+			write_attribute(writer.next(), OID_MESSAGE_DIGEST, |writer| {
+				writer.write_bytes(message_digest.as_ref());
+			});
+			for (oid, value) in attributes {
+				write_attribute(writer.next(), oid, |writer| {
+					writer.write_der(value);
+				});
+			}
+		});
+	});
+	let signature = key_pair.sign_raw(&signed_attrs)?;
+	let mut tagged_signed_attrs = signed_attrs;
+	tagged_signed_attrs[0] = 0xa0;
+
+	Ok(write_signed_data(|writer| {
+		writer.write_sequence(|writer| {
+			// Version 3 is required for content types other than id-data
+			writer.next().write_u8(if content_type == OID_DATA { 1 } else { 3 });
+			writer.next().write_set(|writer| {
+				write_digest_alg_ident(writer.next(), digest_oid);
+			});
+			// encapContentInfo
+			writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(content_type));
+				if !detached {
+					writer.next().write_tagged(Tag::context(0), |writer| {
+						writer.write_bytes(content);
+					});
+				}
+			});
+			if !certificates.is_empty() {
+				write_certificate_set(writer.next(), certificates);
+			}
+			// signerInfos
+			writer.next().write_set(|writer| {
+				writer.next().write_sequence(|writer| {
+					// Version 1, as the signer is identified by issuer and serial number
+					writer.next().write_u8(1);
+					writer.next().write_sequence(|writer| {
+						writer.next().write_der(&issuer);
+						writer.next().write_der(&serial);
+					});
+					write_digest_alg_ident(writer.next(), digest_oid);
+					writer.next().write_der(&tagged_signed_attrs);
+					key_pair.alg.write_alg_ident(writer.next());
+					writer.next().write_bytes(&signature);
+				});
+			});
+		});
+	}))
+}
+
+/// Creates a degenerate certificates-only PKCS#7 SignedData structure in DER format,
+/// also known as a `.p7b` file, for distributing certificate chains
+///
+/// The structure has no content and no signers, as specified in
+/// [RFC 5652 section 5.2](https://tools.ietf.org/html/rfc5652#section-5.2).
+///
+/// ```
+/// let chain = rcgen::PkiBuilder::new().build().unwrap();
+/// let certificates = chain.certificates().iter().map(|cert| cert.der()).collect::<Vec<_>>();
+/// let p7b = rcgen::serialize_certs_only_pkcs7_der(&certificates);
+/// ```
+pub fn serialize_certs_only_pkcs7_der(certificates :&[&[u8]]) -> Vec<u8> {
+	write_signed_data(|writer| {
+		writer.write_sequence(|writer| {
+			writer.next().write_u8(1);
+			// No digest algorithms
+			writer.next().write_set(|_writer| ());
+			writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(OID_DATA));
+			});
+			write_certificate_set(writer.next(), certificates);
+			// No signer infos
+			writer.next().write_set(|_writer| ());
+		});
+	})
+}
+
+/// Creates a degenerate certificates-only PKCS#7 SignedData structure in the ASCII PEM format
+///
+/// See [`serialize_certs_only_pkcs7_der`] for more details.
+/// The output can be read by `openssl pkcs7 -print_certs`.
+///
+/// *This function is only available if rcgen is built with the "pem" feature*
+#[cfg(feature = "pem")]
+pub fn serialize_certs_only_pkcs7_pem(certificates :&[&[u8]]) -> String {
+	let p = Pem {
+		tag : "PKCS7".to_string(),
+		contents : serialize_certs_only_pkcs7_der(certificates),
+	};
+	pem::encode(&p)
+}
+
+/// Wraps the SignedData written by the callback into a ContentInfo
+fn write_signed_data(signed_data :impl FnOnce(DERWriter)) -> Vec<u8> {
+	yasna::construct_der(|writer| {
+		writer.write_sequence(|writer| {
+			writer.next().write_oid(&ObjectIdentifier::from_slice(OID_SIGNED_DATA));
+			writer.next().write_tagged(Tag::context(0), signed_data);
+		});
+	})
+}
+
+fn write_certificate_set(writer :DERWriter, certificates :&[&[u8]]) {
+	writer.write_tagged_implicit(Tag::context(0), |writer| {
+		writer.write_set_of(|writer| {
+			for cert in certificates {
+				writer.next().write_der(cert);
+			}
+		});
+	});
+}
+
+fn write_attribute(writer :DERWriter, oid :&[u64], value :impl FnOnce(DERWriter)) {
+	writer.write_sequence(|writer| {
+		writer.next().write_oid(&ObjectIdentifier::from_slice(oid));
+		writer.next().write_set(|writer| {
+			value(writer.next());
+		});
+	});
+}
+
+fn write_digest_alg_ident(writer :DERWriter, oid :&[u64]) {
+	// The parameters are absent for the SHA-2 family
+	writer.write_sequence(|writer| {
+		writer.next().write_oid(&ObjectIdentifier::from_slice(oid));
+	});
+}
+
+fn digest_algorithm(alg :&SignatureAlgorithm) -> (&'static digest::Algorithm, &'static [u64]) {
+	if alg == &PKCS_ED25519 || alg == &PKCS_RSA_SHA512 {
+		(&digest::SHA512, OID_SHA512)
+	} else if alg == &PKCS_ECDSA_P384_SHA384 || alg == &PKCS_RSA_SHA384 {
+		(&digest::SHA384, OID_SHA384)
+	} else {
+		(&digest::SHA256, OID_SHA256)
+	}
+}
+
+/// Obtains the DER encoded issuer name and serial number of the certificate
+fn parse_issuer_and_serial(certificate_der :&[u8]) -> yasna::ASN1Result<(Vec<u8>, Vec<u8>)> {
+	yasna::parse_der(certificate_der, |reader| {
+		reader.read_sequence(|reader| {
+			let issuer_and_serial = reader.next().read_sequence(|reader| {
+				reader.read_optional(|reader| {
+					reader.read_tagged(Tag::context(0), |reader| reader.read_u8())
+				})?;
+				let serial = reader.next().read_der()?;
+				// signature
+				reader.next().read_der()?;
+				let issuer = reader.next().read_der()?;
+				// validity, subject, subjectPublicKeyInfo, and the optional fields
+				while reader.read_optional(|reader| reader.read_der())?.is_some() {}
+				Ok((issuer, serial))
+			})?;
+			reader.next().read_der()?;
+			reader.next().read_der()?;
+			Ok(issuer_and_serial)
+		})
+	})
+}
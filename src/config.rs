@@ -0,0 +1,252 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use crate::{Certificate, CertificateParams, ChainCertificate, CidrSubnet,
+	DistinguishedName, DnType, DnValue, RcgenError};
+
+/// Declarative description of a PKI, e.g. loaded from a configuration file
+///
+/// The certificates are created in the order they are listed in. Each one is
+/// either self signed, or signed by the certificate whose name is given as
+/// its `issuer`, which has to be listed before it. Any serde format can be
+/// used, in TOML a root CA with a server certificate looks like this:
+///
+/// ```toml
+/// [[certificates]]
+/// name = "root"
+/// is_ca = { Ca = "Unconstrained" }
+/// key_usages = ["KeyCertSign", "CrlSign"]
+/// distinguished_name = { CommonName = "Crab root CA" }
+///
+/// [[certificates]]
+/// name = "server"
+/// issuer = "root"
+/// alg = "PKCS_ED25519"
+/// not_after = "2030-01-01T00:00:00Z"
+/// subject_alt_names = [{ DnsName = "crabs.crabs" }, { IpAddress = "127.0.0.1" }]
+/// extended_key_usages = ["ServerAuth"]
+/// use_authority_key_identifier_extension = true
+/// ```
+///
+/// *This type is only available if rcgen is built with the "serde" feature*
+#[derive(Default, Serialize, Deserialize)]
+pub struct PkiConfig {
+	/// The certificates of the PKI, issuers first
+	#[serde(default)]
+	pub certificates :Vec<CertificateConfig>,
+}
+
+/// A single certificate of a [`PkiConfig`]
+///
+/// The fields of the [`CertificateParams`] are given next to
+/// the name and the issuer, not in a nested table.
+///
+/// *This type is only available if rcgen is built with the "serde" feature*
+#[derive(Serialize, Deserialize)]
+pub struct CertificateConfig {
+	/// Name used to refer to the certificate, e.g. as issuer of other certificates
+	pub name :String,
+	/// Name of the issuing certificate, or `None` for a self signed certificate
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub issuer :Option<String>,
+	/// Parameters of the certificate
+	#[serde(flatten)]
+	pub params :CertificateParams,
+}
+
+impl PkiConfig {
+	/// Creates all certificates of the PKI
+	///
+	/// ```
+	/// # use rcgen::{CertificateConfig, CertificateParams, PkiConfig};
+	/// let config = PkiConfig {
+	/// 	certificates : vec![
+	/// 		CertificateConfig {
+	/// 			name : "root".to_string(),
+	/// 			issuer : None,
+	/// 			params : CertificateParams::root_ca("Crab root CA"),
+	/// 		},
+	/// 		CertificateConfig {
+	/// 			name : "server".to_string(),
+	/// 			issuer : Some("root".to_string()),
+	/// 			params : CertificateParams::tls_server(vec!["crabs.crabs".to_string()]),
+	/// 		},
+	/// 	],
+	/// };
+	/// let pki = config.generate().unwrap();
+	/// assert!(pki.get("server").is_some());
+	/// ```
+	pub fn generate(self) -> Result<GeneratedPki, RcgenError> {
+		let mut certificates :Vec<(String, ChainCertificate)> = Vec::new();
+		for config in self.certificates {
+			if certificates.iter().any(|(name, _)| name == &config.name) {
+				return Err(RcgenError::DuplicateCertificateName(config.name));
+			}
+			let certificate = Certificate::from_params(config.params)?;
+			let der = match config.issuer {
+				Some(issuer_name) => {
+					let issuer = certificates.iter()
+						.find(|(name, _)| name == &issuer_name)
+						.map(|(_, issuer)| issuer)
+						.ok_or(RcgenError::UnknownIssuer(issuer_name))?;
+					certificate.serialize_der_with_signer(&issuer.certificate)?
+				},
+				None => certificate.serialize_der()?,
+			};
+			certificates.push((config.name, ChainCertificate { certificate, der }));
+		}
+		Ok(GeneratedPki { certificates })
+	}
+}
+
+/// The certificates created by [`PkiConfig::generate`]
+///
+/// *This type is only available if rcgen is built with the "serde" feature*
+pub struct GeneratedPki {
+	certificates :Vec<(String, ChainCertificate)>,
+}
+
+impl GeneratedPki {
+	/// Returns the certificate with the given name
+	pub fn get(&self, name :&str) -> Option<&ChainCertificate> {
+		self.certificates.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, certificate)| certificate)
+	}
+	/// Iterates over the names and certificates, in the order of the configuration
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &ChainCertificate)> {
+		self.certificates.iter()
+			.map(|(name, certificate)| (name.as_str(), certificate))
+	}
+}
+
+/// (De)serializes signature algorithms by the names of their statics
+pub(crate) mod signature_algorithm {
+	use serde::{Deserialize, Deserializer, Serializer};
+	use serde::de::Error;
+	use crate::*;
+
+	// PKCS_RSA_PSS_SHA256 is left out on purpose, just like it isn't public
+	static ALGORITHMS :&[(&str, &SignatureAlgorithm)] = &[
+		("PKCS_RSA_SHA256", &PKCS_RSA_SHA256),
+		("PKCS_RSA_SHA384", &PKCS_RSA_SHA384),
+		("PKCS_RSA_SHA512", &PKCS_RSA_SHA512),
+		("PKCS_ECDSA_P256_SHA256", &PKCS_ECDSA_P256_SHA256),
+		("PKCS_ECDSA_P384_SHA384", &PKCS_ECDSA_P384_SHA384),
+		("PKCS_ED25519", &PKCS_ED25519),
+	];
+
+	pub fn serialize<S :Serializer>(alg :&&'static SignatureAlgorithm, serializer :S) -> Result<S::Ok, S::Error> {
+		let (name, _) = ALGORITHMS.iter()
+			.find(|(_, a)| a == alg)
+			.ok_or_else(|| serde::ser::Error::custom("unknown signature algorithm"))?;
+		serializer.serialize_str(name)
+	}
+
+	pub fn deserialize<'de, D :Deserializer<'de>>(deserializer :D) -> Result<&'static SignatureAlgorithm, D::Error> {
+		let name = String::deserialize(deserializer)?;
+		ALGORITHMS.iter()
+			.find(|(n, _)| *n == name)
+			.map(|(_, alg)| *alg)
+			.ok_or_else(|| D::Error::custom(format!("unknown signature algorithm {:?}", name)))
+	}
+}
+
+// Distinguished names are maps from the attribute type to the value. Well known
+// types are referred to by their variant name, custom types by their dotted OID.
+// Values are plain strings for UTF-8 strings, and tagged with the DnValue variant otherwise.
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DnValueRepr {
+	Plain(String),
+	Tagged(DnValue),
+}
+
+fn dn_type_name(ty :&DnType) -> String {
+	match ty {
+		DnType::CountryName => "CountryName".to_string(),
+		DnType::LocalityName => "LocalityName".to_string(),
+		DnType::StateOrProvinceName => "StateOrProvinceName".to_string(),
+		DnType::OrganizationName => "OrganizationName".to_string(),
+		DnType::OrganizationalUnitName => "OrganizationalUnitName".to_string(),
+		DnType::CommonName => "CommonName".to_string(),
+		DnType::CustomDnType(oid) => oid.iter()
+			.map(|component| component.to_string())
+			.collect::<Vec<_>>()
+			.join("."),
+	}
+}
+
+fn dn_type_from_name(name :&str) -> Option<DnType> {
+	Some(match name {
+		"CountryName" => DnType::CountryName,
+		"LocalityName" => DnType::LocalityName,
+		"StateOrProvinceName" => DnType::StateOrProvinceName,
+		"OrganizationName" => DnType::OrganizationName,
+		"OrganizationalUnitName" => DnType::OrganizationalUnitName,
+		"CommonName" => DnType::CommonName,
+		_ => {
+			let oid = name.split('.')
+				.map(|component| component.parse::<u64>().ok())
+				.collect::<Option<Vec<_>>>()?;
+			DnType::from_oid(&oid)
+		},
+	})
+}
+
+impl Serialize for DistinguishedName {
+	fn serialize<S :Serializer>(&self, serializer :S) -> Result<S::Ok, S::Error> {
+		let mut map = serializer.serialize_map(Some(self.order.len()))?;
+		for (ty, value) in self.iter() {
+			let value = match value {
+				DnValue::Utf8String(s) => DnValueRepr::Plain(s.clone()),
+				value => DnValueRepr::Tagged(value.clone()),
+			};
+			map.serialize_entry(&dn_type_name(ty), &value)?;
+		}
+		map.end()
+	}
+}
+
+impl<'de> Deserialize<'de> for DistinguishedName {
+	fn deserialize<D :Deserializer<'de>>(deserializer :D) -> Result<Self, D::Error> {
+		struct DnVisitor;
+		impl<'de> Visitor<'de> for DnVisitor {
+			type Value = DistinguishedName;
+			fn expecting(&self, f :&mut fmt::Formatter) -> fmt::Result {
+				write!(f, "a map from attribute types to values")
+			}
+			fn visit_map<A :MapAccess<'de>>(self, mut access :A) -> Result<Self::Value, A::Error> {
+				let mut dn = DistinguishedName::new();
+				while let Some((name, value)) = access.next_entry::<String, DnValueRepr>()? {
+					let ty = dn_type_from_name(&name)
+						.ok_or_else(|| de::Error::custom(format!("unknown attribute type {:?}", name)))?;
+					match value {
+						DnValueRepr::Plain(s) => dn.push(ty, s),
+						DnValueRepr::Tagged(value) => dn.push(ty, value),
+					}
+				}
+				Ok(dn)
+			}
+		}
+		deserializer.deserialize_map(DnVisitor)
+	}
+}
+
+// CIDR subnets use the addr/prefix notation
+
+impl Serialize for CidrSubnet {
+	fn serialize<S :Serializer>(&self, serializer :S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+impl<'de> Deserialize<'de> for CidrSubnet {
+	fn deserialize<D :Deserializer<'de>>(deserializer :D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		CidrSubnet::from_str(&s)
+			.map_err(|()| de::Error::custom(format!("invalid CIDR subnet {:?}", s)))
+	}
+}
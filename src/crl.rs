@@ -0,0 +1,194 @@
+use time::OffsetDateTime;
+use yasna::{DERWriter, Tag};
+#[cfg(feature = "pem")]
+use pem::Pem;
+use crate::{write_distinguished_name, write_dt_utc_or_generalized, Certificate,
+	CertificateParams, KeyUsagePurpose, RcgenError};
+use crate::{OID_AUTHORITY_KEY_IDENTIFIER, OID_CRL_NUMBER, OID_CRL_REASONS};
+
+/// Parameters used for certificate revocation list (CRL) generation
+///
+/// The CRL is signed by, and issued in the name of, the CA
+/// passed to [`CertificateRevocationList::serialize_der_with_signer`].
+#[allow(missing_docs)]
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CertificateRevocationListParams {
+	/// Issue date of the CRL
+	pub this_update :OffsetDateTime,
+	/// Date by which the next CRL will be issued
+	pub next_update :OffsetDateTime,
+	/// Monotonically increasing sequence number of the CRL
+	pub crl_number :u64,
+	pub revoked_certs :Vec<RevokedCertParams>,
+}
+
+impl CertificateRevocationListParams {
+	/// Creates parameters for an empty CRL with the given validity and number
+	pub fn new(this_update :OffsetDateTime, next_update :OffsetDateTime, crl_number :u64) -> Self {
+		CertificateRevocationListParams {
+			this_update,
+			next_update,
+			crl_number,
+			revoked_certs : Vec::new(),
+		}
+	}
+}
+
+/// A revoked certificate, identified by its serial number
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct RevokedCertParams {
+	/// Serial number of the revoked certificate
+	pub serial_number :u64,
+	/// Time at which the certificate was revoked
+	pub revocation_time :OffsetDateTime,
+	/// Optional reason of the revocation
+	pub reason_code :Option<RevocationReason>,
+}
+
+/// The reason a certificate was revoked, as specified in
+/// [RFC 5280 section 5.3.1](https://tools.ietf.org/html/rfc5280#section-5.3.1)
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[allow(missing_docs)]
+pub enum RevocationReason {
+	Unspecified = 0,
+	KeyCompromise = 1,
+	CaCompromise = 2,
+	AffiliationChanged = 3,
+	Superseded = 4,
+	CessationOfOperation = 5,
+	CertificateHold = 6,
+	// 7 is not used
+	RemoveFromCrl = 8,
+	PrivilegeWithdrawn = 9,
+	AaCompromise = 10,
+}
+
+impl RevocationReason {
+	/// Obtains the reason from its numeric code
+	pub fn from_code(code :u8) -> Option<Self> {
+		use self::RevocationReason::*;
+		Some(match code {
+			0 => Unspecified,
+			1 => KeyCompromise,
+			2 => CaCompromise,
+			3 => AffiliationChanged,
+			4 => Superseded,
+			5 => CessationOfOperation,
+			6 => CertificateHold,
+			8 => RemoveFromCrl,
+			9 => PrivilegeWithdrawn,
+			10 => AaCompromise,
+			_ => return None,
+		})
+	}
+}
+
+/// A certificate revocation list (CRL), as specified in
+/// [RFC 5280 section 5](https://tools.ietf.org/html/rfc5280#section-5)
+pub struct CertificateRevocationList {
+	params :CertificateRevocationListParams,
+}
+
+impl CertificateRevocationList {
+	/// Creates a CRL from the given parameters
+	pub fn from_params(params :CertificateRevocationListParams) -> Result<Self, RcgenError> {
+		if params.next_update < params.this_update {
+			return Err(RcgenError::InvalidCrlNextUpdate);
+		}
+		Ok(CertificateRevocationList { params })
+	}
+	/// Returns the parameters the CRL was created from
+	pub fn get_params(&self) -> &CertificateRevocationListParams {
+		&self.params
+	}
+	/// Serializes the CRL, signed by the given CA, in binary DER format
+	///
+	/// Fails with [`RcgenError::IssuerNotCrlSigner`] if the CA has key usages
+	/// that don't include [`KeyUsagePurpose::CrlSign`].
+	pub fn serialize_der_with_signer(&self, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+		let ca_params :&CertificateParams = &ca.params;
+		if !ca_params.key_usages.is_empty() && !ca_params.key_usages.contains(&KeyUsagePurpose::CrlSign) {
+			return Err(RcgenError::IssuerNotCrlSigner);
+		}
+		yasna::try_construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				let tbs_cert_list_serialized = yasna::construct_der(|writer| {
+					self.write_tbs_cert_list(writer, ca);
+				});
+				writer.next().write_der(&tbs_cert_list_serialized);
+
+				// Write signatureAlgorithm
+				ca.params.alg.write_alg_ident(writer.next());
+
+				// Write signature
+				ca.key_pair.sign(&tbs_cert_list_serialized, writer.next())?;
+
+				Ok(())
+			})
+		})
+	}
+	/// Serializes the CRL, signed by the given CA, to the ASCII PEM format
+	///
+	/// *This function is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn serialize_pem_with_signer(&self, ca :&Certificate) -> Result<String, RcgenError> {
+		let p = Pem {
+			tag : "X509 CRL".to_string(),
+			contents : self.serialize_der_with_signer(ca)?,
+		};
+		Ok(pem::encode(&p))
+	}
+	fn write_tbs_cert_list(&self, writer :DERWriter, ca :&Certificate) {
+		writer.write_sequence(|writer| {
+			// Write version, v2 is required as extensions are present
+			writer.next().write_u8(1);
+			// Write signature
+			ca.params.alg.write_alg_ident(writer.next());
+			// Write issuer
+			write_distinguished_name(writer.next(), &ca.params.distinguished_name);
+			// Write thisUpdate and nextUpdate
+			write_dt_utc_or_generalized(writer.next(), self.params.this_update);
+			write_dt_utc_or_generalized(writer.next(), self.params.next_update);
+			// Write revokedCertificates, which must be omitted if empty
+			if !self.params.revoked_certs.is_empty() {
+				writer.next().write_sequence(|writer| {
+					for revoked_cert in self.params.revoked_certs.iter() {
+						write_revoked_cert(writer.next(), revoked_cert);
+					}
+				});
+			}
+			// Write crlExtensions
+			writer.next().write_tagged(Tag::context(0), |writer| {
+				writer.write_sequence(|writer| {
+					// RFC 5280 requires both the authority key identifier
+					// and the CRL number to be present.
+					CertificateParams::write_extension(writer.next(), OID_AUTHORITY_KEY_IDENTIFIER, false, |writer| {
+						writer.write_sequence(|writer| {
+							writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+								writer.write_bytes(ca.get_key_identifier().as_ref())
+							})
+						});
+					});
+					CertificateParams::write_extension(writer.next(), OID_CRL_NUMBER, false, |writer| {
+						writer.write_u64(self.params.crl_number);
+					});
+				});
+			});
+		});
+	}
+}
+
+fn write_revoked_cert(writer :DERWriter, revoked_cert :&RevokedCertParams) {
+	writer.write_sequence(|writer| {
+		writer.next().write_u64(revoked_cert.serial_number);
+		write_dt_utc_or_generalized(writer.next(), revoked_cert.revocation_time);
+		if let Some(reason_code) = revoked_cert.reason_code {
+			writer.next().write_sequence(|writer| {
+				CertificateParams::write_extension(writer.next(), OID_CRL_REASONS, false, |writer| {
+					writer.write_enum(reason_code as i64);
+				});
+			});
+		}
+	});
+}
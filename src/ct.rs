@@ -0,0 +1,243 @@
+use std::convert::TryInto;
+use ring::digest;
+use ring::signature::{self, UnparsedPublicKey};
+use time::OffsetDateTime;
+use crate::{Certificate, CustomExtension, KeyPair, RcgenError, SignatureAlgorithm,
+	OID_CT_PRECERT_POISON, OID_CT_SCT_LIST, PKCS_ECDSA_P256_SHA256, PKCS_RSA_SHA256};
+
+/// A signed certificate timestamp (SCT), as specified in
+/// [RFC 6962 section 3.2](https://tools.ietf.org/html/rfc6962#section-3.2)
+///
+/// Only version 1 SCTs exist.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct SignedCertificateTimestamp {
+	/// The SHA-256 digest of the log's public key
+	pub log_id :[u8; 32],
+	/// Milliseconds since the UNIX epoch
+	pub timestamp :u64,
+	/// The opaque SCT extensions, empty for all logs so far
+	pub extensions :Vec<u8>,
+	/// The TLS `HashAlgorithm` of the signature, 4 for SHA-256
+	pub hash_algorithm :u8,
+	/// The TLS `SignatureAlgorithm` of the signature, 1 for RSA and 3 for ECDSA
+	pub signature_algorithm :u8,
+	/// The signature over the log entry
+	pub signature :Vec<u8>,
+}
+
+impl SignedCertificateTimestamp {
+	/// Serializes the SCT in the TLS encoding used inside of
+	/// SignedCertificateTimestampList extensions
+	pub fn serialize(&self) -> Vec<u8> {
+		let mut out = vec![0]; // v1
+		out.extend_from_slice(&self.log_id);
+		out.extend_from_slice(&self.timestamp.to_be_bytes());
+		write_u16_prefixed(&mut out, &self.extensions);
+		out.push(self.hash_algorithm);
+		out.push(self.signature_algorithm);
+		write_u16_prefixed(&mut out, &self.signature);
+		out
+	}
+	/// The data that is signed by the log
+	fn signed_data(&self, entry :&CtLogEntry<'_>) -> Vec<u8> {
+		// version v1, signature_type certificate_timestamp
+		let mut out = vec![0, 0];
+		out.extend_from_slice(&self.timestamp.to_be_bytes());
+		match entry {
+			CtLogEntry::X509(certificate) => {
+				out.extend_from_slice(&[0, 0]);
+				write_u24_prefixed(&mut out, certificate);
+			},
+			CtLogEntry::Precertificate { issuer_key_hash, tbs_certificate } => {
+				out.extend_from_slice(&[0, 1]);
+				out.extend_from_slice(issuer_key_hash);
+				write_u24_prefixed(&mut out, tbs_certificate);
+			},
+		}
+		write_u16_prefixed(&mut out, &self.extensions);
+		out
+	}
+}
+
+/// An entry of a CT log that SCTs are issued for, as specified in
+/// [RFC 6962 section 3.1](https://tools.ietf.org/html/rfc6962#section-3.1)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CtLogEntry<'a> {
+	/// A final certificate in DER format, for SCTs delivered via TLS or OCSP
+	X509(&'a [u8]),
+	/// A precertificate, for SCTs embedded in the final certificate
+	Precertificate {
+		/// The SHA-256 digest of the issuer's SubjectPublicKeyInfo
+		issuer_key_hash :[u8; 32],
+		/// The DER encoded TBSCertificate without the poison extension,
+		/// as returned by [`Certificate::precertificate_tbs_der`]
+		tbs_certificate :&'a [u8],
+	},
+}
+
+impl<'a> CtLogEntry<'a> {
+	/// Creates a precertificate entry for the TBSCertificate of a
+	/// precertificate signed directly by the given issuer
+	pub fn precertificate(issuer :&Certificate, tbs_certificate :&'a [u8]) -> Self {
+		let digest = digest::digest(&digest::SHA256, &issuer.key_pair.public_key_der());
+		CtLogEntry::Precertificate {
+			issuer_key_hash : digest.as_ref().try_into().unwrap(),
+			tbs_certificate,
+		}
+	}
+}
+
+/// A minimal Certificate Transparency log that signs SCTs, e.g. for tests
+///
+/// The log doesn't keep a Merkle tree, it only issues SCTs.
+///
+/// ```
+/// # use rcgen::{Certificate, CertificateParams, CtLog, CtLogEntry};
+/// let ca = Certificate::from_params(CertificateParams::root_ca("Crab CA")).unwrap();
+/// let cert = Certificate::from_params(CertificateParams::new(vec!["crabs.crabs".to_string()])).unwrap();
+/// let log = CtLog::generate().unwrap();
+///
+/// // The precertificate would be submitted to the log
+/// let _precert = cert.serialize_precertificate_der_with_signer(&ca).unwrap();
+/// let tbs = cert.precertificate_tbs_der(&ca).unwrap();
+/// let sct = log.sign(&CtLogEntry::precertificate(&ca, &tbs), time::OffsetDateTime::now_utc()).unwrap();
+/// let final_cert = cert.serialize_der_with_signer_and_scts(&ca, &[sct]).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CtLog {
+	key_pair :KeyPair,
+}
+
+impl CtLog {
+	/// Creates a log with a new ECDSA P-256 key pair
+	pub fn generate() -> Result<Self, RcgenError> {
+		Self::from_key_pair(KeyPair::generate(&PKCS_ECDSA_P256_SHA256)?)
+	}
+	/// Creates a log with the given key pair
+	///
+	/// RFC 6962 only allows ECDSA P-256 and RSA keys with SHA-256,
+	/// other key pairs are rejected with [`RcgenError::UnsupportedSignatureAlgorithm`].
+	pub fn from_key_pair(key_pair :KeyPair) -> Result<Self, RcgenError> {
+		if key_pair.alg != &PKCS_ECDSA_P256_SHA256 && key_pair.alg != &PKCS_RSA_SHA256 {
+			return Err(RcgenError::UnsupportedSignatureAlgorithm);
+		}
+		Ok(CtLog { key_pair })
+	}
+	/// The log's key pair
+	pub fn key_pair(&self) -> &KeyPair {
+		&self.key_pair
+	}
+	/// The log ID, the SHA-256 digest of the log's SubjectPublicKeyInfo
+	pub fn log_id(&self) -> [u8; 32] {
+		let digest = digest::digest(&digest::SHA256, &self.key_pair.public_key_der());
+		digest.as_ref().try_into().unwrap()
+	}
+	/// Issues an SCT for the entry with the given timestamp
+	pub fn sign(&self, entry :&CtLogEntry<'_>, timestamp :OffsetDateTime) -> Result<SignedCertificateTimestamp, RcgenError> {
+		let mut sct = SignedCertificateTimestamp {
+			log_id : self.log_id(),
+			timestamp : (timestamp.unix_timestamp_nanos() / 1_000_000) as u64,
+			extensions : Vec::new(),
+			hash_algorithm : 4,
+			signature_algorithm : tls_signature_algorithm(self.key_pair.alg),
+			signature : Vec::new(),
+		};
+		sct.signature = self.key_pair.sign_raw(&sct.signed_data(entry))?;
+		Ok(sct)
+	}
+	/// Checks whether the SCT was issued by this log for the entry
+	pub fn verify(&self, sct :&SignedCertificateTimestamp, entry :&CtLogEntry<'_>) -> bool {
+		if sct.log_id != self.log_id() || sct.hash_algorithm != 4 ||
+				sct.signature_algorithm != tls_signature_algorithm(self.key_pair.alg) {
+			return false;
+		}
+		let alg :&dyn signature::VerificationAlgorithm = if self.key_pair.alg == &PKCS_ECDSA_P256_SHA256 {
+			&signature::ECDSA_P256_SHA256_ASN1
+		} else {
+			&signature::RSA_PKCS1_2048_8192_SHA256
+		};
+		UnparsedPublicKey::new(alg, self.key_pair.public_key_raw())
+			.verify(&sct.signed_data(entry), &sct.signature)
+			.is_ok()
+	}
+}
+
+fn tls_signature_algorithm(alg :&SignatureAlgorithm) -> u8 {
+	if alg == &PKCS_RSA_SHA256 { 1 } else { 3 }
+}
+
+fn write_u16_prefixed(out :&mut Vec<u8>, data :&[u8]) {
+	out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+	out.extend_from_slice(data);
+}
+
+fn write_u24_prefixed(out :&mut Vec<u8>, data :&[u8]) {
+	out.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+	out.extend_from_slice(data);
+}
+
+impl CustomExtension {
+	/// Creates the critical precertificate poison extension as specified in
+	/// [RFC 6962 section 3.1](https://tools.ietf.org/html/rfc6962#section-3.1)
+	///
+	/// See [`Certificate::serialize_precertificate_der_with_signer`]
+	/// for serializing precertificates with it.
+	pub fn new_ct_poison() -> Self {
+		let mut ext = CustomExtension::from_oid_content(OID_CT_PRECERT_POISON,
+			yasna::construct_der(|writer| writer.write_null()));
+		ext.set_criticality(true);
+		ext
+	}
+	/// Creates a SignedCertificateTimestampList extension as specified in
+	/// [RFC 6962 section 3.3](https://tools.ietf.org/html/rfc6962#section-3.3)
+	pub fn new_sct_list(scts :&[SignedCertificateTimestamp]) -> Self {
+		let mut list = Vec::new();
+		for sct in scts {
+			write_u16_prefixed(&mut list, &sct.serialize());
+		}
+		let mut prefixed = Vec::new();
+		write_u16_prefixed(&mut prefixed, &list);
+		CustomExtension::from_oid_content(OID_CT_SCT_LIST,
+			yasna::construct_der(|writer| writer.write_bytes(&prefixed)))
+	}
+}
+
+impl Certificate {
+	/// The custom extensions without CT poison and SCT list extensions
+	fn ct_custom_extensions(&self) -> Vec<CustomExtension> {
+		self.params.custom_extensions.iter()
+			.filter(|ext| ext.oid != OID_CT_PRECERT_POISON && ext.oid != OID_CT_SCT_LIST)
+			.cloned()
+			.collect()
+	}
+	/// Serializes the precertificate, signed with another certificate's key, in binary DER format
+	///
+	/// The precertificate carries the critical poison extension in addition to
+	/// the certificate's extensions, so that it isn't accepted as a certificate.
+	/// It is submitted to CT logs in exchange for SCTs.
+	pub fn serialize_precertificate_der_with_signer(&self, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+		let mut extensions = self.ct_custom_extensions();
+		extensions.push(CustomExtension::new_ct_poison());
+		self.params.serialize_signed_der_with_extensions(&self.key_pair, ca, &extensions)
+	}
+	/// Serializes the TBSCertificate of the precertificate without the poison
+	/// extension in binary DER format, which is what CT logs sign
+	///
+	/// See [`CtLogEntry::precertificate`].
+	pub fn precertificate_tbs_der(&self, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+		let extensions = self.ct_custom_extensions();
+		yasna::try_construct_der(|writer| {
+			self.params.write_cert(writer, &self.key_pair, ca, &extensions)
+		})
+	}
+	/// Serializes the certificate with embedded SCTs, signed with another
+	/// certificate's key, in binary DER format
+	///
+	/// The SCTs have to be issued for the
+	/// [TBSCertificate of the precertificate](Self::precertificate_tbs_der).
+	pub fn serialize_der_with_signer_and_scts(&self, ca :&Certificate, scts :&[SignedCertificateTimestamp]) -> Result<Vec<u8>, RcgenError> {
+		let mut extensions = self.ct_custom_extensions();
+		extensions.push(CustomExtension::new_sct_list(scts));
+		self.params.serialize_signed_der_with_extensions(&self.key_pair, ca, &extensions)
+	}
+}
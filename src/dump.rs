@@ -0,0 +1,430 @@
+use std::fmt;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::certification_request::X509CertificationRequest;
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::extensions::{GeneralName, ParsedExtension, X509Extension};
+use x509_parser::prelude::FromDer;
+use x509_parser::revocation_list::CertificateRevocationList;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::{AlgorithmIdentifier, SubjectPublicKeyInfo, X509Name};
+use crate::{hex, DnType, ExtendedKeyUsagePurpose, FingerprintAlgorithm, KeyUsagePurpose, RcgenError,
+	RevocationReason, SignatureAlgorithm, PKCS_ECDSA_P256_SHA256, PKCS_ECDSA_P384_SHA384, PKCS_ED25519, PKCS_RSA_SHA256};
+use crate::verify::{cidr_subnet_from_bytes, ip_addr_from_bytes};
+
+/// Human readable rendering of a DER encoded certificate, CSR or CRL
+///
+/// Similar to the output of `openssl x509 -text`, the [`Display`](fmt::Display)
+/// implementation lists names in [RFC 4514](https://tools.ietf.org/html/rfc4514) form,
+/// the validity period, the serial number, the public key algorithm,
+/// SHA-256 and SHA-1 fingerprints of the DER encoding, and the decoded extensions.
+///
+/// ```
+/// # use rcgen::X509Dump;
+/// let cert = rcgen::generate_simple_self_signed(vec!["crabs.crabs".to_string()]).unwrap();
+/// let dump = X509Dump::from_der(&cert.serialize_der().unwrap()).unwrap();
+/// let text = dump.to_string();
+/// assert!(text.contains("Subject: CN=rcgen self signed cert"));
+/// assert!(text.contains("Subject alternative names: DNS:crabs.crabs"));
+/// ```
+///
+/// *This type is only available if rcgen is built with the "x509-parser" feature*
+pub struct X509Dump {
+	der :Vec<u8>,
+	kind :DumpKind,
+}
+
+#[derive(Clone, Copy)]
+enum DumpKind {
+	Certificate,
+	CertificateSigningRequest,
+	CertificateRevocationList,
+}
+
+impl X509Dump {
+	/// Parses a DER encoded certificate, certificate signing request or
+	/// certificate revocation list, detecting which one of them it is
+	pub fn from_der(der :&[u8]) -> Result<Self, RcgenError> {
+		let kind = if x509_parser::parse_x509_certificate(der).is_ok() {
+			DumpKind::Certificate
+		} else if X509CertificationRequest::from_der(der).is_ok() {
+			DumpKind::CertificateSigningRequest
+		} else if x509_parser::parse_x509_crl(der).is_ok() {
+			DumpKind::CertificateRevocationList
+		} else {
+			return Err(RcgenError::CouldNotParseCertificate);
+		};
+		Ok(X509Dump {
+			der : der.to_vec(),
+			kind,
+		})
+	}
+}
+
+impl fmt::Display for X509Dump {
+	fn fmt(&self, f :&mut fmt::Formatter) -> fmt::Result {
+		// Parsing succeeded in from_der already, so it can't fail here
+		match self.kind {
+			DumpKind::Certificate => {
+				let (_, cert) = x509_parser::parse_x509_certificate(&self.der)
+					.map_err(|_| fmt::Error)?;
+				write_certificate(f, &cert, &self.der)
+			},
+			DumpKind::CertificateSigningRequest => {
+				let (_, csr) = X509CertificationRequest::from_der(&self.der)
+					.map_err(|_| fmt::Error)?;
+				write_csr(f, &csr, &self.der)
+			},
+			DumpKind::CertificateRevocationList => {
+				let (_, crl) = x509_parser::parse_x509_crl(&self.der)
+					.map_err(|_| fmt::Error)?;
+				write_crl(f, &crl, &self.der)
+			},
+		}
+	}
+}
+
+fn write_certificate(f :&mut fmt::Formatter, cert :&X509Certificate, der :&[u8]) -> fmt::Result {
+	writeln!(f, "Certificate:")?;
+	writeln!(f, "  Version: {}", cert.version().0 + 1)?;
+	writeln!(f, "  Serial number: {}", hex(cert.raw_serial(), ":"))?;
+	writeln!(f, "  Signature algorithm: {}", signature_algorithm(&cert.signature_algorithm))?;
+	writeln!(f, "  Issuer: {}", rfc4514(cert.issuer()))?;
+	writeln!(f, "  Validity:")?;
+	writeln!(f, "    Not before: {}", time(&cert.validity().not_before))?;
+	writeln!(f, "    Not after: {}", time(&cert.validity().not_after))?;
+	writeln!(f, "  Subject: {}", rfc4514(cert.subject()))?;
+	writeln!(f, "  Public key: {}", public_key(cert.public_key()))?;
+	write_fingerprints(f, der)?;
+	write_extensions(f, "Extensions", cert.extensions())
+}
+
+fn write_csr(f :&mut fmt::Formatter, csr :&X509CertificationRequest, der :&[u8]) -> fmt::Result {
+	let info = &csr.certification_request_info;
+	writeln!(f, "Certificate signing request:")?;
+	writeln!(f, "  Version: {}", info.version.0 + 1)?;
+	writeln!(f, "  Signature algorithm: {}", signature_algorithm(&csr.signature_algorithm))?;
+	writeln!(f, "  Subject: {}", rfc4514(&info.subject))?;
+	writeln!(f, "  Public key: {}", public_key(&info.subject_pki))?;
+	write_fingerprints(f, der)?;
+
+	// x509-parser only gives access to the parsed contents of the requested
+	// extensions, so parse them again to obtain their OIDs and criticality.
+	let extension_ders = info.iter_attributes()
+		.filter(|attr| oid_components(&attr.oid) == crate::OID_PKCS_9_AT_EXTENSION_REQUEST)
+		.flat_map(|attr| parse_extension_request(attr.value).unwrap_or_default())
+		.collect::<Vec<_>>();
+	let extensions = extension_ders.iter()
+		.filter_map(|der| X509Extension::from_der(der).ok())
+		.map(|(_, ext)| ext)
+		.collect::<Vec<_>>();
+	write_extensions(f, "Requested extensions", &extensions)
+}
+
+fn write_crl(f :&mut fmt::Formatter, crl :&CertificateRevocationList, der :&[u8]) -> fmt::Result {
+	let tbs_cert_list = &crl.tbs_cert_list;
+	writeln!(f, "Certificate revocation list:")?;
+	writeln!(f, "  Version: {}", tbs_cert_list.version.map(|v| v.0 + 1).unwrap_or(1))?;
+	writeln!(f, "  Signature algorithm: {}", signature_algorithm(&crl.signature_algorithm))?;
+	writeln!(f, "  Issuer: {}", rfc4514(&tbs_cert_list.issuer))?;
+	writeln!(f, "  This update: {}", time(&tbs_cert_list.this_update))?;
+	if let Some(next_update) = &tbs_cert_list.next_update {
+		writeln!(f, "  Next update: {}", time(next_update))?;
+	}
+	write_fingerprints(f, der)?;
+	write_extensions(f, "Extensions", crl.extensions())?;
+	if tbs_cert_list.revoked_certificates.is_empty() {
+		return Ok(());
+	}
+	writeln!(f, "  Revoked certificates:")?;
+	for revoked in crl.iter_revoked_certificates() {
+		writeln!(f, "    Serial number: {}", hex(revoked.raw_serial(), ":"))?;
+		writeln!(f, "      Revocation date: {}", time(&revoked.revocation_date))?;
+		if let Some((_critical, reason)) = revoked.reason_code() {
+			match RevocationReason::from_code(reason.0) {
+				Some(reason) => writeln!(f, "      Reason: {:?}", reason)?,
+				None => writeln!(f, "      Reason: {}", reason.0)?,
+			}
+		}
+	}
+	Ok(())
+}
+
+fn write_fingerprints(f :&mut fmt::Formatter, der :&[u8]) -> fmt::Result {
+	writeln!(f, "  Fingerprints:")?;
+	writeln!(f, "    SHA-256: {}", hex(&FingerprintAlgorithm::Sha256.fingerprint(der), ":"))?;
+	writeln!(f, "    SHA-1: {}", hex(&FingerprintAlgorithm::Sha1.fingerprint(der), ":"))
+}
+
+fn write_extensions(f :&mut fmt::Formatter, title :&str, extensions :&[X509Extension]) -> fmt::Result {
+	if extensions.is_empty() {
+		return Ok(());
+	}
+	writeln!(f, "  {}:", title)?;
+	for ext in extensions {
+		let oid = oid_components(&ext.oid);
+		let name = match oid.as_slice() {
+			crate::OID_SUBJECT_ALT_NAME => "Subject alternative names".to_string(),
+			crate::OID_KEY_USAGE => "Key usage".to_string(),
+			crate::OID_EXT_KEY_USAGE => "Extended key usage".to_string(),
+			crate::OID_BASIC_CONSTRAINTS => "Basic constraints".to_string(),
+			crate::OID_NAME_CONSTRAINTS => "Name constraints".to_string(),
+			crate::OID_SUBJECT_KEY_IDENTIFIER => "Subject key identifier".to_string(),
+			crate::OID_AUTHORITY_KEY_IDENTIFIER => "Authority key identifier".to_string(),
+			crate::OID_CRL_NUMBER => "CRL number".to_string(),
+			crate::OID_PE_ACME => "ACME identifier".to_string(),
+			_ => dotted(&oid),
+		};
+		let critical = if ext.critical { " (critical)" } else { "" };
+		writeln!(f, "    {}{}: {}", name, critical, extension_value(&oid, ext))?;
+	}
+	Ok(())
+}
+
+fn extension_value(oid :&[u64], ext :&X509Extension) -> String {
+	match ext.parsed_extension() {
+		ParsedExtension::SubjectAlternativeName(san) => general_names(&san.general_names),
+		ParsedExtension::KeyUsage(key_usage) => {
+			KEY_USAGES.iter()
+				.filter(|(usage, _)| key_usage.flags & (1 << usage.bit_index()) != 0)
+				.map(|(_, name)| *name)
+				.collect::<Vec<_>>()
+				.join(", ")
+		},
+		ParsedExtension::BasicConstraints(constraints) => {
+			match (constraints.ca, constraints.path_len_constraint) {
+				(true, Some(path_len)) => format!("CA, path length {}", path_len),
+				(true, None) => "CA".to_string(),
+				(false, _) => "not a CA".to_string(),
+			}
+		},
+		ParsedExtension::NameConstraints(constraints) => {
+			let subtrees = |subtrees :&Option<Vec<x509_parser::extensions::GeneralSubtree>>| {
+				let names = subtrees.iter().flatten()
+					.map(|subtree| subtree.base.clone())
+					.collect::<Vec<_>>();
+				general_names(&names)
+			};
+			let mut parts = Vec::new();
+			if constraints.permitted_subtrees.is_some() {
+				parts.push(format!("permitted {}", subtrees(&constraints.permitted_subtrees)));
+			}
+			if constraints.excluded_subtrees.is_some() {
+				parts.push(format!("excluded {}", subtrees(&constraints.excluded_subtrees)));
+			}
+			parts.join("; ")
+		},
+		ParsedExtension::SubjectKeyIdentifier(key_identifier) => hex(key_identifier.0, ":"),
+		ParsedExtension::AuthorityKeyIdentifier(aki) => match &aki.key_identifier {
+			Some(key_identifier) => hex(key_identifier.0, ":"),
+			None => hex(ext.value, ":"),
+		},
+		ParsedExtension::CRLNumber(number) => number.to_string(),
+		_ if oid == crate::OID_EXT_KEY_USAGE => {
+			extended_key_usages(ext.value).unwrap_or_else(|| hex(ext.value, ":"))
+		},
+		_ => hex(ext.value, ":"),
+	}
+}
+
+static KEY_USAGES :&[(KeyUsagePurpose, &str)] = &[
+	(KeyUsagePurpose::DigitalSignature, "digitalSignature"),
+	(KeyUsagePurpose::ContentCommitment, "contentCommitment"),
+	(KeyUsagePurpose::KeyEncipherment, "keyEncipherment"),
+	(KeyUsagePurpose::DataEncipherment, "dataEncipherment"),
+	(KeyUsagePurpose::KeyAgreement, "keyAgreement"),
+	(KeyUsagePurpose::KeyCertSign, "keyCertSign"),
+	(KeyUsagePurpose::CrlSign, "cRLSign"),
+	(KeyUsagePurpose::EncipherOnly, "encipherOnly"),
+	(KeyUsagePurpose::DecipherOnly, "decipherOnly"),
+];
+
+static EXTENDED_KEY_USAGES :&[(ExtendedKeyUsagePurpose, &str)] = &[
+	(ExtendedKeyUsagePurpose::Any, "anyExtendedKeyUsage"),
+	(ExtendedKeyUsagePurpose::ServerAuth, "serverAuth"),
+	(ExtendedKeyUsagePurpose::ClientAuth, "clientAuth"),
+	(ExtendedKeyUsagePurpose::CodeSigning, "codeSigning"),
+	(ExtendedKeyUsagePurpose::EmailProtection, "emailProtection"),
+	(ExtendedKeyUsagePurpose::TimeStamping, "timeStamping"),
+	(ExtendedKeyUsagePurpose::OcspSigning, "OCSPSigning"),
+];
+
+fn extended_key_usages(value :&[u8]) -> Option<String> {
+	let oids = yasna::parse_der(value, |reader| {
+		reader.collect_sequence_of(|reader| reader.read_oid())
+	}).ok()?;
+	let names = oids.iter()
+		.map(|oid| {
+			EXTENDED_KEY_USAGES.iter()
+				.find(|(purpose, _)| purpose.oid() == oid.components().as_slice())
+				.map(|(_, name)| name.to_string())
+				.unwrap_or_else(|| dotted(oid.components()))
+		})
+		.collect::<Vec<_>>();
+	Some(names.join(", "))
+}
+
+/// Extracts the DER encoded extensions from the value of an extensionRequest attribute
+fn parse_extension_request(value :&[u8]) -> Option<Vec<Vec<u8>>> {
+	yasna::parse_der(value, |reader| {
+		let mut extensions = Vec::new();
+		reader.read_set_of(|reader| {
+			extensions = reader.collect_sequence_of(|reader| reader.read_der())?;
+			Ok(())
+		})?;
+		Ok(extensions)
+	}).ok()
+}
+
+fn general_names(names :&[GeneralName]) -> String {
+	names.iter()
+		.map(|name| match name {
+			GeneralName::DNSName(name) => format!("DNS:{}", name),
+			GeneralName::RFC822Name(name) => format!("email:{}", name),
+			GeneralName::URI(name) => format!("URI:{}", name),
+			GeneralName::DirectoryName(name) => format!("DirName:{}", rfc4514(name)),
+			GeneralName::IPAddress(bytes) => {
+				if let Ok(addr) = ip_addr_from_bytes(bytes) {
+					format!("IP:{}", addr)
+				} else if let Ok(subnet) = cidr_subnet_from_bytes(bytes) {
+					format!("IP:{}", subnet)
+				} else {
+					format!("IP:{}", hex(bytes, ":"))
+				}
+			},
+			GeneralName::RegisteredID(oid) => format!("RID:{}", dotted(&oid_components(oid))),
+			GeneralName::OtherName(oid, _) => format!("othername:{}", dotted(&oid_components(oid))),
+			GeneralName::X400Address(_) => "X400Address".to_string(),
+			GeneralName::EDIPartyName(_) => "EDIPartyName".to_string(),
+		})
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/// Formats the name as specified by RFC 4514, i.e. starting with the last RDN
+fn rfc4514(name :&X509Name) -> String {
+	let rdns = name.iter_rdn()
+		.map(|rdn| {
+			rdn.iter()
+				.map(|attr| {
+					let oid = oid_components(attr.attr_type());
+					let attr_type = match DnType::from_oid(&oid) {
+						DnType::CountryName => "C".to_string(),
+						DnType::LocalityName => "L".to_string(),
+						DnType::StateOrProvinceName => "ST".to_string(),
+						DnType::OrganizationName => "O".to_string(),
+						DnType::OrganizationalUnitName => "OU".to_string(),
+						DnType::CommonName => "CN".to_string(),
+						_ => dotted(&oid),
+					};
+					let value = match attr.as_str() {
+						Ok(value) => escape_rfc4514(value),
+						Err(_) => format!("#{}", attr.as_slice()
+							.map(|bytes| hex(bytes, ""))
+							.unwrap_or_default()),
+					};
+					format!("{}={}", attr_type, value)
+				})
+				.collect::<Vec<_>>()
+				.join("+")
+		})
+		.collect::<Vec<_>>();
+	rdns.into_iter().rev().collect::<Vec<_>>().join(",")
+}
+
+fn escape_rfc4514(value :&str) -> String {
+	let last = value.chars().count().saturating_sub(1);
+	let mut escaped = String::new();
+	for (i, c) in value.chars().enumerate() {
+		match c {
+			'"' | '+' | ',' | ';' | '<' | '>' | '\\' => escaped.push('\\'),
+			'#' if i == 0 => escaped.push('\\'),
+			' ' if i == 0 || i == last => escaped.push('\\'),
+			'\0' => {
+				escaped.push_str("\\00");
+				continue;
+			},
+			_ => (),
+		}
+		escaped.push(c);
+	}
+	escaped
+}
+
+fn signature_algorithm(alg :&AlgorithmIdentifier) -> String {
+	let oid = oid_components(&alg.algorithm);
+	match SignatureAlgorithm::from_oid(&oid) {
+		Ok(alg) => format!("{:?}", alg),
+		Err(_) => dotted(&oid),
+	}
+}
+
+fn public_key(spki :&SubjectPublicKeyInfo) -> String {
+	let mut oids = vec![oid_components(&spki.algorithm.algorithm)];
+	if let Some(curve) = spki.algorithm.parameters.as_ref().and_then(|params| params.as_oid().ok()) {
+		oids.push(oid_components(curve));
+	}
+	let key_types :[(&SignatureAlgorithm, &str); 4] = [
+		(&PKCS_ECDSA_P256_SHA256, "ECDSA P-256"),
+		(&PKCS_ECDSA_P384_SHA384, "ECDSA P-384"),
+		(&PKCS_ED25519, "Ed25519"),
+		(&PKCS_RSA_SHA256, "RSA"),
+	];
+	match key_types.iter().find(|(alg, _)| alg.has_key_oids(&oids)) {
+		Some((alg, name)) if *alg == &PKCS_RSA_SHA256 => {
+			match spki.parsed() {
+				Ok(x509_parser::public_key::PublicKey::RSA(key)) => {
+					format!("{} ({} bits)", name, bit_length(key.modulus))
+				},
+				_ => name.to_string(),
+			}
+		},
+		Some((_, name)) => name.to_string(),
+		None => dotted(&oids[0]),
+	}
+}
+
+fn bit_length(integer :&[u8]) -> usize {
+	match integer.iter().position(|b| *b != 0) {
+		Some(i) => (integer.len() - i) * 8 - integer[i].leading_zeros() as usize,
+		None => 0,
+	}
+}
+
+fn time(time :&ASN1Time) -> String {
+	let dt = time.to_datetime();
+	format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", dt.year(), dt.month() as u8, dt.day(),
+		dt.hour(), dt.minute(), dt.second())
+}
+
+fn oid_components(oid :&Oid) -> Vec<u64> {
+	oid.iter()
+		.map(|components| components.collect())
+		.unwrap_or_default()
+}
+
+fn dotted(components :&[u64]) -> String {
+	components.iter()
+		.map(|component| component.to_string())
+		.collect::<Vec<_>>()
+		.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_escape_rfc4514() {
+		assert_eq!(escape_rfc4514("Crab widgits SE"), "Crab widgits SE");
+		assert_eq!(escape_rfc4514("Crabs, Inc."), "Crabs\\, Inc.");
+		assert_eq!(escape_rfc4514("#1 crab "), "\\#1 crab\\ ");
+		assert_eq!(escape_rfc4514("a+b=\"c\""), "a\\+b=\\\"c\\\"");
+	}
+
+	#[test]
+	fn test_bit_length() {
+		assert_eq!(bit_length(&[0x00, 0x80, 0x00]), 16);
+		assert_eq!(bit_length(&[0x01, 0x00]), 9);
+		assert_eq!(bit_length(&[0x00]), 0);
+	}
+}
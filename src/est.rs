@@ -0,0 +1,302 @@
+//! Server side of Enrollment over Secure Transport (EST, [RFC 7030](https://tools.ietf.org/html/rfc7030))
+//!
+//! [`EstServer`] turns EST requests into responses without doing any I/O itself,
+//! so it can be plugged into any HTTP framework: pass the method, path, content
+//! type and body of each request under `/.well-known/est/` to [`EstServer::handle`],
+//! and write the returned status, headers and body. TLS and client
+//! authentication are left to the HTTP server.
+//!
+//! The `/cacerts`, `/simpleenroll`, `/simplereenroll` and `/csrattrs` operations
+//! are supported, optionally below a CA label like `/.well-known/est/devices/simpleenroll`.
+//! Bodies are base64 encoded DER, and certificates are returned as
+//! certs-only PKCS#7 structures.
+//!
+//! For SCEP ([RFC 8894](https://tools.ietf.org/html/rfc8894)) clients,
+//! [`EstServer::handle_scep`] answers the `GetCACaps` and `GetCACert` operations,
+//! so that they can fetch the CA certificates. Enrollment via `PKIOperation`
+//! is not supported: its messages are encrypted to the CA's RSA key, and
+//! *ring* can't decrypt with RSA keys.
+
+use time::Duration;
+use yasna::models::ObjectIdentifier;
+use crate::{random_serial_number, serialize_certs_only_pkcs7_der, Certificate,
+	CertificateSigningRequest, ExtendedKeyUsagePurpose, KeyUsagePurpose, RcgenError, SigningRules};
+
+/// Options for the [`EstServer`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct EstServerOptions {
+	/// Validity of issued certificates, defaults to 365 days
+	pub validity :Duration,
+	/// Key usages of issued certificates, defaults to `DigitalSignature` and `KeyEncipherment`
+	pub key_usages :Vec<KeyUsagePurpose>,
+	/// Extended key usages of issued certificates, defaults to `ClientAuth`
+	pub extended_key_usages :Vec<ExtendedKeyUsagePurpose>,
+	/// DER encoded certificates returned by `/cacerts` and SCEP `GetCACert`
+	/// in addition to the CA's certificate, e.g. the certificates up to the root CA
+	pub ca_certificates :Vec<Vec<u8>>,
+	/// OIDs of the attributes that `/csrattrs` asks clients to include in their CSRs
+	///
+	/// If empty, `/csrattrs` responds with 204 No Content.
+	pub csr_attributes :Vec<Vec<u64>>,
+	/// Rules that CSRs have to pass, applied after the validity and usages
+	/// of these options are set
+	///
+	/// Rejected CSRs get a 403 response.
+	pub signing_rules :Option<SigningRules>,
+}
+
+impl Default for EstServerOptions {
+	fn default() -> Self {
+		EstServerOptions {
+			validity : Duration::days(365),
+			key_usages : vec![KeyUsagePurpose::DigitalSignature, KeyUsagePurpose::KeyEncipherment],
+			extended_key_usages : vec![ExtendedKeyUsagePurpose::ClientAuth],
+			ca_certificates : Vec::new(),
+			csr_attributes : Vec::new(),
+			signing_rules : None,
+		}
+	}
+}
+
+/// An HTTP request to an EST server
+#[derive(Debug, Clone, Copy)]
+pub struct EstRequest<'a> {
+	/// The HTTP method, like `GET` or `POST`
+	pub method :&'a str,
+	/// The path of the request, like `/.well-known/est/simpleenroll`
+	pub path :&'a str,
+	/// The value of the Content-Type header, if any
+	pub content_type :Option<&'a str>,
+	/// The body of the request
+	pub body :&'a [u8],
+}
+
+/// An HTTP response of an EST server
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EstResponse {
+	/// The HTTP status code
+	pub status :u16,
+	/// The headers of the response, including Content-Type
+	pub headers :Vec<(&'static str, String)>,
+	/// The body of the response
+	pub body :Vec<u8>,
+}
+
+impl EstResponse {
+	fn base64(content_type :&str, der :&[u8]) -> Self {
+		EstResponse {
+			status : 200,
+			headers : vec![
+				("Content-Type", content_type.to_string()),
+				("Content-Transfer-Encoding", "base64".to_string()),
+			],
+			body : base64::encode(der).into_bytes(),
+		}
+	}
+	fn error(status :u16, message :&str) -> Self {
+		EstResponse {
+			status,
+			headers : vec![("Content-Type", "text/plain".to_string())],
+			body : message.as_bytes().to_vec(),
+		}
+	}
+	/// Returns the value of the header with the given name, ignoring case
+	pub fn header(&self, name :&str) -> Option<&str> {
+		self.headers.iter()
+			.find(|(n, _)| n.eq_ignore_ascii_case(name))
+			.map(|(_, v)| v.as_str())
+	}
+}
+
+/// An EST server that issues certificates from an rcgen CA
+///
+/// Issued certificates keep the subject and subject alternative names of the CSR.
+///
+/// ```
+/// # use rcgen::{Certificate, CertificateParams, EstRequest, EstServer};
+/// let ca = Certificate::from_params(CertificateParams::root_ca("Crab EST CA")).unwrap();
+/// let ca_der = ca.serialize_der().unwrap();
+/// let server = EstServer::new(ca, ca_der, Default::default());
+/// let response = server.handle(&EstRequest {
+/// 	method : "GET",
+/// 	path : "/.well-known/est/cacerts",
+/// 	content_type : None,
+/// 	body : &[],
+/// });
+/// assert_eq!(response.status, 200);
+/// ```
+///
+/// *This type is only available if rcgen is built with the "est" feature*
+pub struct EstServer {
+	ca :Certificate,
+	ca_der :Vec<u8>,
+	options :EstServerOptions,
+}
+
+impl EstServer {
+	/// Creates a server issuing certificates from the CA
+	///
+	/// `ca_der` is the DER encoded certificate of the CA, as returned by `/cacerts`.
+	pub fn new(ca :Certificate, ca_der :Vec<u8>, options :EstServerOptions) -> Self {
+		EstServer {
+			ca,
+			ca_der,
+			options,
+		}
+	}
+	/// Dispatches the request to the operation given by the last segment of its path
+	///
+	/// Unknown operations get a 404 response, and requests with the wrong
+	/// method for the operation a 405 response.
+	pub fn handle(&self, request :&EstRequest<'_>) -> EstResponse {
+		let path = request.path.split('?').next().unwrap_or_default();
+		let operation = match path.strip_prefix("/.well-known/est/") {
+			Some(rest) if rest.split('/').count() <= 2 => rest.rsplit('/').next().unwrap_or_default(),
+			_ => return EstResponse::error(404, "not found"),
+		};
+		let method = match operation {
+			"cacerts" | "csrattrs" => "GET",
+			"simpleenroll" | "simplereenroll" => "POST",
+			_ => return EstResponse::error(404, "not found"),
+		};
+		if request.method != method {
+			let mut response = EstResponse::error(405, "method not allowed");
+			response.headers.push(("Allow", method.to_string()));
+			return response;
+		}
+		match operation {
+			"cacerts" => self.cacerts(),
+			"csrattrs" => self.csrattrs(),
+			_ => self.simple_enroll(request.content_type, request.body),
+		}
+	}
+	/// Dispatches a SCEP request to the operation given by the `operation`
+	/// query parameter, ignoring the path
+	///
+	/// `PKIOperation` requests get a 501 response, requests without or with
+	/// an unknown operation a 400 response, and requests with other methods
+	/// than `GET` a 405 response.
+	pub fn handle_scep(&self, request :&EstRequest<'_>) -> EstResponse {
+		let query = request.path.split_once('?').map(|(_, query)| query).unwrap_or_default();
+		let operation = query.split('&')
+			.find_map(|parameter| parameter.strip_prefix("operation="))
+			.unwrap_or_default();
+		match (operation, request.method) {
+			("PKIOperation", _) => EstResponse::error(501, "PKIOperation is not supported"),
+			("GetCACaps", "GET") => self.scep_get_ca_caps(),
+			("GetCACert", "GET") => self.scep_get_ca_cert(),
+			("GetCACaps", _) | ("GetCACert", _) => {
+				let mut response = EstResponse::error(405, "method not allowed");
+				response.headers.push(("Allow", "GET".to_string()));
+				response
+			},
+			_ => EstResponse::error(400, "unknown SCEP operation"),
+		}
+	}
+	/// Answers the SCEP `GetCACaps` operation, as specified in
+	/// [RFC 8894 section 3.5.2](https://tools.ietf.org/html/rfc8894#section-3.5.2)
+	///
+	/// As `PKIOperation` is not supported, only the `SHA-256` capability is advertised.
+	pub fn scep_get_ca_caps(&self) -> EstResponse {
+		EstResponse {
+			status : 200,
+			headers : vec![("Content-Type", "text/plain".to_string())],
+			body : b"SHA-256\n".to_vec(),
+		}
+	}
+	/// Answers the SCEP `GetCACert` operation, as specified in
+	/// [RFC 8894 section 4.2](https://tools.ietf.org/html/rfc8894#section-4.2)
+	///
+	/// Without further configured CA certificates, the response is the DER encoded
+	/// certificate of the CA. Otherwise it's a certs-only PKCS#7 structure with
+	/// the CA's certificate followed by the configured ones.
+	pub fn scep_get_ca_cert(&self) -> EstResponse {
+		let (content_type, body) = if self.options.ca_certificates.is_empty() {
+			("application/x-x509-ca-cert", self.ca_der.clone())
+		} else {
+			let mut certificates = vec![self.ca_der.as_slice()];
+			certificates.extend(self.options.ca_certificates.iter().map(|cert| cert.as_slice()));
+			("application/x-x509-ca-ra-cert", serialize_certs_only_pkcs7_der(&certificates))
+		};
+		EstResponse {
+			status : 200,
+			headers : vec![("Content-Type", content_type.to_string())],
+			body,
+		}
+	}
+	/// Answers `/cacerts` with the CA's certificate and the configured CA certificates,
+	/// as specified in [RFC 7030 section 4.1](https://tools.ietf.org/html/rfc7030#section-4.1)
+	pub fn cacerts(&self) -> EstResponse {
+		let mut certificates = vec![self.ca_der.as_slice()];
+		certificates.extend(self.options.ca_certificates.iter().map(|cert| cert.as_slice()));
+		EstResponse::base64("application/pkcs7-mime", &serialize_certs_only_pkcs7_der(&certificates))
+	}
+	/// Answers `/csrattrs` with the configured attribute OIDs, as specified in
+	/// [RFC 7030 section 4.5](https://tools.ietf.org/html/rfc7030#section-4.5)
+	pub fn csrattrs(&self) -> EstResponse {
+		if self.options.csr_attributes.is_empty() {
+			return EstResponse {
+				status : 204,
+				headers : Vec::new(),
+				body : Vec::new(),
+			};
+		}
+		let der = yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				for oid in self.options.csr_attributes.iter() {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(oid));
+				}
+			});
+		});
+		EstResponse::base64("application/csrattrs", &der)
+	}
+	/// Answers `/simpleenroll` and `/simplereenroll` with a certificate for the
+	/// base64 encoded CSR in the body, as specified in
+	/// [RFC 7030 section 4.2](https://tools.ietf.org/html/rfc7030#section-4.2)
+	///
+	/// Invalid CSRs get a 400 response, CSRs rejected by the signing rules
+	/// a 403 response, and other content types than `application/pkcs10` a 415 response.
+	pub fn simple_enroll(&self, content_type :Option<&str>, body :&[u8]) -> EstResponse {
+		if let Some(content_type) = content_type {
+			let media_type = content_type.split(';').next().unwrap_or_default().trim();
+			if !media_type.eq_ignore_ascii_case("application/pkcs10") {
+				return EstResponse::error(415, "the CSR must have the application/pkcs10 content type");
+			}
+		}
+		// The base64 encoding may contain line breaks
+		let body = body.iter()
+			.filter(|b| !b.is_ascii_whitespace())
+			.copied()
+			.collect::<Vec<_>>();
+		let csr = match base64::decode(&body) {
+			Ok(csr) => csr,
+			Err(_) => return EstResponse::error(400, "invalid base64 encoding"),
+		};
+		let mut csr = match CertificateSigningRequest::from_der(&csr) {
+			Ok(csr) => csr,
+			Err(e) => return EstResponse::error(400, &e.to_string()),
+		};
+		let params = &mut csr.params;
+		params.valid_for(self.options.validity);
+		params.serial_number = match random_serial_number() {
+			Ok(serial) => Some(serial),
+			Err(e) => return EstResponse::error(500, &e.to_string()),
+		};
+		params.key_usages = self.options.key_usages.clone();
+		params.extended_key_usages = self.options.extended_key_usages.clone();
+		params.use_authority_key_identifier_extension = true;
+		if let Some(rules) = &self.options.signing_rules {
+			match csr.apply_policy(rules) {
+				Ok(()) => (),
+				Err(e @ RcgenError::PolicyViolation(_)) => return EstResponse::error(403, &e.to_string()),
+				Err(e) => return EstResponse::error(500, &e.to_string()),
+			}
+		}
+		match csr.serialize_der_with_signer(&self.ca) {
+			Ok(der) => EstResponse::base64("application/pkcs7-mime; smime-type=certs-only",
+				&serialize_certs_only_pkcs7_der(&[&der])),
+			Err(e) => EstResponse::error(500, &e.to_string()),
+		}
+	}
+}
@@ -0,0 +1,151 @@
+use ring::digest;
+use crate::{Certificate, ChainCertificate, KeyPair, PublicKey, RcgenError};
+
+/// Digest algorithm used to compute fingerprints
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FingerprintAlgorithm {
+	/// SHA-1, as still commonly used to look up certificates
+	Sha1,
+	/// SHA-256
+	Sha256,
+	/// SHA-384
+	Sha384,
+	/// SHA-512
+	Sha512,
+}
+
+impl FingerprintAlgorithm {
+	/// Computes the fingerprint of the given DER encoded data,
+	/// e.g. of a certificate
+	///
+	/// ```
+	/// # use rcgen::FingerprintAlgorithm;
+	/// let cert = rcgen::generate_simple_self_signed(vec!["crabs.crabs".to_string()]).unwrap();
+	/// let der = cert.serialize_der().unwrap();
+	/// assert_eq!(FingerprintAlgorithm::Sha256.fingerprint(&der).len(), 32);
+	/// ```
+	pub fn fingerprint(&self, der :&[u8]) -> Vec<u8> {
+		let alg = match self {
+			FingerprintAlgorithm::Sha1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+			FingerprintAlgorithm::Sha256 => &digest::SHA256,
+			FingerprintAlgorithm::Sha384 => &digest::SHA384,
+			FingerprintAlgorithm::Sha512 => &digest::SHA512,
+		};
+		digest::digest(alg, der).as_ref().to_vec()
+	}
+}
+
+/// Computes the pin as used by HPKP and Chrome, the base64 encoded
+/// SHA-256 digest of the DER encoded SubjectPublicKeyInfo
+fn spki_pin(public_key_der :&[u8]) -> String {
+	base64(&FingerprintAlgorithm::Sha256.fingerprint(public_key_der))
+}
+
+impl Certificate {
+	/// Computes the fingerprint of the self signed certificate, as
+	/// serialized by [`serialize_der`](Self::serialize_der)
+	///
+	/// The certificate is serialized anew on every call, so the fingerprint
+	/// changes for algorithms with randomized signatures like ECDSA. Use
+	/// [`FingerprintAlgorithm::fingerprint`] on the serialized certificate to
+	/// get the fingerprint of a particular serialization.
+	///
+	/// ```
+	/// # use rcgen::{Certificate, CertificateParams, FingerprintAlgorithm};
+	/// let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+	/// params.alg = &rcgen::PKCS_ED25519;
+	/// let cert = Certificate::from_params(params).unwrap();
+	/// let der = cert.serialize_der().unwrap();
+	/// assert_eq!(cert.fingerprint(FingerprintAlgorithm::Sha256).unwrap(),
+	/// 	FingerprintAlgorithm::Sha256.fingerprint(&der));
+	/// ```
+	pub fn fingerprint(&self, alg :FingerprintAlgorithm) -> Result<Vec<u8>, RcgenError> {
+		Ok(alg.fingerprint(&self.serialize_der()?))
+	}
+	/// Computes the fingerprint of the certificate's SubjectPublicKeyInfo
+	///
+	/// Unlike the [fingerprint of the certificate](Self::fingerprint),
+	/// it doesn't depend on the signature.
+	pub fn spki_fingerprint(&self, alg :FingerprintAlgorithm) -> Vec<u8> {
+		self.key_pair.spki_fingerprint(alg)
+	}
+	/// Computes the base64 encoded SHA-256 pin of the certificate's SubjectPublicKeyInfo,
+	/// as used by HPKP and Chrome
+	pub fn spki_pin(&self) -> String {
+		self.key_pair.spki_pin()
+	}
+}
+
+impl ChainCertificate {
+	/// Computes the fingerprint of the certificate, as signed by its issuer
+	pub fn fingerprint(&self, alg :FingerprintAlgorithm) -> Vec<u8> {
+		alg.fingerprint(&self.der)
+	}
+}
+
+impl KeyPair {
+	/// Computes the fingerprint of the key pair's SubjectPublicKeyInfo
+	pub fn spki_fingerprint(&self, alg :FingerprintAlgorithm) -> Vec<u8> {
+		alg.fingerprint(&self.public_key_der())
+	}
+	/// Computes the base64 encoded SHA-256 pin of the key pair's SubjectPublicKeyInfo,
+	/// as used by HPKP and Chrome
+	///
+	/// ```
+	/// # use rcgen::KeyPair;
+	/// let key_pair = KeyPair::generate(&rcgen::PKCS_ED25519).unwrap();
+	/// let pin = key_pair.spki_pin();
+	/// assert_eq!(pin.len(), 44);
+	/// assert!(pin.ends_with('='));
+	/// ```
+	pub fn spki_pin(&self) -> String {
+		spki_pin(&self.public_key_der())
+	}
+}
+
+impl PublicKey {
+	/// Computes the fingerprint of the SubjectPublicKeyInfo
+	pub fn spki_fingerprint(&self, alg :FingerprintAlgorithm) -> Vec<u8> {
+		alg.fingerprint(&self.public_key_der())
+	}
+	/// Computes the base64 encoded SHA-256 pin of the SubjectPublicKeyInfo,
+	/// as used by HPKP and Chrome
+	pub fn spki_pin(&self) -> String {
+		spki_pin(&self.public_key_der())
+	}
+}
+
+/// Encodes the data with the standard base64 alphabet, including padding
+fn base64(data :&[u8]) -> String {
+	const ALPHABET :&[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+	for chunk in data.chunks(3) {
+		let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+		let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	encoded
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_base64() {
+		// Test vectors from RFC 4648 section 10
+		assert_eq!(base64(b""), "");
+		assert_eq!(base64(b"f"), "Zg==");
+		assert_eq!(base64(b"fo"), "Zm8=");
+		assert_eq!(base64(b"foo"), "Zm9v");
+		assert_eq!(base64(b"foob"), "Zm9vYg==");
+		assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+		assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+	}
+}
@@ -43,7 +43,7 @@ use ring::signature::{self, EcdsaSigningAlgorithm, EdDSAParameters};
 use yasna::DERWriter;
 use yasna::models::{GeneralizedTime, UTCTime};
 use yasna::tags::{TAG_BMPSTRING, TAG_TELETEXSTRING, TAG_UNIVERSALSTRING};
-use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time};
 use std::collections::HashMap;
 use std::fmt;
 use std::convert::TryFrom;
@@ -52,6 +52,71 @@ use std::net::IpAddr;
 use std::str::FromStr;
 use std::hash::{Hash, Hasher};
 
+mod acme;
+#[cfg(feature = "acme-server")]
+pub mod acme_server;
+mod attr_cert;
+mod authority;
+mod chain;
+mod cms;
+#[cfg(feature = "serde")]
+mod config;
+mod crl;
+mod ct;
+#[cfg(feature = "x509-parser")]
+mod dump;
+#[cfg(feature = "est")]
+pub mod est;
+mod fingerprint;
+mod lint;
+mod name_constraints;
+mod profiles;
+#[cfg(feature = "x509-parser")]
+mod renew;
+mod signing_policy;
+mod tsa;
+#[cfg(feature = "encryption")]
+mod pbes2;
+#[cfg(feature = "x509-parser")]
+mod verify;
+#[cfg(feature = "encryption")]
+mod pkcs12;
+
+pub use crate::acme::{generate_tls_alpn_01_certificate, AcmeIdentifier};
+#[cfg(feature = "acme-server")]
+pub use crate::acme_server::{AcmeTestServer, AcmeTestServerOptions, ChallengeValidation};
+pub use crate::attr_cert::{AttributeCertificate, AttributeCertificateHolder,
+	AttributeCertificateParams, CertificateAttribute};
+pub use crate::authority::{CertificateAuthority, CertificateStore, IssuedCertificate,
+	MemoryCertificateStore, Revocation};
+#[cfg(feature = "x509-parser")]
+pub use crate::authority::FileCertificateStore;
+pub use crate::chain::{CertificateChain, ChainCertificate, PkiBuilder};
+pub use crate::cms::{serialize_certs_only_pkcs7_der, CmsSignedDataParams};
+#[cfg(feature = "pem")]
+pub use crate::cms::serialize_certs_only_pkcs7_pem;
+#[cfg(feature = "serde")]
+pub use crate::config::{CertificateConfig, GeneratedPki, PkiConfig};
+pub use crate::ct::{CtLog, CtLogEntry, SignedCertificateTimestamp};
+pub use crate::crl::{CertificateRevocationList, CertificateRevocationListParams,
+	RevocationReason, RevokedCertParams};
+#[cfg(feature = "x509-parser")]
+pub use crate::dump::X509Dump;
+#[cfg(feature = "est")]
+pub use crate::est::{EstRequest, EstResponse, EstServer, EstServerOptions};
+pub use crate::fingerprint::FingerprintAlgorithm;
+pub use crate::lint::{Lint, LintSeverity};
+#[cfg(feature = "encryption")]
+pub use crate::pbes2::{KeyDerivationFunction, KeyEncryptionCipher, KeyEncryptionParams};
+#[cfg(feature = "encryption")]
+pub use crate::pkcs12::{Pkcs12Contents, Pkcs12Params};
+#[cfg(feature = "x509-parser")]
+pub use crate::renew::{cross_sign_certificate, rekey_certificate, renew_certificate};
+pub use crate::signing_policy::{PolicyViolation, SigningPolicy, SigningRules};
+pub use crate::tsa::{TimeStampAuthority, TimeStampAuthorityParams, TimeStampRequest};
+#[cfg(feature = "x509-parser")]
+pub use crate::verify::{verify_certificate_chain, PathValidationError, PathValidationOptions};
+
 /// A self signed certificate together with signing keys
 pub struct Certificate {
 	params :CertificateParams,
@@ -143,11 +208,30 @@ const OID_EXT_KEY_USAGE :&[u64] = &[2, 5, 29, 37];
 /// https://tools.ietf.org/html/rfc5280#section-4.2.1.10
 const OID_NAME_CONSTRAINTS :&[u64] = &[2, 5, 29, 30];
 
+// https://tools.ietf.org/html/rfc5280#section-5.2.3
+const OID_CRL_NUMBER :&[u64] = &[2, 5, 29, 20];
+
+// https://tools.ietf.org/html/rfc5280#section-5.3.1
+const OID_CRL_REASONS :&[u64] = &[2, 5, 29, 21];
+
 // id-pe-acmeIdentifier in
 // https://www.iana.org/assignments/smi-numbers/smi-numbers.xhtml#smi-numbers-1.3.6.1.5.5.7.1
 const OID_PE_ACME :&[u64] = &[1, 3, 6, 1, 5, 5, 7, 1, 31];
 
+// https://tools.ietf.org/html/rfc5755#section-4.4.5
+const OID_AT_ROLE :&[u64] = &[2, 5, 4, 72];
+
+// https://tools.ietf.org/html/rfc5755#section-4.4.4
+const OID_ACA_GROUP :&[u64] = &[1, 3, 6, 1, 5, 5, 7, 10, 4];
+
+// https://tools.ietf.org/html/rfc6962#section-3.1
+const OID_CT_PRECERT_POISON :&[u64] = &[1, 3, 6, 1, 4, 1, 11129, 2, 4, 3];
+
+// https://tools.ietf.org/html/rfc6962#section-3.3
+const OID_CT_SCT_LIST :&[u64] = &[1, 3, 6, 1, 4, 1, 11129, 2, 4, 2];
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 #[non_exhaustive]
 /// The type of subject alt name
@@ -172,6 +256,10 @@ impl SanType {
 			x509_parser::extensions::GeneralName::URI(name) => {
 				SanType::URI((*name).into())
 			}
+			x509_parser::extensions::GeneralName::IPAddress(octets) => {
+				SanType::IpAddress(crate::verify::ip_addr_from_bytes(octets)
+					.or(Err(RcgenError::InvalidNameType))?)
+			}
 			_ => return Err(RcgenError::InvalidNameType),
 		})
 	}
@@ -194,6 +282,7 @@ impl SanType {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 #[non_exhaustive]
 /// General Subtree type.
@@ -260,6 +349,7 @@ impl CidrSubnet {
 	/// // https://tools.ietf.org/html/rfc5280#page-42
 	/// let subnet = CidrSubnet::from_str("192.0.2.0/24").unwrap();
 	/// assert_eq!(subnet, CidrSubnet::V4([0xC0, 0x00, 0x02, 0x00], [0xFF, 0xFF, 0xFF, 0x00]));
+	/// assert_eq!(subnet.to_string(), "192.0.2.0/24");
 	/// ```
 	pub fn from_str(s :&str) -> Result<Self, ()> {
 		let mut iter = s.split('/');
@@ -320,7 +410,22 @@ impl CidrSubnet {
 	}
 }
 
+/// Formats the subnet in the addr/prefix notation, e.g. `192.0.2.0/24`
+impl fmt::Display for CidrSubnet {
+	fn fmt(&self, f :&mut fmt::Formatter) -> fmt::Result {
+		match self {
+			CidrSubnet::V4(addr, mask) => {
+				write!(f, "{}/{}", IpAddr::from(*addr), u32::from_be_bytes(*mask).count_ones())
+			},
+			CidrSubnet::V6(addr, mask) => {
+				write!(f, "{}/{}", IpAddr::from(*addr), u128::from_be_bytes(*mask).count_ones())
+			},
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 /// The attribute type of a distinguished name entry
 pub enum DnType {
@@ -370,6 +475,7 @@ impl DnType {
 
 /// A distinguished name entry
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum DnValue {
 	/// A string of characters from the T.61 character set
@@ -473,7 +579,7 @@ impl DistinguishedName {
 					dn
 				}
 			} else {
-				panic!("x509-parser distinguished name set is empty");
+				return Err(RcgenError::CouldNotParseCertificate);
 			};
 
 			let attr_type_oid = attr.attr_type().iter()
@@ -492,6 +598,17 @@ impl DistinguishedName {
 		}
 		Ok(dn)
 	}
+	/// Like [`from_name`](Self::from_name), but fails if the name can't be represented
+	/// without changes, e.g. as it repeats an attribute type
+	#[cfg(feature = "x509-parser")]
+	pub(crate) fn from_name_exact(name :&x509_parser::x509::X509Name) -> Result<Self, RcgenError> {
+		let dn = Self::from_name(name)?;
+		let der = yasna::construct_der(|writer| write_distinguished_name(writer, &dn));
+		if der != name.as_raw() {
+			return Err(RcgenError::CouldNotParseCertificate);
+		}
+		Ok(dn)
+	}
 }
 
 /**
@@ -514,13 +631,90 @@ impl <'a> Iterator for DistinguishedNameIterator<'a> {
 }
 
 
-/// A public key, extracted from a CSR
+/// A public key, extracted from a CSR or a SubjectPublicKeyInfo structure
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct PublicKey {
 	raw: Vec<u8>,
 	alg: &'static SignatureAlgorithm,
 }
 
+impl PublicKey {
+	/// Parses the public key from a DER encoded SubjectPublicKeyInfo structure
+	///
+	/// The [`SignatureAlgorithm`] is detected from the AlgorithmIdentifier of the
+	/// SubjectPublicKeyInfo. For RSA keys, where multiple signature algorithms fit,
+	/// [`PKCS_RSA_SHA256`] is chosen. Use
+	/// [`from_spki_der_and_sign_algo`](Self::from_spki_der_and_sign_algo)
+	/// to select a different one.
+	pub fn from_spki_der(spki :&[u8]) -> Result<Self, RcgenError> {
+		let (alg_oids, raw) = Self::parse_spki(spki)?;
+		let alg = SignatureAlgorithm::iter()
+			.find(|alg| alg.has_key_oids(&alg_oids))
+			.ok_or(RcgenError::UnsupportedSignatureAlgorithm)?;
+		Ok(PublicKey { raw, alg })
+	}
+	/// Parses the public key from a SubjectPublicKeyInfo structure
+	/// in the ASCII PEM format, as output by `openssl pkey -pubout`
+	///
+	/// See [`from_spki_der`](Self::from_spki_der) for more details.
+	///
+	/// *This constructor is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn from_spki_pem(pem_str :&str) -> Result<Self, RcgenError> {
+		let spki = pem::parse(pem_str)?;
+		Self::from_spki_der(&spki.contents)
+	}
+	/// Parses the public key from a DER encoded SubjectPublicKeyInfo structure
+	/// using the specified [`SignatureAlgorithm`]
+	///
+	/// Returns an error if the key type doesn't fit the signature algorithm.
+	pub fn from_spki_der_and_sign_algo(spki :&[u8], alg :&'static SignatureAlgorithm) -> Result<Self, RcgenError> {
+		let (alg_oids, raw) = Self::parse_spki(spki)?;
+		if !alg.has_key_oids(&alg_oids) {
+			return Err(RcgenError::CertificateKeyPairMismatch);
+		}
+		Ok(PublicKey { raw, alg })
+	}
+	/// The signature algorithm the key is used with
+	pub fn algorithm(&self) -> &'static SignatureAlgorithm {
+		self.alg
+	}
+	/// Get the raw public key, in the same format as [`KeyPair::public_key_raw`]
+	pub fn public_key_raw(&self) -> &[u8] {
+		&self.raw
+	}
+	/// Return the public key in DER format
+	///
+	/// The key is formatted according to the SubjectPublicKeyInfo struct of
+	/// X.509.
+	/// See [RFC 5280 section 4.1](https://tools.ietf.org/html/rfc5280#section-4.1).
+	pub fn public_key_der(&self) -> Vec<u8> {
+		yasna::construct_der(|writer| self.serialize_public_key_der(writer))
+	}
+	fn parse_spki(spki :&[u8]) -> Result<(Vec<Vec<u64>>, Vec<u8>), RcgenError> {
+		yasna::parse_der(spki, |reader| {
+			reader.read_sequence(|reader| {
+				let alg_oids = reader.next().read_sequence(|reader| {
+					let mut oids = vec![reader.next().read_oid()?.components().clone()];
+					// The parameters are the named curve for EC keys,
+					// and NULL for RSA keys.
+					if let Some(curve) = reader.read_optional(|reader| reader.read_oid())? {
+						oids.push(curve.components().clone());
+					} else {
+						reader.read_optional(|reader| reader.read_null())?;
+					}
+					Ok(oids)
+				})?;
+				let (raw, bits) = reader.next().read_bitvec_bytes()?;
+				if bits != raw.len() * 8 {
+					return Err(yasna::ASN1Error::new(yasna::ASN1ErrorKind::Invalid));
+				}
+				Ok((alg_oids, raw))
+			})
+		}).map_err(|_| RcgenError::CouldNotParsePublicKey)
+	}
+}
+
 impl PublicKeyData for PublicKey {
 	fn alg(&self) -> &SignatureAlgorithm {
 		self.alg
@@ -594,9 +788,17 @@ impl CertificateSigningRequest {
 			public_key: PublicKey { alg, raw },
 		})
 	}
+	/// Checks and adjusts the requested parameters with the signing policy
+	///
+	/// Call this before [`serialize_der_with_signer`](Self::serialize_der_with_signer),
+	/// which signs whatever the CSR asked for. Fails with [`RcgenError::PolicyViolation`]
+	/// if the policy rejects the request.
+	pub fn apply_policy(&mut self, policy :&dyn SigningPolicy) -> Result<(), RcgenError> {
+		policy.apply(&mut self.params)
+	}
 	/// Serializes the requested certificate, signed with another certificate's key, in binary DER format
 	pub fn serialize_der_with_signer(&self, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
-		self.params.serialize_der_with_signer(&self.public_key, ca)
+		self.params.serialize_signed_der(&self.public_key, ca)
 	}
 	/// Serializes the requested certificate, signed with another certificate's key, to the ASCII PEM format
 	///
@@ -605,18 +807,28 @@ impl CertificateSigningRequest {
 	pub fn serialize_pem_with_signer(&self, ca :&Certificate) -> Result<String, RcgenError> {
 		let p = Pem {
 			tag : "CERTIFICATE".to_string(),
-			contents : self.params.serialize_der_with_signer(&self.public_key, ca)?,
+			contents : self.params.serialize_signed_der(&self.public_key, ca)?,
 		};
 		Ok(pem::encode(&p))
 	}
 }
 
 /// Parameters used for certificate generation
+///
+/// With the "serde" feature, the parameters can be (de)serialized, e.g. from
+/// a configuration file. Fields that are missing keep their default values,
+/// dates use the RFC 3339 format, and `alg` is given by the name of one of
+/// the `PKCS_*` statics, like `"PKCS_ECDSA_P256_SHA256"`. The `key_pair`
+/// is never serialized.
 #[allow(missing_docs)]
 #[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct CertificateParams {
+	#[cfg_attr(feature = "serde", serde(with = "crate::config::signature_algorithm"))]
 	pub alg :&'static SignatureAlgorithm,
+	#[cfg_attr(feature = "serde", serde(with = "time::serde::rfc3339"))]
 	pub not_before :OffsetDateTime,
+	#[cfg_attr(feature = "serde", serde(with = "time::serde::rfc3339"))]
 	pub not_after :OffsetDateTime,
 	pub serial_number :Option<u64>,
 	pub subject_alt_names :Vec<SanType>,
@@ -624,9 +836,15 @@ pub struct CertificateParams {
 	pub is_ca :IsCa,
 	pub key_usages :Vec<KeyUsagePurpose>,
 	pub extended_key_usages :Vec<ExtendedKeyUsagePurpose>,
+	/// If `true`, the extended key usage extension is marked critical
+	///
+	/// Defaults to `false`. [`time_stamping`](Self::time_stamping) sets it,
+	/// as RFC 3161 requires.
+	pub extended_key_usages_critical :bool,
 	pub name_constraints :Option<NameConstraints>,
 	pub custom_extensions :Vec<CustomExtension>,
 	/// The certificate's key pair, a new random key pair will be generated if this is `None`
+	#[cfg_attr(feature = "serde", serde(skip))]
 	pub key_pair :Option<KeyPair>,
 	/// If `true` (and not self-signed), the 'Authority Key Identifier' extension will be added to the generated cert
 	pub use_authority_key_identifier_extension :bool,
@@ -634,6 +852,37 @@ pub struct CertificateParams {
 	///
 	/// Defaults to SHA-256.
 	pub key_identifier_method :KeyIdMethod,
+	/// If `true`, [`Certificate::from_params`] refuses parameters for which
+	/// [`lint`](Self::lint) reports errors
+	///
+	/// Warnings are not taken into account. Defaults to `false`.
+	pub strict :bool,
+	/// If `true`, `not_after` is limited to the `not_after` of the issuer
+	/// when signing with another certificate, so that the certificate
+	/// doesn't outlive its issuer
+	///
+	/// If the issuer's validity ends before `not_before`, signing fails with
+	/// [`RcgenError::IssuerValidityEndsBeforeNotBefore`].
+	/// Defaults to `false`.
+	pub cap_validity_to_issuer :bool,
+	/// If `true`, signing with another certificate fails if the subject
+	/// alternative names or the subject are not permitted by the
+	/// [name constraints](Self::name_constraints) of the issuer
+	///
+	/// The names are checked with
+	/// [`NameConstraints::permits_subject_alt_name`] and
+	/// [`NameConstraints::permits_distinguished_name`]. Self-signed
+	/// certificates are not checked. Defaults to `false`.
+	pub enforce_name_constraints :bool,
+	/// Set by [`from_ca_cert_der`](Self::from_ca_cert_der) if the certificate
+	/// has name constraints that can't be represented by [`NameConstraints`]
+	#[cfg_attr(feature = "serde", serde(skip))]
+	unsupported_name_constraints :bool,
+	/// The DER encoded subject to write instead of the
+	/// [`distinguished_name`](Self::distinguished_name), set by
+	/// [`cross_sign_certificate`] to keep the subject unchanged
+	#[cfg_attr(feature = "serde", serde(skip))]
+	raw_subject :Option<Vec<u8>>,
 }
 
 impl Default for CertificateParams {
@@ -653,11 +902,17 @@ impl Default for CertificateParams {
 			is_ca : IsCa::SelfSignedOnly,
 			key_usages : Vec::new(),
 			extended_key_usages : Vec::new(),
+			extended_key_usages_critical : false,
 			name_constraints : None,
 			custom_extensions : Vec::new(),
 			key_pair : None,
 			use_authority_key_identifier_extension : false,
 			key_identifier_method : KeyIdMethod::Sha256,
+			strict : false,
+			cap_validity_to_issuer : false,
+			enforce_name_constraints : false,
+			unsupported_name_constraints : false,
+			raw_subject : None,
 		}
 	}
 }
@@ -684,8 +939,13 @@ impl CertificateParams {
 	/// functions.
 	///
 	/// This function only extracts from the given ca cert the informations
-	/// needed for signing. Any information beyond that is not extracted
-	/// and left to defaults.
+	/// needed for signing, its validity for
+	/// [`cap_validity_to_issuer`](Self::cap_validity_to_issuer), and its name constraints for
+	/// [`enforce_name_constraints`](Self::enforce_name_constraints).
+	/// Any information beyond that is not extracted and left to defaults.
+	/// Name constraints that can't be represented by [`NameConstraints`] are
+	/// only reported when signing with enforcement, with
+	/// [`RcgenError::UnsupportedIssuerNameConstraints`].
 	///
 	/// Will not check if certificate is a ca certificate!
 	///
@@ -700,28 +960,57 @@ impl CertificateParams {
 		let alg = SignatureAlgorithm::from_oid(&alg_oid.collect::<Vec<_>>())?;
 
 		let dn = DistinguishedName::from_name(&x509.tbs_certificate.subject)?;
+		let validity = x509.validity();
+		let (name_constraints, unsupported_name_constraints) = match x509.name_constraints() {
+			Ok(Some(name_constraints)) => match verify::convert_name_constraints(name_constraints.value) {
+				Ok(name_constraints) => (Some(name_constraints), false),
+				Err(_) => (None, true),
+			},
+			Ok(None) => (None, false),
+			Err(_) => (None, true),
+		};
 		Ok(
 			CertificateParams {
 				alg,
+				not_before : validity.not_before.to_datetime(),
+				not_after : validity.not_after.to_datetime(),
 				distinguished_name : dn,
+				name_constraints,
+				unsupported_name_constraints,
 				key_pair : Some(key_pair),
 				.. Default::default()
 			}
 		)
 	}
+	/// Makes the certificate valid from now on, for the given duration
+	///
+	/// ```
+	/// # use rcgen::CertificateParams;
+	/// let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+	/// params.valid_for(time::Duration::hours(12));
+	/// assert_eq!(params.not_after - params.not_before, time::Duration::hours(12));
+	/// ```
+	pub fn valid_for(&mut self, duration :Duration) {
+		let now = OffsetDateTime::now_utc();
+		self.not_before = now;
+		self.not_after = now + duration;
+	}
+	/// Makes the certificate valid from now on, for the given number of days
+	pub fn valid_from_now(&mut self, days :i64) {
+		self.valid_for(Duration::days(days));
+	}
+	/// Moves the start of the validity period back by the given tolerance
+	///
+	/// This keeps certificates usable right after issuance by relying parties
+	/// whose clocks are behind. The end of the validity period is not changed.
+	pub fn backdate(&mut self, tolerance :Duration) {
+		self.not_before -= tolerance;
+	}
 	fn write_subject_alt_names(&self, writer :DERWriter) {
 		Self::write_extension(writer, OID_SUBJECT_ALT_NAME, false, |writer| {
 			writer.write_sequence(|writer| {
 				for san in self.subject_alt_names.iter() {
-					writer.next().write_tagged_implicit(Tag::context(san.tag()), |writer| {
-						match san {
-							SanType::Rfc822Name(name) |
-							SanType::DnsName(name) |
-							SanType::URI(name) => writer.write_ia5_string(name),
-							SanType::IpAddress(IpAddr::V4(addr)) => writer.write_bytes(&addr.octets()),
-							SanType::IpAddress(IpAddr::V6(addr)) => writer.write_bytes(&addr.octets()),
-						}
-					});
+					write_general_name(writer.next(), san);
 				}
 			});
 		});
@@ -774,7 +1063,8 @@ impl CertificateParams {
 
 		});
 	}
-	fn write_cert<K: PublicKeyData>(&self, writer :DERWriter, pub_key: &K, ca :&Certificate) -> Result<(), RcgenError> {
+	fn write_cert<K: PublicKeyData>(&self, writer :DERWriter, pub_key: &K, ca :&Certificate,
+			custom_extensions :&[CustomExtension]) -> Result<(), RcgenError> {
 		writer.write_sequence(|writer| {
 			// Write version
 			writer.next().write_tagged(Tag::context(0), |writer| {
@@ -796,21 +1086,33 @@ impl CertificateParams {
 				// Not before
 				write_dt_utc_or_generalized(writer.next(), self.not_before);
 				// Not after
-				write_dt_utc_or_generalized(writer.next(), self.not_after);
+				let not_after = if self.cap_validity_to_issuer {
+					if ca.params.not_after < self.not_before {
+						return Err(RcgenError::IssuerValidityEndsBeforeNotBefore);
+					}
+					self.not_after.min(ca.params.not_after)
+				} else {
+					self.not_after
+				};
+				write_dt_utc_or_generalized(writer.next(), not_after);
 				Ok::<(), RcgenError>(())
 			})?;
 			// Write subject
-			write_distinguished_name(writer.next(), &self.distinguished_name);
+			match &self.raw_subject {
+				Some(raw_subject) => writer.next().write_der(raw_subject),
+				None => write_distinguished_name(writer.next(), &self.distinguished_name),
+			}
 			// Write subjectPublicKeyInfo
 			pub_key.serialize_public_key_der(writer.next());
 			// write extensions
 			let not_self_signed = ca.key_pair.public_key_raw() != pub_key.raw_bytes();
 			let should_write_exts = (not_self_signed && self.use_authority_key_identifier_extension) ||
 				!self.subject_alt_names.is_empty() ||
+				!self.key_usages.is_empty() ||
 				!self.extended_key_usages.is_empty() ||
 				self.name_constraints.iter().any(|c| !c.is_empty()) ||
 				matches!(self.is_ca, IsCa::Ca(_)) ||
-				!self.custom_extensions.is_empty();
+				!custom_extensions.is_empty();
 			if should_write_exts {
 				writer.next().write_tagged(Tag::context(3), |writer| {
 					writer.write_sequence(|writer| {
@@ -846,19 +1148,7 @@ impl CertificateParams {
 								let mut bits :u16 = 0;
 
 								for entry in self.key_usages.iter() {
-									// Map the index to a value
-									let index = match entry {
-										KeyUsagePurpose::DigitalSignature => 0,
-										KeyUsagePurpose::ContentCommitment => 1,
-										KeyUsagePurpose::KeyEncipherment => 2,
-										KeyUsagePurpose::DataEncipherment => 3,
-										KeyUsagePurpose::KeyAgreement => 4,
-										KeyUsagePurpose::KeyCertSign => 5,
-										KeyUsagePurpose::CrlSign => 6,
-										KeyUsagePurpose::EncipherOnly => 7,
-										KeyUsagePurpose::DecipherOnly => 8,
-									};
-
+									let index = entry.bit_index();
 									bits |= 1 << index;
 								}
 
@@ -887,7 +1177,7 @@ impl CertificateParams {
 
 						// Write extended key usage
 						if !self.extended_key_usages.is_empty() {
-							Self::write_extension(writer.next(), OID_EXT_KEY_USAGE, false, |writer| {
+							Self::write_extension(writer.next(), OID_EXT_KEY_USAGE, self.extended_key_usages_critical, |writer| {
 								writer.write_sequence(|writer| {
 									for usage in self.extended_key_usages.iter() {
 										let oid = ObjectIdentifier::from_slice(usage.oid());
@@ -928,7 +1218,7 @@ impl CertificateParams {
 							});
 						}
 						// Write the custom extensions
-						for ext in &self.custom_extensions {
+						for ext in custom_extensions {
 							writer.next().write_sequence(|writer| {
 								let oid = ObjectIdentifier::from_slice(&ext.oid);
 								writer.next().write_oid(&oid);
@@ -973,21 +1263,53 @@ impl CertificateParams {
 	/// This key identifier is used in the SubjectKeyIdentifier X.509v3 extension.
 	fn key_identifier<K: PublicKeyData>(&self, pub_key: &K) -> Vec<u8> {
 		// Decide which method from RFC 7093 to use
-		let digest_method = match self.key_identifier_method {
+		let digest_method = match &self.key_identifier_method {
 			KeyIdMethod::Sha256 => &digest::SHA256,
 			KeyIdMethod::Sha384 => &digest::SHA384,
 			KeyIdMethod::Sha512 => &digest::SHA512,
+			KeyIdMethod::PreSpecified(key_identifier) => return key_identifier.clone(),
 		};
 		let digest = digest::digest(digest_method, pub_key.raw_bytes());
 		let truncated_digest = &digest.as_ref()[0..20];
 		truncated_digest.to_vec()
 	}
-	fn serialize_der_with_signer<K: PublicKeyData>(&self, pub_key: &K, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+	/// Serializes a certificate for the given public key, signed with another certificate's key, in binary DER format
+	///
+	/// This allows issuing certificates for keys where only the public part
+	/// is known, for example after obtaining it via [`PublicKey::from_spki_der`].
+	/// The [`key_pair`](Self::key_pair) and [`alg`](Self::alg) fields are ignored.
+	pub fn serialize_der_with_signer(&self, pub_key :&PublicKey, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+		self.serialize_signed_der(pub_key, ca)
+	}
+	/// Serializes a certificate for the given public key, signed with another certificate's key, to the ASCII PEM format
+	///
+	/// See [`serialize_der_with_signer`](Self::serialize_der_with_signer) for more details.
+	///
+	/// *This function is only available if rcgen is built with the "pem" feature*
+	#[cfg(feature = "pem")]
+	pub fn serialize_pem_with_signer(&self, pub_key :&PublicKey, ca :&Certificate) -> Result<String, RcgenError> {
+		let p = Pem {
+			tag : "CERTIFICATE".to_string(),
+			contents : self.serialize_signed_der(pub_key, ca)?,
+		};
+		Ok(pem::encode(&p))
+	}
+	fn serialize_signed_der<K: PublicKeyData>(&self, pub_key: &K, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
+		self.serialize_signed_der_with_extensions(pub_key, ca, &self.custom_extensions)
+	}
+	/// Like [`serialize_signed_der`](Self::serialize_signed_der), but with
+	/// the given custom extensions instead of [`custom_extensions`](Self::custom_extensions)
+	fn serialize_signed_der_with_extensions<K: PublicKeyData>(&self, pub_key: &K, ca :&Certificate,
+			custom_extensions :&[CustomExtension]) -> Result<Vec<u8>, RcgenError> {
+		let self_signed = ca.key_pair.public_key_raw() == pub_key.raw_bytes();
+		if self.enforce_name_constraints && !self_signed {
+			self.check_name_constraints(&ca.params)?;
+		}
 		yasna::try_construct_der(|writer| {
 			writer.write_sequence(|writer| {
 
 				let tbs_cert_list_serialized = yasna::try_construct_der(|writer| {
-					self.write_cert(writer, pub_key, ca)?;
+					self.write_cert(writer, pub_key, ca, custom_extensions)?;
 					Ok::<(), RcgenError>(())
 				})?;
 				// Write tbsCertList
@@ -1007,6 +1329,7 @@ impl CertificateParams {
 
 /// Whether the certificate is allowed to sign other certificates
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IsCa {
 	/// The certificate can only sign itself
 	SelfSignedOnly,
@@ -1019,6 +1342,7 @@ pub enum IsCa {
 /// Sets an optional upper limit on the length of the intermediate certificate chain
 /// length allowed for this CA certificate (not including the end entity certificate).
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BasicConstraints {
 	/// No constraint
 	Unconstrained,
@@ -1043,6 +1367,7 @@ impl CertificateParams {
 /// The [NameConstraints extension](https://tools.ietf.org/html/rfc5280#section-4.2.1.10)
 /// (only relevant for CA certificates)
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NameConstraints {
 	/// If non-empty, a whitelist of subtrees that the
 	/// domain has to match.
@@ -1062,6 +1387,7 @@ impl NameConstraints {
 
 /// One of the purposes contained in the [key usage](https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.3) extension
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyUsagePurpose {
 	/// digitalSignature
 	DigitalSignature,
@@ -1083,7 +1409,25 @@ pub enum KeyUsagePurpose {
 	DecipherOnly,
 }
 
+impl KeyUsagePurpose {
+	/// Position of the purpose in the key usage bit string
+	fn bit_index(&self) -> u16 {
+		match self {
+			KeyUsagePurpose::DigitalSignature => 0,
+			KeyUsagePurpose::ContentCommitment => 1,
+			KeyUsagePurpose::KeyEncipherment => 2,
+			KeyUsagePurpose::DataEncipherment => 3,
+			KeyUsagePurpose::KeyAgreement => 4,
+			KeyUsagePurpose::KeyCertSign => 5,
+			KeyUsagePurpose::CrlSign => 6,
+			KeyUsagePurpose::EncipherOnly => 7,
+			KeyUsagePurpose::DecipherOnly => 8,
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// One of the purposes contained in the [extended key usage extension](https://tools.ietf.org/html/rfc5280#section-4.2.1.12)
 pub enum ExtendedKeyUsagePurpose {
 	/// anyExtendedKeyUsage
@@ -1122,6 +1466,7 @@ impl ExtendedKeyUsagePurpose {
 /// A custom extension of a certificate, as specified in
 /// [RFC 5280](https://tools.ietf.org/html/rfc5280#section-4.2)
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CustomExtension {
 	oid :Vec<u64>,
 	critical :bool,
@@ -1135,6 +1480,8 @@ impl CustomExtension {
 	/// as specified in [RFC 8737](https://tools.ietf.org/html/rfc8737#section-3)
 	///
 	/// Panics if the passed `sha_digest` parameter doesn't hold 32 bytes (256 bits).
+	/// See [`try_new_acme_identifier`](Self::try_new_acme_identifier) for a fallible
+	/// version, and [`generate_tls_alpn_01_certificate`] for the entire certificate.
 	pub fn new_acme_identifier(sha_digest :&[u8]) -> Self {
 		assert_eq!(sha_digest.len(), 32, "wrong size of sha_digest");
 		let content = yasna::construct_der(|writer| {
@@ -1177,6 +1524,7 @@ impl CustomExtension {
 /// This allows choice over methods to generate key identifiers
 /// as specified in RFC 7093 section 2.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum KeyIdMethod {
 	/// RFC 7093 method 1
@@ -1185,6 +1533,10 @@ pub enum KeyIdMethod {
 	Sha384,
 	/// RFC 7093 method 3
 	Sha512,
+	/// A fixed key identifier, e.g. the one of an existing certificate
+	///
+	/// Only use this with the key the identifier was created for.
+	PreSpecified(Vec<u8>),
 }
 
 /// Helper to obtain an `OffsetDateTime` from year, month, day values
@@ -1204,6 +1556,33 @@ pub fn date_time_ymd(year :i32, month :u8, day :u8) -> OffsetDateTime {
 	primitive_dt.assume_utc()
 }
 
+/// Helper to obtain an `OffsetDateTime` from year, month, day, hour, minute and second values
+///
+/// The values are assumed to be in UTC. Like [`date_time_ymd`], this is meant
+/// for specifying fixed points in time, e.g. in tests that need reproducible output.
+pub fn date_time_ymd_hms(year :i32, month :u8, day :u8, hour :u8, minute :u8, second :u8) -> OffsetDateTime {
+	let time = Time::from_hms(hour, minute, second).expect("invalid or out-of-range time");
+	date_time_ymd(year, month, day).replace_time(time)
+}
+
+/// Generates a random, positive and non-zero serial number
+///
+/// This is how rcgen picks serial numbers when renewing or issuing
+/// certificates on its own, e.g. in [`renew_certificate`].
+/// The first byte is never zero, so the DER encoding always has eight bytes.
+///
+/// ```
+/// let serial = rcgen::random_serial_number().unwrap();
+/// assert!(serial >> 56 != 0 && serial >> 63 == 0);
+/// ```
+pub fn random_serial_number() -> Result<u64, RcgenError> {
+	use ring::rand::SecureRandom;
+	let mut bytes = [0u8; 8];
+	SystemRandom::new().fill(&mut bytes)?;
+	bytes[0] = (bytes[0] & 0x7f) | 0x01;
+	Ok(u64::from_be_bytes(bytes))
+}
+
 fn dt_strip_nanos(dt :OffsetDateTime) -> OffsetDateTime {
 	// Set nanoseconds to zero
 	// This is needed because the GeneralizedTime serializer would otherwise
@@ -1222,6 +1601,26 @@ fn dt_to_generalized(dt :OffsetDateTime) -> GeneralizedTime {
 	GeneralizedTime::from_datetime(date_time)
 }
 
+/// Formats the bytes as uppercase hexadecimal digits, separated by `separator`
+#[cfg(feature = "x509-parser")]
+fn hex(bytes :&[u8], separator :&str) -> String {
+	bytes.iter()
+		.map(|b| format!("{:02X}", b))
+		.collect::<Vec<_>>()
+		.join(separator)
+}
+
+/// Parses hexadecimal digits without separators
+#[cfg(feature = "x509-parser")]
+fn unhex(s :&str) -> Option<Vec<u8>> {
+	if s.len() % 2 != 0 || !s.is_ascii() {
+		return None;
+	}
+	(0..s.len()).step_by(2)
+		.map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+		.collect()
+}
+
 fn write_dt_utc_or_generalized(writer :DERWriter, dt :OffsetDateTime) {
 	// RFC 5280 requires CAs to write certificate validity dates
 	// below 2050 as UTCTime, and anything starting from 2050
@@ -1239,6 +1638,18 @@ fn write_dt_utc_or_generalized(writer :DERWriter, dt :OffsetDateTime) {
 	}
 }
 
+fn write_general_name(writer :DERWriter, san :&SanType) {
+	writer.write_tagged_implicit(Tag::context(san.tag()), |writer| {
+		match san {
+			SanType::Rfc822Name(name) |
+			SanType::DnsName(name) |
+			SanType::URI(name) => writer.write_ia5_string(name),
+			SanType::IpAddress(IpAddr::V4(addr)) => writer.write_bytes(&addr.octets()),
+			SanType::IpAddress(IpAddr::V6(addr)) => writer.write_bytes(&addr.octets()),
+		}
+	});
+}
+
 fn write_distinguished_name(writer :DERWriter, dn :&DistinguishedName) {
 		writer.write_sequence(|writer| {
 			for (ty, content) in dn.iter() {
@@ -1269,14 +1680,20 @@ fn write_general_subtrees(writer :DERWriter, tag :u64, general_subtrees :&[Gener
 		writer.write_sequence(|writer| {
 			for subtree in general_subtrees.iter() {
 				writer.next().write_sequence(|writer| {
-					writer.next().write_tagged_implicit(Tag::context(subtree.tag()), |writer| {
-						match subtree {
-							GeneralSubtree::Rfc822Name(name) |
-							GeneralSubtree::DnsName(name) => writer.write_ia5_string(name),
-							GeneralSubtree::DirectoryName(name) => write_distinguished_name(writer, name),
-							GeneralSubtree::IpAddress(subnet) => writer.write_bytes(&subnet.to_bytes()),
-						}
-					});
+					let tag = Tag::context(subtree.tag());
+					match subtree {
+						GeneralSubtree::Rfc822Name(name) |
+						GeneralSubtree::DnsName(name) => writer.next().write_tagged_implicit(tag, |writer| {
+							writer.write_ia5_string(name)
+						}),
+						// Name is a CHOICE, so its tag has to be explicit
+						GeneralSubtree::DirectoryName(name) => writer.next().write_tagged(tag, |writer| {
+							write_distinguished_name(writer, name)
+						}),
+						GeneralSubtree::IpAddress(subnet) => writer.next().write_tagged_implicit(tag, |writer| {
+							writer.write_bytes(&subnet.to_bytes())
+						}),
+					}
 					// minimum must be 0 (the default) and maximum must be absent
 				});
 			}
@@ -1287,6 +1704,15 @@ fn write_general_subtrees(writer :DERWriter, tag :u64, general_subtrees :&[Gener
 impl Certificate {
 	/// Generates a new certificate from the given parameters
 	pub fn from_params(mut params :CertificateParams) -> Result<Self, RcgenError> {
+		if params.strict {
+			let errors = params.lint()
+				.into_iter()
+				.filter(|lint| lint.severity() == LintSeverity::Error)
+				.collect::<Vec<_>>();
+			if !errors.is_empty() {
+				return Err(RcgenError::LintFailed(errors));
+			}
+		}
 		let key_pair = if let Some(key_pair) = params.key_pair.take() {
 			if !key_pair.is_compatible(&params.alg) {
 				return Err(RcgenError::CertificateKeyPairMismatch);
@@ -1312,7 +1738,7 @@ impl Certificate {
 	}
 	/// Serializes the certificate, signed with another certificate's key, in binary DER format
 	pub fn serialize_der_with_signer(&self, ca :&Certificate) -> Result<Vec<u8>, RcgenError> {
-		self.params.serialize_der_with_signer(&self.key_pair, ca)
+		self.params.serialize_signed_der(&self.key_pair, ca)
 	}
 	/// Serializes a certificate signing request in binary DER format
 	pub fn serialize_request_der(&self) -> Result<Vec<u8>, RcgenError> {
@@ -1385,6 +1811,28 @@ impl Certificate {
 	pub fn serialize_private_key_pem(&self) -> String {
 		self.key_pair.serialize_pem()
 	}
+	/// Serializes the private key in PKCS#8 format, encrypted with the given password
+	///
+	/// See [`KeyPair::serialize_der_encrypted`] for more details.
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "encryption" feature*
+	#[cfg(feature = "encryption")]
+	pub fn serialize_private_key_der_encrypted(&self, password :&str) -> Result<Vec<u8>, RcgenError> {
+		self.key_pair.serialize_der_encrypted(password)
+	}
+	/// Serializes the private key in PEM format, encrypted with the given password
+	///
+	/// See [`KeyPair::serialize_pem_encrypted`] for more details.
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "pem" and "encryption" features*
+	#[cfg(all(feature = "pem", feature = "encryption"))]
+	pub fn serialize_private_key_pem_encrypted(&self, password :&str) -> Result<String, RcgenError> {
+		self.key_pair.serialize_pem_encrypted(password)
+	}
 }
 
 enum SignAlgo {
@@ -1447,6 +1895,30 @@ impl KeyPair {
 		Ok(private_key_der.try_into()?)
 	}
 
+	/// Parses the key pair from a password protected `EncryptedPrivateKeyInfo` in DER format
+	///
+	/// Supports the PBES2 scheme with PBKDF2 or scrypt as key derivation function
+	/// and AES in CBC or GCM mode as cipher. Fails with
+	/// [`RcgenError::UnsupportedKeyEncryption`] if deriving the key would take
+	/// more than 10 000 000 PBKDF2 iterations or 1 GiB of memory for scrypt.
+	///
+	/// *This constructor is only available if rcgen is built with the "encryption" feature*
+	#[cfg(feature = "encryption")]
+	pub fn from_der_encrypted(der :&[u8], password :&str) -> Result<Self, RcgenError> {
+		let pkcs8 = pbes2::decrypt_private_key_info(der, password.as_bytes())?;
+		Ok(pkcs8.try_into()?)
+	}
+	/// Parses the key pair from a password protected `ENCRYPTED PRIVATE KEY` in ASCII PEM format
+	///
+	/// See [`from_der_encrypted`](Self::from_der_encrypted) for more details.
+	///
+	/// *This constructor is only available if rcgen is built with the "pem" and "encryption" features*
+	#[cfg(all(feature = "pem", feature = "encryption"))]
+	pub fn from_pem_encrypted(pem_str :&str, password :&str) -> Result<Self, RcgenError> {
+		let private_key = pem::parse(pem_str)?;
+		Self::from_der_encrypted(&private_key.contents, password)
+	}
+
 	/// Obtains the key pair from a raw public key and a remote private key
 	pub fn from_remote(key_pair :Box<dyn RemoteKeyPair + Send + Sync>) -> Result<Self, RcgenError> {
 		Ok(Self {
@@ -1552,6 +2024,8 @@ pub enum RcgenError {
 	CouldNotParseCertificationRequest,
 	/// The given key pair couldn't be parsed
 	CouldNotParseKeyPair,
+	/// The given public key couldn't be parsed
+	CouldNotParsePublicKey,
 	#[cfg(feature = "x509-parser")]
 	/// Invalid subject alternative name type
 	InvalidNameType,
@@ -1579,6 +2053,76 @@ pub enum RcgenError {
 	PemError(pem::PemError),
 	/// Error generated by a remote key operation
 	RemoteKeyError,
+	/// The certificate parameters are in [strict mode](CertificateParams::strict)
+	/// and [linting](CertificateParams::lint) them reported the contained errors
+	LintFailed(Vec<Lint>),
+	/// The next update time of a CRL lies before its this update time
+	InvalidCrlNextUpdate,
+	/// The CA certificate's key usages don't allow signing CRLs
+	IssuerNotCrlSigner,
+	/// The digest for the acmeIdentifier extension doesn't have 32 bytes
+	InvalidAcmeIdentifierDigest,
+	/// The not after time of an attribute certificate lies before its not before time
+	InvalidAttributeCertificateValidity,
+	/// The given time-stamp request couldn't be parsed or uses an unsupported hash algorithm
+	CouldNotParseTimeStampRequest,
+	/// The serial number was already used for another certificate of the [`CertificateAuthority`]
+	DuplicateSerialNumber(u64),
+	/// The [`CertificateAuthority`] has no record of a certificate with the serial number
+	UnknownSerialNumber(u64),
+	/// The [`CertificateStore`] couldn't be read or written
+	CertificateStoreError(String),
+	/// The name constraints of the issuer can't be enforced, as they are
+	/// malformed or use name forms that [`NameConstraints`] can't represent,
+	/// see [`CertificateParams::enforce_name_constraints`]
+	UnsupportedIssuerNameConstraints,
+	/// The validity of the issuer ends before the `not_before` of the certificate,
+	/// so it can't be capped as requested by
+	/// [`CertificateParams::cap_validity_to_issuer`]
+	IssuerValidityEndsBeforeNotBefore,
+	/// The [`SigningPolicy`] rejected the request for the contained reason
+	PolicyViolation(PolicyViolation),
+	/// The subject alternative name is not permitted by the name constraints of the issuer,
+	/// see [`CertificateParams::enforce_name_constraints`]
+	SubjectAltNameNotPermitted(SanType),
+	/// The subject is not permitted by the name constraints of the issuer,
+	/// see [`CertificateParams::enforce_name_constraints`]
+	SubjectNotPermitted(DistinguishedName),
+	#[cfg(feature = "encryption")]
+	/// The encrypted key pair or PKCS#12 archive couldn't be decrypted
+	/// or authenticated, most likely because of a wrong password
+	///
+	/// *This variant is only available if rcgen is built with the "encryption" feature*
+	KeyDecryptionFailed,
+	#[cfg(feature = "encryption")]
+	/// The key encryption scheme or its parameters are not supported
+	///
+	/// *This variant is only available if rcgen is built with the "encryption" feature*
+	UnsupportedKeyEncryption,
+	#[cfg(feature = "encryption")]
+	/// The given PKCS#12 archive couldn't be parsed
+	///
+	/// *This variant is only available if rcgen is built with the "encryption" feature*
+	CouldNotParsePkcs12,
+	#[cfg(feature = "encryption")]
+	/// The given PKCS#12 archive has no MAC, so its integrity can't be verified
+	///
+	/// *This variant is only available if rcgen is built with the "encryption" feature*
+	Pkcs12NotAuthenticated,
+	#[cfg(feature = "serde")]
+	/// The issuer of a certificate in a [`PkiConfig`] is not
+	/// the name of a certificate defined before it
+	///
+	/// *This variant is only available if rcgen is built with the "serde" feature*
+	UnknownIssuer(String),
+	#[cfg(feature = "serde")]
+	/// The name is used for more than one certificate in a [`PkiConfig`]
+	///
+	/// *This variant is only available if rcgen is built with the "serde" feature*
+	DuplicateCertificateName(String),
+	#[cfg(feature = "x509-parser")]
+	/// The given certificate is not a CA certificate
+	NotACaCertificate,
 }
 
 impl fmt::Display for RcgenError {
@@ -1589,6 +2133,7 @@ impl fmt::Display for RcgenError {
 			CouldNotParseCertificationRequest => write!(f, "Could not parse certificate signing \
 				request")?,
 			CouldNotParseKeyPair => write!(f, "Could not parse key pair")?,
+			CouldNotParsePublicKey => write!(f, "Could not parse public key")?,
 			#[cfg(feature = "x509-parser")]
 			InvalidNameType => write!(f, "Invalid subject alternative name type")?,
 			KeyGenerationUnavailable => write!(f, "There is no support for generating \
@@ -1604,8 +2149,56 @@ impl fmt::Display for RcgenError {
 
 			Time => write!(f, "Time error")?,
 			RemoteKeyError => write!(f, "Remote key error")?,
+			InvalidCrlNextUpdate => write!(f, "Invalid CRL next update parameter")?,
+			IssuerNotCrlSigner => write!(f, "CRL issuer must specify no key usage, \
+				or key usage including cRLSign")?,
+			InvalidAcmeIdentifierDigest => write!(f, "The acmeIdentifier digest must \
+				have 32 bytes")?,
+			InvalidAttributeCertificateValidity => write!(f, "Invalid attribute certificate \
+				validity period")?,
+			CouldNotParseTimeStampRequest => write!(f, "Could not parse time-stamp request")?,
+			DuplicateSerialNumber(serial) => write!(f, "The serial number {:x} was already \
+				used", serial)?,
+			UnknownSerialNumber(serial) => write!(f, "No certificate with serial number \
+				{:x} was issued", serial)?,
+			CertificateStoreError(e) => write!(f, "Certificate store error: {}", e)?,
+			UnsupportedIssuerNameConstraints => write!(f, "The name constraints of the issuer \
+				can't be enforced")?,
+			IssuerValidityEndsBeforeNotBefore => write!(f, "The validity of the issuer \
+				ends before the certificate's validity starts")?,
+			PolicyViolation(violation) => write!(f, "Signing policy violation: {}", violation)?,
+			SubjectAltNameNotPermitted(name) => write!(f, "The subject alternative name {:?} \
+				is not permitted by the name constraints of the issuer", name)?,
+			SubjectNotPermitted(name) => write!(f, "The subject {:?} is not permitted by \
+				the name constraints of the issuer", name)?,
+			LintFailed(lints) => {
+				write!(f, "Linting the certificate parameters failed")?;
+				for (i, lint) in lints.iter().enumerate() {
+					let sep = if i == 0 { ": " } else { ", " };
+					write!(f, "{}{}", sep, lint)?;
+				}
+			},
 			#[cfg(feature = "pem")]
 			PemError(e) => write!(f, "PEM error: {}", e)?,
+			#[cfg(feature = "encryption")]
+			KeyDecryptionFailed => write!(f, "Could not decrypt key pair, \
+				is the password correct?")?,
+			#[cfg(feature = "encryption")]
+			UnsupportedKeyEncryption => write!(f, "The key encryption scheme \
+				is not supported")?,
+			#[cfg(feature = "encryption")]
+			CouldNotParsePkcs12 => write!(f, "Could not parse PKCS#12 archive")?,
+			#[cfg(feature = "encryption")]
+			Pkcs12NotAuthenticated => write!(f, "The PKCS#12 archive is not \
+				protected by a MAC")?,
+			#[cfg(feature = "serde")]
+			UnknownIssuer(name) => write!(f, "Unknown issuer {:?}, issuers have \
+				to be defined before the certificates they sign", name)?,
+			#[cfg(feature = "serde")]
+			DuplicateCertificateName(name) => write!(f, "Duplicate certificate \
+				name {:?}", name)?,
+			#[cfg(feature = "x509-parser")]
+			NotACaCertificate => write!(f, "The certificate is not a CA certificate")?,
 		};
 		Ok(())
 	}
@@ -1691,6 +2284,52 @@ impl KeyPair {
 			SignAlgo::Rsa() => Err(RcgenError::KeyGenerationUnavailable),
 		}
 	}
+	/// Deterministically derive a key pair for the specified signature algorithm from a seed
+	///
+	/// The same seed and algorithm always yield the same key pair, which
+	/// is useful for reproducible test setups, e.g. golden file tests.
+	/// Never use this with a seed that is not a properly generated secret,
+	/// as the private key is only as unpredictable as the seed.
+	///
+	/// Only [`PKCS_ED25519`] is supported, as it is the only algorithm whose
+	/// signatures are deterministic as well: ring always uses randomized
+	/// nonces for ECDSA, so certificates signed by an ECDSA key would still
+	/// differ between runs. Other algorithms return
+	/// [`RcgenError::KeyGenerationUnavailable`].
+	pub fn generate_from_seed(alg :&'static SignatureAlgorithm, seed :&[u8]) -> Result<Self, RcgenError> {
+		if alg != &PKCS_ED25519 {
+			return Err(RcgenError::KeyGenerationUnavailable);
+		}
+		let mut ctx = digest::Context::new(&digest::SHA512);
+		ctx.update(b"rcgen key pair seed");
+		ctx.update(&alg.oid_components.iter().flat_map(|c| c.to_be_bytes()).collect::<Vec<u8>>());
+		ctx.update(seed);
+		let secret = ctx.finish();
+		let secret = &secret.as_ref()[..32];
+		let key_pair = Ed25519KeyPair::from_seed_unchecked(secret)?;
+
+		// A PKCS#8 v2 document, like the ones ring generates
+		let pkcs8 = yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				writer.next().write_u8(1);
+				writer.next().write_sequence(|writer| {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(alg.oid_components));
+				});
+				let private_key = yasna::construct_der(|writer| writer.write_bytes(secret));
+				writer.next().write_bytes(&private_key);
+				// ring writes and expects the public key with an explicit tag
+				writer.next().write_tagged(Tag::context(1), |writer| {
+					let public_key = key_pair.public_key().as_ref();
+					writer.write_bitvec_bytes(public_key, public_key.len() * 8);
+				});
+			});
+		});
+		Ok(KeyPair {
+			kind : KeyPairKind::Ed(key_pair),
+			alg,
+			serialized_der : pkcs8,
+		})
+	}
 	/// Get the raw public key of this key pair
 	///
 	/// The key is in raw format, as how [`ring::signature::KeyPair::public_key`]
@@ -1710,32 +2349,28 @@ impl KeyPair {
 		std::iter::once(self.alg)
 	}
 	fn sign(&self, msg :&[u8], writer :DERWriter) -> Result<(), RcgenError> {
-		match &self.kind {
+		let sig = self.sign_raw(msg)?;
+		writer.write_bitvec_bytes(&sig, &sig.len() * 8);
+		Ok(())
+	}
+	/// Signs the message, returning the signature in the format of the algorithm
+	fn sign_raw(&self, msg :&[u8]) -> Result<Vec<u8>, RcgenError> {
+		let signature = match &self.kind {
 			KeyPairKind::Ec(kp) => {
 				let system_random = SystemRandom::new();
-				let signature = kp.sign(&system_random, msg)?;
-				let sig = &signature.as_ref();
-				writer.write_bitvec_bytes(&sig, &sig.len() * 8);
-			},
-			KeyPairKind::Ed(kp) => {
-				let signature = kp.sign(msg);
-				let sig = &signature.as_ref();
-				writer.write_bitvec_bytes(&sig, &sig.len() * 8);
+				kp.sign(&system_random, msg)?.as_ref().to_vec()
 			},
+			KeyPairKind::Ed(kp) => kp.sign(msg).as_ref().to_vec(),
 			KeyPairKind::Rsa(kp, padding_alg) => {
 				let system_random = SystemRandom::new();
 				let mut signature = vec![0; kp.public_modulus_len()];
 				kp.sign(*padding_alg, &system_random,
 					msg, &mut signature)?;
-				let sig = &signature.as_ref();
-				writer.write_bitvec_bytes(&sig, &sig.len() * 8);
-			},
-			KeyPairKind::Remote(kp) => {
-				let signature = kp.sign(msg)?;
-				writer.write_bitvec_bytes(&signature, &signature.len() * 8);
+				signature
 			},
-		}
-		Ok(())
+			KeyPairKind::Remote(kp) => kp.sign(msg)?,
+		};
+		Ok(signature)
 	}
 	/// Return the key pair's public key in DER format
 	///
@@ -1779,6 +2414,53 @@ impl KeyPair {
 		};
 		pem::encode(&p)
 	}
+	/// Serializes the key pair in PKCS#8 format, encrypted with the given password, in DER
+	///
+	/// Uses the default [`KeyEncryptionParams`].
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "encryption" feature*
+	#[cfg(feature = "encryption")]
+	pub fn serialize_der_encrypted(&self, password :&str) -> Result<Vec<u8>, RcgenError> {
+		self.serialize_der_encrypted_with_params(password, &KeyEncryptionParams::default())
+	}
+	/// Serializes the key pair in PKCS#8 format, encrypted with the given password
+	/// and encryption parameters, in DER
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "encryption" feature*
+	#[cfg(feature = "encryption")]
+	pub fn serialize_der_encrypted_with_params(&self, password :&str, params :&KeyEncryptionParams) -> Result<Vec<u8>, RcgenError> {
+		pbes2::encrypt_private_key_info(&self.serialize_der(), password.as_bytes(), params)
+	}
+	/// Serializes the key pair in PKCS#8 format, encrypted with the given password, in PEM
+	///
+	/// Uses the default [`KeyEncryptionParams`].
+	/// The output can be read by `openssl pkey`.
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "pem" and "encryption" features*
+	#[cfg(all(feature = "pem", feature = "encryption"))]
+	pub fn serialize_pem_encrypted(&self, password :&str) -> Result<String, RcgenError> {
+		self.serialize_pem_encrypted_with_params(password, &KeyEncryptionParams::default())
+	}
+	/// Serializes the key pair in PKCS#8 format, encrypted with the given password
+	/// and encryption parameters, in PEM
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "pem" and "encryption" features*
+	#[cfg(all(feature = "pem", feature = "encryption"))]
+	pub fn serialize_pem_encrypted_with_params(&self, password :&str, params :&KeyEncryptionParams) -> Result<String, RcgenError> {
+		let p = Pem {
+			tag : "ENCRYPTED PRIVATE KEY".to_string(),
+			contents : self.serialize_der_encrypted_with_params(password, params)?,
+		};
+		Ok(pem::encode(&p))
+	}
 }
 
 impl PublicKeyData for KeyPair {
@@ -1880,6 +2562,12 @@ impl SignatureAlgorithm {
 		ALGORITHMS.iter()
 	}
 
+	/// Whether the given OIDs from the AlgorithmIdentifier of a
+	/// SubjectPublicKeyInfo structure describe keys of this algorithm
+	fn has_key_oids(&self, oids :&[Vec<u64>]) -> bool {
+		self.oids_sign_alg.iter().copied().eq(oids.iter().map(|oid| oid.as_slice()))
+	}
+
 	/// Retrieve the SignatureAlgorithm for the provided OID
 	pub fn from_oid(oid :&[u64]) -> Result<&'static SignatureAlgorithm, RcgenError> {
 		for algo in Self::iter() {
@@ -1943,7 +2631,7 @@ static PKCS_RSA_PSS_SHA256 :SignatureAlgorithm = SignatureAlgorithm {
 pub static PKCS_ECDSA_P256_SHA256 :SignatureAlgorithm = SignatureAlgorithm {
 	oids_sign_alg :&[&OID_EC_PUBLIC_KEY, &OID_EC_SECP_256_R1],
 	sign_alg :SignAlgo::EcDsa(&signature::ECDSA_P256_SHA256_ASN1_SIGNING),
-	/// ecdsa-with-SHA256 in RFC 5758
+	// ecdsa-with-SHA256 in RFC 5758
 	oid_components : &[1, 2, 840, 10045, 4, 3, 2],
 	params : SignatureAlgorithmParams::None,
 };
@@ -1952,7 +2640,7 @@ pub static PKCS_ECDSA_P256_SHA256 :SignatureAlgorithm = SignatureAlgorithm {
 pub static PKCS_ECDSA_P384_SHA384 :SignatureAlgorithm = SignatureAlgorithm {
 	oids_sign_alg :&[&OID_EC_PUBLIC_KEY, &OID_EC_SECP_384_R1],
 	sign_alg :SignAlgo::EcDsa(&signature::ECDSA_P384_SHA384_ASN1_SIGNING),
-	/// ecdsa-with-SHA384 in RFC 5758
+	// ecdsa-with-SHA384 in RFC 5758
 	oid_components : &[1, 2, 840, 10045, 4, 3, 3],
 	params : SignatureAlgorithmParams::None,
 };
@@ -1961,10 +2649,10 @@ pub static PKCS_ECDSA_P384_SHA384 :SignatureAlgorithm = SignatureAlgorithm {
 
 /// ED25519 curve signing as per [RFC 8410](https://tools.ietf.org/html/rfc8410)
 pub static PKCS_ED25519 :SignatureAlgorithm = SignatureAlgorithm {
-	/// id-Ed25519 in RFC 8410
+	// id-Ed25519 in RFC 8410
 	oids_sign_alg :&[&[1, 3, 101, 112]],
 	sign_alg :SignAlgo::EdDsa(&signature::ED25519),
-	/// id-Ed25519 in RFC 8410
+	// id-Ed25519 in RFC 8410
 	oid_components : &[1, 3, 101, 112],
 	params : SignatureAlgorithmParams::None,
 };
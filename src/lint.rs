@@ -0,0 +1,280 @@
+use std::fmt;
+use crate::{CertificateParams, DnType, DnValue, ExtendedKeyUsagePurpose, IsCa,
+	KeyUsagePurpose, SanType, SignAlgo};
+
+/// How severe a [`Lint`] is
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum LintSeverity {
+	/// The certificate is valid, but goes against recommendations,
+	/// mostly those of the CA/Browser Forum baseline requirements
+	Warning,
+	/// The certificate would violate RFC 5280
+	Error,
+}
+
+/// A problem found by [`CertificateParams::lint`]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub enum Lint {
+	/// `not_after` lies before `not_before`
+	NotAfterBeforeNotBefore,
+	/// The serial number is zero, but RFC 5280 requires it to be positive
+	ZeroSerialNumber,
+	/// A CA certificate with key usages that don't include `KeyCertSign`
+	CaWithoutKeyCertSign,
+	/// The `KeyCertSign` key usage is set on a certificate that is no CA
+	KeyCertSignWithoutCa,
+	/// Name constraints are set on a certificate that is no CA
+	NameConstraintsWithoutCa,
+	/// `EncipherOnly` or `DecipherOnly` are set without `KeyAgreement`
+	EncipherOrDecipherOnlyWithoutKeyAgreement,
+	/// The DNS name is syntactically invalid, e.g. because it
+	/// contains a wildcard that isn't the entire leftmost label
+	InvalidDnsName(String),
+	/// The e-mail address is syntactically invalid
+	InvalidRfc822Name(String),
+	/// The value of the given attribute can't be encoded as `PrintableString`
+	InvalidPrintableString(DnType),
+	/// Both the subject distinguished name and the subject alternative names are empty
+	EmptySubject,
+	/// A CA certificate without key usages
+	CaWithoutKeyUsage,
+	/// A TLS server certificate, or a certificate that is neither a CA
+	/// nor has extended key usages, without subject alternative names
+	MissingSubjectAltNames,
+	/// The common name isn't one of the subject alternative names
+	CommonNameNotInSubjectAltNames,
+	/// The subject alternative name is listed more than once
+	DuplicateSubjectAltName(SanType),
+	/// The validity period of the TLS server certificate, one with the `ServerAuth`
+	/// extended key usage, exceeds the 398 days the baseline requirements allow,
+	/// the contained value is the actual number of days
+	ValidityTooLong(i64),
+	/// The `anyExtendedKeyUsage` purpose is set on a certificate that is no CA
+	AnyExtendedKeyUsageWithoutCa,
+	/// The key usage is not possible with the key type of the signature algorithm
+	KeyUsageIncompatibleWithKey(KeyUsagePurpose),
+}
+
+impl Lint {
+	/// Returns whether the lint is a warning or an error
+	pub fn severity(&self) -> LintSeverity {
+		use self::Lint::*;
+		match self {
+			NotAfterBeforeNotBefore |
+			ZeroSerialNumber |
+			CaWithoutKeyCertSign |
+			KeyCertSignWithoutCa |
+			NameConstraintsWithoutCa |
+			EncipherOrDecipherOnlyWithoutKeyAgreement |
+			InvalidDnsName(_) |
+			InvalidRfc822Name(_) |
+			InvalidPrintableString(_) |
+			EmptySubject => LintSeverity::Error,
+			CaWithoutKeyUsage |
+			MissingSubjectAltNames |
+			CommonNameNotInSubjectAltNames |
+			DuplicateSubjectAltName(_) |
+			ValidityTooLong(_) |
+			AnyExtendedKeyUsageWithoutCa |
+			KeyUsageIncompatibleWithKey(_) => LintSeverity::Warning,
+		}
+	}
+}
+
+impl fmt::Display for Lint {
+	fn fmt(&self, f :&mut fmt::Formatter) -> fmt::Result {
+		use self::Lint::*;
+		match self {
+			NotAfterBeforeNotBefore => write!(f, "not_after lies before not_before")?,
+			ZeroSerialNumber => write!(f, "The serial number is zero")?,
+			CaWithoutKeyCertSign => write!(f, "CA certificate without the \
+				KeyCertSign key usage")?,
+			KeyCertSignWithoutCa => write!(f, "KeyCertSign key usage on a certificate \
+				that is no CA")?,
+			NameConstraintsWithoutCa => write!(f, "Name constraints on a certificate \
+				that is no CA")?,
+			EncipherOrDecipherOnlyWithoutKeyAgreement => write!(f, "EncipherOnly or \
+				DecipherOnly key usage without KeyAgreement")?,
+			InvalidDnsName(name) => write!(f, "Invalid DNS name {:?}", name)?,
+			InvalidRfc822Name(name) => write!(f, "Invalid e-mail address {:?}", name)?,
+			InvalidPrintableString(ty) => write!(f, "The value of {:?} is no valid \
+				PrintableString", ty)?,
+			EmptySubject => write!(f, "Both the subject and the subject alternative \
+				names are empty")?,
+			CaWithoutKeyUsage => write!(f, "CA certificate without key usages")?,
+			MissingSubjectAltNames => write!(f, "Certificate without subject \
+				alternative names")?,
+			CommonNameNotInSubjectAltNames => write!(f, "The common name is not one of \
+				the subject alternative names")?,
+			DuplicateSubjectAltName(name) => write!(f, "Duplicate subject alternative \
+				name {:?}", name)?,
+			ValidityTooLong(days) => write!(f, "TLS server certificate valid for {} days, \
+				more than the allowed 398", days)?,
+			AnyExtendedKeyUsageWithoutCa => write!(f, "anyExtendedKeyUsage on a certificate \
+				that is no CA")?,
+			KeyUsageIncompatibleWithKey(usage) => write!(f, "Key usage {:?} is not possible \
+				with the key type", usage)?,
+		};
+		Ok(())
+	}
+}
+
+impl CertificateParams {
+	/// Checks the parameters for problems before they are used to create a certificate
+	///
+	/// The checks are based on [RFC 5280](https://tools.ietf.org/html/rfc5280) and the
+	/// [CA/Browser Forum baseline requirements](https://cabforum.org/baseline-requirements-documents/).
+	/// Violations of the former are reported as [`LintSeverity::Error`],
+	/// violations of the latter as [`LintSeverity::Warning`].
+	///
+	/// Set [`strict`](Self::strict) to make [`Certificate::from_params`](crate::Certificate::from_params)
+	/// refuse parameters with errors.
+	///
+	/// ```
+	/// # use rcgen::{CertificateParams, Lint};
+	/// let params = CertificateParams::new(vec!["www.*.example.com".to_string()]);
+	/// assert!(params.lint().contains(&Lint::InvalidDnsName("www.*.example.com".to_string())));
+	/// ```
+	pub fn lint(&self) -> Vec<Lint> {
+		let mut lints = Vec::new();
+		let is_ca = matches!(self.is_ca, IsCa::Ca(_));
+
+		if self.not_after < self.not_before {
+			lints.push(Lint::NotAfterBeforeNotBefore);
+		}
+		if self.serial_number == Some(0) {
+			lints.push(Lint::ZeroSerialNumber);
+		}
+
+		let is_tls_server = !is_ca && self.extended_key_usages.contains(&ExtendedKeyUsagePurpose::ServerAuth);
+		// Certificates without extended key usages may be used for any purpose
+		let is_any_purpose = !is_ca && self.extended_key_usages.is_empty();
+
+		let has_usage = |usage :KeyUsagePurpose| self.key_usages.contains(&usage);
+		if is_ca && self.key_usages.is_empty() {
+			lints.push(Lint::CaWithoutKeyUsage);
+		}
+		if is_ca && !self.key_usages.is_empty() && !has_usage(KeyUsagePurpose::KeyCertSign) {
+			lints.push(Lint::CaWithoutKeyCertSign);
+		}
+		if !is_ca && has_usage(KeyUsagePurpose::KeyCertSign) {
+			lints.push(Lint::KeyCertSignWithoutCa);
+		}
+		if (has_usage(KeyUsagePurpose::EncipherOnly) || has_usage(KeyUsagePurpose::DecipherOnly))
+				&& !has_usage(KeyUsagePurpose::KeyAgreement) {
+			lints.push(Lint::EncipherOrDecipherOnlyWithoutKeyAgreement);
+		}
+		if !matches!(self.alg.sign_alg, SignAlgo::Rsa()) {
+			for usage in [KeyUsagePurpose::KeyEncipherment, KeyUsagePurpose::DataEncipherment].iter() {
+				if has_usage(usage.clone()) {
+					lints.push(Lint::KeyUsageIncompatibleWithKey(usage.clone()));
+				}
+			}
+		}
+		if !is_ca && self.name_constraints.iter().any(|c| !c.is_empty()) {
+			lints.push(Lint::NameConstraintsWithoutCa);
+		}
+		if !is_ca && self.extended_key_usages.contains(&ExtendedKeyUsagePurpose::Any) {
+			lints.push(Lint::AnyExtendedKeyUsageWithoutCa);
+		}
+
+		for (i, san) in self.subject_alt_names.iter().enumerate() {
+			match san {
+				SanType::DnsName(name) if !is_valid_dns_name(name) => {
+					lints.push(Lint::InvalidDnsName(name.clone()));
+				},
+				SanType::Rfc822Name(name) if !is_valid_rfc822_name(name) => {
+					lints.push(Lint::InvalidRfc822Name(name.clone()));
+				},
+				_ => (),
+			}
+			if self.subject_alt_names[..i].contains(san) {
+				lints.push(Lint::DuplicateSubjectAltName(san.clone()));
+			}
+		}
+		for (ty, value) in self.distinguished_name.iter() {
+			if let DnValue::PrintableString(s) = value {
+				if !s.chars().all(is_printable_char) {
+					lints.push(Lint::InvalidPrintableString(ty.clone()));
+				}
+			}
+		}
+
+		let subject_empty = self.distinguished_name.iter().next().is_none();
+		if subject_empty && self.subject_alt_names.is_empty() {
+			lints.push(Lint::EmptySubject);
+		} else if (is_tls_server || is_any_purpose) && self.subject_alt_names.is_empty() {
+			lints.push(Lint::MissingSubjectAltNames);
+		}
+		if !is_ca && !self.subject_alt_names.is_empty() {
+			let common_name = match self.distinguished_name.get(&DnType::CommonName) {
+				Some(DnValue::Utf8String(s)) | Some(DnValue::PrintableString(s)) => Some(s),
+				_ => None,
+			};
+			if let Some(common_name) = common_name {
+				let in_sans = self.subject_alt_names.iter().any(|san| match san {
+					SanType::DnsName(name) |
+					SanType::Rfc822Name(name) => name.eq_ignore_ascii_case(common_name),
+					SanType::IpAddress(addr) => &addr.to_string() == common_name,
+					_ => false,
+				});
+				if !in_sans {
+					lints.push(Lint::CommonNameNotInSubjectAltNames);
+				}
+			}
+		}
+
+		if is_tls_server {
+			let days = (self.not_after - self.not_before).whole_days();
+			if days > 398 {
+				lints.push(Lint::ValidityTooLong(days));
+			}
+		}
+		lints
+	}
+}
+
+/// Checks the preferred name syntax of RFC 1034, allowing a wildcard as entire leftmost label
+fn is_valid_dns_name(name :&str) -> bool {
+	if name.is_empty() || name.len() > 253 {
+		return false;
+	}
+	let name = name.strip_prefix("*.").unwrap_or(name);
+	name.split('.').all(|label| {
+		!label.is_empty() && label.len() <= 63
+			&& !label.starts_with('-') && !label.ends_with('-')
+			&& label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+	})
+}
+
+fn is_valid_rfc822_name(name :&str) -> bool {
+	match name.rsplit_once('@') {
+		Some((local, host)) => !local.is_empty() && name.is_ascii() && is_valid_dns_name(host)
+			&& !host.starts_with('*'),
+		None => false,
+	}
+}
+
+fn is_printable_char(c :char) -> bool {
+	c.is_ascii_alphanumeric() || " '()+,-./:=?".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_dns_names() {
+		assert!(is_valid_dns_name("example.com"));
+		assert!(is_valid_dns_name("*.example.com"));
+		assert!(is_valid_dns_name("xn--bcher-kva.example"));
+		assert!(!is_valid_dns_name("www.*.example.com"));
+		assert!(!is_valid_dns_name("w*.example.com"));
+		assert!(!is_valid_dns_name("*"));
+		assert!(!is_valid_dns_name("example..com"));
+		assert!(!is_valid_dns_name("-example.com"));
+		assert!(!is_valid_dns_name("under_score.example.com"));
+		assert!(!is_valid_dns_name("bücher.example"));
+	}
+}
@@ -1,30 +1,520 @@
-#![allow(clippy::complexity, clippy::style, clippy::pedantic)]
+//! The `rcgen` command line tool
+//!
+//! It's only built if the `x509-parser` feature is enabled, e.g. with
+//! `cargo install rcgen --features x509-parser`.
 
-use rcgen::{Certificate, CertificateParams,
-	DistinguishedName, DnType, SanType,
-	date_time_ymd};
+use rcgen::{Certificate, CertificateParams, CertificateRevocationList,
+	CertificateRevocationListParams, CertificateSigningRequest, DistinguishedName, DnType,
+	ExtendedKeyUsagePurpose, KeyPair, KeyUsagePurpose, RevocationReason, RevokedCertParams,
+	SanType, SignatureAlgorithm};
+use std::collections::HashMap;
+use std::error::Error;
 use std::fs;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::net::IpAddr;
+use time::{Duration, OffsetDateTime};
+use x509_parser::extensions::ParsedExtension;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-	let mut params :CertificateParams = Default::default();
-	params.not_before = date_time_ymd(1975, 01, 01);
-	params.not_after = date_time_ymd(4096, 01, 01);
-	params.distinguished_name = DistinguishedName::new();
-	params.distinguished_name.push(DnType::OrganizationName, "Crab widgits SE");
-	params.distinguished_name.push(DnType::CommonName, "Master Cert");
-	params.subject_alt_names = vec![SanType::DnsName("crabs.crabs".to_string()),
-		SanType::DnsName("localhost".to_string())];
+const USAGE :&str = "\
+Usage: rcgen <command> [options]
 
+Commands:
+  ca        Create a CA certificate, self signed or signed by --ca-cert/--ca-key
+              --cn <name> [--path-len <n>] [--alg <alg>] [--days <n>]
+              [--out-cert ca.pem] [--out-key ca.key.pem]
+  issue     Issue a leaf certificate from a CA
+              --ca-cert <file> --ca-key <file> [--cn <name>] [--san <dns name>]...
+              [--ip <address>]... [--alg <alg>] [--days <n>] [--ku <usage>]...
+              [--eku <usage>]... [--out-cert cert.pem] [--out-key key.pem]
+  csr       Generate a key pair and a certificate signing request
+              [--cn <name>] [--san <dns name>]... [--ip <address>]... [--alg <alg>]
+              [--out-csr csr.pem] [--out-key key.pem]
+  sign-csr  Issue a certificate for a certificate signing request
+              --ca-cert <file> --ca-key <file> --csr <file> [--days <n>]
+              [--ku <usage>]... [--eku <usage>]... [--out-cert cert.pem]
+  revoke    Add certificates to a CRL and sign it
+              --ca-cert <file> --ca-key <file> [--serial <n>]... [--cert <file>]...
+              [--reason <reason>] [--crl <existing crl>] [--days <n>] [--out-crl crl.pem]
+  print     Print the contents of certificates, CSRs and CRLs
+              <file>...
+  pki       Create all certificates described in a TOML file, see rcgen::PkiConfig
+              <config.toml> [--out-dir <dir>]
+              (only available when built with the cli-pki feature)
+
+Input files may be PEM or DER encoded. Output files ending in .der are
+written as DER, all others as PEM. Private keys are only readable by their
+owner, and are encrypted with --key-password if given. Encrypted CA keys are
+decrypted with --ca-key-password. Both need rcgen to be built with the
+encryption feature.
+
+  <alg>     p256 (default), p384, ed25519
+  <usage>   key usages: digitalSignature, contentCommitment, keyEncipherment,
+            dataEncipherment, keyAgreement, keyCertSign, cRLSign, encipherOnly,
+            decipherOnly
+            extended key usages: serverAuth, clientAuth, codeSigning,
+            emailProtection, timeStamping, ocspSigning, any
+  <reason>  unspecified, keyCompromise, caCompromise, affiliationChanged,
+            superseded, cessationOfOperation, certificateHold, removeFromCrl,
+            privilegeWithdrawn, aaCompromise
+";
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() {
+	let mut args = std::env::args().skip(1);
+	let command = args.next();
+	let result = match command.as_deref() {
+		None | Some("help") | Some("--help") | Some("-h") => {
+			print!("{}", USAGE);
+			Ok(())
+		},
+		Some(command) => Args::parse(args).and_then(|args| run(command, &args)),
+	};
+	if let Err(e) = result {
+		eprintln!("error: {}", e);
+		std::process::exit(1);
+	}
+}
+
+fn run(command :&str, args :&Args) -> CliResult<()> {
+	match command {
+		"ca" => create_ca(args),
+		"issue" => issue(args),
+		"csr" => create_csr(args),
+		"sign-csr" => sign_csr(args),
+		"revoke" => revoke(args),
+		"print" => print(args),
+		"pki" => pki(args),
+		_ => Err(format!("unknown command {:?}, see rcgen --help", command).into()),
+	}
+}
+
+/// Command line options, all of which take a value, and positional arguments
+struct Args {
+	options :HashMap<String, Vec<String>>,
+	positional :Vec<String>,
+}
+
+impl Args {
+	fn parse(mut args :impl Iterator<Item = String>) -> CliResult<Self> {
+		let mut options = HashMap::<String, Vec<String>>::new();
+		let mut positional = Vec::new();
+		while let Some(arg) = args.next() {
+			if let Some(name) = arg.strip_prefix("--") {
+				let value = args.next()
+					.ok_or_else(|| format!("missing value for --{}", name))?;
+				options.entry(name.to_string()).or_default().push(value);
+			} else {
+				positional.push(arg);
+			}
+		}
+		Ok(Args { options, positional })
+	}
+	fn get(&self, name :&str) -> Option<&str> {
+		self.options.get(name)
+			.and_then(|values| values.last())
+			.map(|value| value.as_str())
+	}
+	fn required(&self, name :&str) -> CliResult<&str> {
+		self.get(name)
+			.ok_or_else(|| format!("missing required option --{}", name).into())
+	}
+	/// All values of a repeatable option, which may also be comma separated
+	fn all(&self, name :&str) -> Vec<&str> {
+		self.options.get(name)
+			.into_iter()
+			.flatten()
+			.flat_map(|value| value.split(','))
+			.map(|value| value.trim())
+			.filter(|value| !value.is_empty())
+			.collect()
+	}
+	fn alg(&self) -> CliResult<&'static SignatureAlgorithm> {
+		Ok(match self.get("alg").unwrap_or("p256") {
+			"p256" | "ecdsa-p256" => &rcgen::PKCS_ECDSA_P256_SHA256,
+			"p384" | "ecdsa-p384" => &rcgen::PKCS_ECDSA_P384_SHA384,
+			"ed25519" => &rcgen::PKCS_ED25519,
+			alg => return Err(format!("unsupported algorithm {:?}", alg).into()),
+		})
+	}
+	fn names(&self) -> CliResult<Vec<SanType>> {
+		let mut names = self.all("san").into_iter()
+			.map(|name| SanType::DnsName(name.to_string()))
+			.collect::<Vec<_>>();
+		for ip in self.all("ip") {
+			let ip = ip.parse::<IpAddr>()
+				.map_err(|_| format!("invalid IP address {:?}", ip))?;
+			names.push(SanType::IpAddress(ip));
+		}
+		Ok(names)
+	}
+	/// Applies --days, --ku and --eku, if given
+	fn apply_overrides(&self, params :&mut CertificateParams) -> CliResult<()> {
+		if let Some(days) = self.get("days") {
+			let days = days.parse::<i64>()
+				.map_err(|_| format!("invalid number of days {:?}", days))?;
+			params.not_after = params.not_before + Duration::days(days);
+		}
+		let key_usages = self.all("ku");
+		if !key_usages.is_empty() {
+			params.key_usages = key_usages.into_iter()
+				.map(parse_key_usage)
+				.collect::<CliResult<_>>()?;
+		}
+		let extended_key_usages = self.all("eku");
+		if !extended_key_usages.is_empty() {
+			params.extended_key_usages = extended_key_usages.into_iter()
+				.map(parse_extended_key_usage)
+				.collect::<CliResult<_>>()?;
+		}
+		params.serial_number = Some(rcgen::random_serial_number()?);
+		Ok(())
+	}
+}
+
+fn create_ca(args :&Args) -> CliResult<()> {
+	let common_name = args.required("cn")?;
+	let mut params = match args.get("path-len") {
+		Some(path_len) => {
+			let path_len = path_len.parse::<u8>()
+				.map_err(|_| format!("invalid path length {:?}", path_len))?;
+			CertificateParams::intermediate_ca(common_name, path_len)
+		},
+		None => CertificateParams::root_ca(common_name),
+	};
+	params.alg = args.alg()?;
+	args.apply_overrides(&mut params)?;
+	let cert = Certificate::from_params(params)?;
+	let cert_der = match load_ca_if_given(args)? {
+		Some(ca) => cert.serialize_der_with_signer(&ca)?,
+		None => cert.serialize_der()?,
+	};
+	write_output(args.get("out-cert").unwrap_or("ca.pem"), "CERTIFICATE", &cert_der)?;
+	write_key(args, args.get("out-key").unwrap_or("ca.key.pem"), cert.get_key_pair())?;
+	Ok(())
+}
+
+fn issue(args :&Args) -> CliResult<()> {
+	let ca = load_ca(args)?;
+	let names = args.names()?;
+	let mut params = CertificateParams::tls_server(Vec::new());
+	params.subject_alt_names = names;
+	params.distinguished_name = leaf_distinguished_name(args, &params.subject_alt_names)?;
+	params.alg = args.alg()?;
+	args.apply_overrides(&mut params)?;
+	let cert = Certificate::from_params(params)?;
+	let cert_der = cert.serialize_der_with_signer(&ca)?;
+	write_output(args.get("out-cert").unwrap_or("cert.pem"), "CERTIFICATE", &cert_der)?;
+	write_key(args, args.get("out-key").unwrap_or("key.pem"), cert.get_key_pair())?;
+	Ok(())
+}
+
+fn create_csr(args :&Args) -> CliResult<()> {
+	let mut params = CertificateParams::new(Vec::new());
+	params.subject_alt_names = args.names()?;
+	params.distinguished_name = leaf_distinguished_name(args, &params.subject_alt_names)?;
+	params.alg = args.alg()?;
 	let cert = Certificate::from_params(params)?;
+	write_output(args.get("out-csr").unwrap_or("csr.pem"), "CERTIFICATE REQUEST",
+		&cert.serialize_request_der()?)?;
+	write_key(args, args.get("out-key").unwrap_or("key.pem"), cert.get_key_pair())?;
+	Ok(())
+}
+
+fn sign_csr(args :&Args) -> CliResult<()> {
+	let ca = load_ca(args)?;
+	let csr_der = read_input(args.required("csr")?)?;
+	let mut csr = CertificateSigningRequest::from_der(&csr_der)?;
+	let profile = CertificateParams::tls_server(Vec::new());
+	csr.params.not_before = profile.not_before;
+	csr.params.not_after = profile.not_after;
+	csr.params.key_usages = profile.key_usages;
+	csr.params.extended_key_usages = profile.extended_key_usages;
+	csr.params.use_authority_key_identifier_extension = true;
+	args.apply_overrides(&mut csr.params)?;
+	let cert_der = csr.serialize_der_with_signer(&ca)?;
+	write_output(args.get("out-cert").unwrap_or("cert.pem"), "CERTIFICATE", &cert_der)?;
+	Ok(())
+}
+
+#[cfg(feature = "cli-pki")]
+fn pki(args :&Args) -> CliResult<()> {
+	let config_path = args.positional.first()
+		.ok_or("missing configuration file")?;
+	let config = fs::read_to_string(config_path)
+		.map_err(|e| format!("could not read {:?}: {}", config_path, e))?;
+	let config :rcgen::PkiConfig = toml::from_str(&config)
+		.map_err(|e| format!("could not parse {:?}: {}", config_path, e))?;
+	let out_dir = std::path::Path::new(args.get("out-dir").unwrap_or("."));
+	let pki = config.generate()?;
+	for (name, cert) in pki.iter() {
+		let path = |file_name :String| out_dir.join(file_name).to_string_lossy().into_owned();
+		write_output(&path(format!("{}.pem", name)), "CERTIFICATE", cert.der())?;
+		write_key(args, &path(format!("{}.key.pem", name)), cert.key_pair())?;
+	}
+	Ok(())
+}
+
+#[cfg(not(feature = "cli-pki"))]
+fn pki(_args :&Args) -> CliResult<()> {
+	Err("rcgen was built without the cli-pki feature".into())
+}
+
+fn revoke(args :&Args) -> CliResult<()> {
+	let ca_der = read_input(args.required("ca-cert")?)?;
+	let ca = load_ca(args)?;
+	let now = OffsetDateTime::now_utc();
+	let days = args.get("days").unwrap_or("30");
+	let days = days.parse::<i64>()
+		.map_err(|_| format!("invalid number of days {:?}", days))?;
+	let mut params = CertificateRevocationListParams::new(now, now + Duration::days(days), 1);
+
+	if let Some(crl_path) = args.get("crl") {
+		let crl_der = read_input(crl_path)?;
+		let (_, crl) = x509_parser::parse_x509_crl(&crl_der)
+			.map_err(|e| format!("could not parse CRL {:?}: {}", crl_path, e))?;
+		let (_, ca_cert) = x509_parser::parse_x509_certificate(&ca_der)?;
+		if crl.tbs_cert_list.issuer.as_raw() != ca_cert.subject().as_raw() {
+			return Err(format!("CRL {:?} was not issued by the given CA", crl_path).into());
+		}
+		for revoked in crl.iter_revoked_certificates() {
+			params.revoked_certs.push(RevokedCertParams {
+				serial_number : serial_from_bytes(revoked.raw_serial())?,
+				revocation_time : revoked.revocation_date.to_datetime(),
+				reason_code : revoked.reason_code()
+					.and_then(|(_critical, reason)| RevocationReason::from_code(reason.0)),
+			});
+		}
+		// CertificateRevocationList::crl_number looks up the wrong extension
+		// in x509-parser 0.13, so search for it ourselves
+		let crl_number = crl.extensions().iter()
+			.find_map(|ext| match ext.parsed_extension() {
+				ParsedExtension::CRLNumber(number) => Some(number),
+				_ => None,
+			});
+		if let Some(crl_number) = crl_number {
+			params.crl_number = serial_from_bytes(&crl_number.to_bytes_be())? + 1;
+		}
+	}
+
+	let reason_code = args.get("reason")
+		.map(parse_revocation_reason)
+		.transpose()?;
+	let mut serials = Vec::new();
+	for serial in args.all("serial") {
+		serials.push(parse_serial(serial)?);
+	}
+	for cert_path in args.all("cert") {
+		let cert_der = read_input(cert_path)?;
+		let (_, cert) = x509_parser::parse_x509_certificate(&cert_der)?;
+		serials.push(serial_from_bytes(cert.raw_serial())?);
+	}
+	for serial_number in serials {
+		if params.revoked_certs.iter().all(|revoked| revoked.serial_number != serial_number) {
+			params.revoked_certs.push(RevokedCertParams {
+				serial_number,
+				revocation_time : now,
+				reason_code,
+			});
+		}
+	}
+
+	let crl = CertificateRevocationList::from_params(params)?;
+	let out = args.get("out-crl").or_else(|| args.get("crl")).unwrap_or("crl.pem");
+	write_output(out, "X509 CRL", &crl.serialize_der_with_signer(&ca)?)?;
+	Ok(())
+}
 
-	let pem_serialized = cert.serialize_pem()?;
-	let der_serialized = pem::parse(&pem_serialized).unwrap().contents;
-	println!("{}", pem_serialized);
-	println!("{}", cert.serialize_private_key_pem());
-	std::fs::create_dir_all("certs/")?;
-	fs::write("certs/cert.pem", &pem_serialized.as_bytes())?;
-	fs::write("certs/cert.der", &der_serialized)?;
-	fs::write("certs/key.pem", &cert.serialize_private_key_pem().as_bytes())?;
-	fs::write("certs/key.der", &cert.serialize_private_key_der())?;
+fn print(args :&Args) -> CliResult<()> {
+	if args.positional.is_empty() {
+		return Err("no files given".into());
+	}
+	for path in args.positional.iter() {
+		let dump = rcgen::X509Dump::from_der(&read_input(path)?)
+			.map_err(|e| format!("could not parse {:?}: {}", path, e))?;
+		println!("{}:", path);
+		print!("{}", dump);
+	}
 	Ok(())
 }
+
+fn load_ca(args :&Args) -> CliResult<Certificate> {
+	load_ca_if_given(args)?
+		.ok_or_else(|| "missing required options --ca-cert and --ca-key".into())
+}
+
+fn load_ca_if_given(args :&Args) -> CliResult<Option<Certificate>> {
+	let (cert_path, key_path) = match (args.get("ca-cert"), args.get("ca-key")) {
+		(Some(cert_path), Some(key_path)) => (cert_path, key_path),
+		(None, None) => return Ok(None),
+		_ => return Err("--ca-cert and --ca-key have to be given together".into()),
+	};
+	let key_pair = match args.get("ca-key-password") {
+		Some(password) => decrypt_key(&read_input(key_path)?, password)?,
+		None => KeyPair::from_der(&read_input(key_path)?)?,
+	};
+	let params = CertificateParams::from_ca_cert_der(&read_input(cert_path)?, key_pair)?;
+	Ok(Some(Certificate::from_params(params)?))
+}
+
+/// Uses --cn as common name, or the first subject alternative name
+fn leaf_distinguished_name(args :&Args, names :&[SanType]) -> CliResult<DistinguishedName> {
+	let common_name = match (args.get("cn"), names.first()) {
+		(Some(common_name), _) => common_name.to_string(),
+		(None, Some(SanType::DnsName(name))) => name.clone(),
+		(None, Some(SanType::IpAddress(addr))) => addr.to_string(),
+		_ => return Err("either --cn, --san or --ip has to be given".into()),
+	};
+	let mut distinguished_name = DistinguishedName::new();
+	distinguished_name.push(DnType::CommonName, common_name);
+	Ok(distinguished_name)
+}
+
+/// Reads a PEM or DER encoded file, returning the DER contents
+fn read_input(path :&str) -> CliResult<Vec<u8>> {
+	let contents = fs::read(path)
+		.map_err(|e| format!("could not read {:?}: {}", path, e))?;
+	if contents.starts_with(b"-----BEGIN") {
+		Ok(pem::parse(&contents)?.contents)
+	} else {
+		Ok(contents)
+	}
+}
+
+fn write_output(path :&str, pem_tag :&str, der :&[u8]) -> CliResult<()> {
+	write_file(path, pem_tag, der, false)
+}
+
+/// Writes the private key, encrypted if --key-password is given
+fn write_key(args :&Args, path :&str, key_pair :&KeyPair) -> CliResult<()> {
+	match args.get("key-password") {
+		Some(password) => write_file(path, "ENCRYPTED PRIVATE KEY", &encrypt_key(key_pair, password)?, true),
+		None => write_file(path, "PRIVATE KEY", &key_pair.serialize_der(), true),
+	}
+}
+
+#[cfg(feature = "encryption")]
+fn encrypt_key(key_pair :&KeyPair, password :&str) -> CliResult<Vec<u8>> {
+	Ok(key_pair.serialize_der_encrypted(password)?)
+}
+
+#[cfg(not(feature = "encryption"))]
+fn encrypt_key(_key_pair :&KeyPair, _password :&str) -> CliResult<Vec<u8>> {
+	Err("rcgen was built without the encryption feature, which --key-password needs".into())
+}
+
+#[cfg(feature = "encryption")]
+fn decrypt_key(der :&[u8], password :&str) -> CliResult<KeyPair> {
+	Ok(KeyPair::from_der_encrypted(der, password)?)
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt_key(_der :&[u8], _password :&str) -> CliResult<KeyPair> {
+	Err("rcgen was built without the encryption feature, which --ca-key-password needs".into())
+}
+
+/// Writes the file, making it only readable by its owner if `private` is set
+fn write_file(path :&str, pem_tag :&str, der :&[u8], private :bool) -> CliResult<()> {
+	let contents = if path.ends_with(".der") {
+		der.to_vec()
+	} else {
+		pem::encode(&pem::Pem {
+			tag : pem_tag.to_string(),
+			contents : der.to_vec(),
+		}).into_bytes()
+	};
+	if let Some(parent) = std::path::Path::new(path).parent() {
+		fs::create_dir_all(parent)?;
+	}
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	if private {
+		options.mode(0o600);
+	}
+	let write = |options :&fs::OpenOptions| -> std::io::Result<()> {
+		let mut file = options.open(path)?;
+		// The mode only applies to newly created files
+		#[cfg(unix)]
+		if private {
+			file.set_permissions(fs::Permissions::from_mode(0o600))?;
+		}
+		file.write_all(&contents)
+	};
+	write(&options)
+		.map_err(|e| format!("could not write {:?}: {}", path, e))?;
+	println!("Wrote {}", path);
+	Ok(())
+}
+
+fn parse_serial(serial :&str) -> CliResult<u64> {
+	let parsed = match serial.strip_prefix("0x") {
+		Some(hex) => u64::from_str_radix(&hex.replace(':', ""), 16),
+		None if serial.contains(':') => u64::from_str_radix(&serial.replace(':', ""), 16),
+		None => serial.parse::<u64>(),
+	};
+	parsed.map_err(|_| format!("invalid serial number {:?}", serial).into())
+}
+
+fn serial_from_bytes(bytes :&[u8]) -> CliResult<u64> {
+	let first_nonzero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+	let bytes = &bytes[first_nonzero..];
+	if bytes.len() > 8 {
+		return Err("serial numbers longer than 64 bits are not supported".into());
+	}
+	Ok(bytes.iter().fold(0, |serial, b| (serial << 8) | *b as u64))
+}
+
+/// Lowercases and strips separators, so that "cRLSign" and "crl-sign" are accepted alike
+fn normalize(name :&str) -> String {
+	name.chars()
+		.filter(|c| *c != '-' && *c != '_')
+		.collect::<String>()
+		.to_ascii_lowercase()
+}
+
+fn parse_key_usage(name :&str) -> CliResult<KeyUsagePurpose> {
+	Ok(match normalize(name).as_str() {
+		"digitalsignature" => KeyUsagePurpose::DigitalSignature,
+		"contentcommitment" | "nonrepudiation" => KeyUsagePurpose::ContentCommitment,
+		"keyencipherment" => KeyUsagePurpose::KeyEncipherment,
+		"dataencipherment" => KeyUsagePurpose::DataEncipherment,
+		"keyagreement" => KeyUsagePurpose::KeyAgreement,
+		"keycertsign" => KeyUsagePurpose::KeyCertSign,
+		"crlsign" => KeyUsagePurpose::CrlSign,
+		"encipheronly" => KeyUsagePurpose::EncipherOnly,
+		"decipheronly" => KeyUsagePurpose::DecipherOnly,
+		_ => return Err(format!("unknown key usage {:?}", name).into()),
+	})
+}
+
+fn parse_extended_key_usage(name :&str) -> CliResult<ExtendedKeyUsagePurpose> {
+	Ok(match normalize(name).as_str() {
+		"any" => ExtendedKeyUsagePurpose::Any,
+		"serverauth" => ExtendedKeyUsagePurpose::ServerAuth,
+		"clientauth" => ExtendedKeyUsagePurpose::ClientAuth,
+		"codesigning" => ExtendedKeyUsagePurpose::CodeSigning,
+		"emailprotection" => ExtendedKeyUsagePurpose::EmailProtection,
+		"timestamping" => ExtendedKeyUsagePurpose::TimeStamping,
+		"ocspsigning" => ExtendedKeyUsagePurpose::OcspSigning,
+		_ => return Err(format!("unknown extended key usage {:?}", name).into()),
+	})
+}
+
+fn parse_revocation_reason(name :&str) -> CliResult<RevocationReason> {
+	Ok(match normalize(name).as_str() {
+		"unspecified" => RevocationReason::Unspecified,
+		"keycompromise" => RevocationReason::KeyCompromise,
+		"cacompromise" => RevocationReason::CaCompromise,
+		"affiliationchanged" => RevocationReason::AffiliationChanged,
+		"superseded" => RevocationReason::Superseded,
+		"cessationofoperation" => RevocationReason::CessationOfOperation,
+		"certificatehold" => RevocationReason::CertificateHold,
+		"removefromcrl" => RevocationReason::RemoveFromCrl,
+		"privilegewithdrawn" => RevocationReason::PrivilegeWithdrawn,
+		"aacompromise" => RevocationReason::AaCompromise,
+		_ => return Err(format!("unknown revocation reason {:?}", name).into()),
+	})
+}
@@ -0,0 +1,205 @@
+use std::net::IpAddr;
+use crate::{CertificateParams, CidrSubnet, DistinguishedName, DnValue, GeneralSubtree,
+	NameConstraints, RcgenError, SanType};
+
+impl NameConstraints {
+	/// Checks whether the given subject alternative name is allowed
+	///
+	/// Follows the matching rules of
+	/// [RFC 5280 section 4.2.1.10](https://tools.ietf.org/html/rfc5280#section-4.2.1.10):
+	/// a name is rejected if it matches any excluded subtree, or if there are
+	/// permitted subtrees of its type but none of them matches. As there is
+	/// no URI subtree type, URIs are always allowed.
+	pub fn permits_subject_alt_name(&self, name :&SanType) -> bool {
+		self.permits(|subtree| san_matches(name, subtree))
+	}
+	/// Checks whether the given subject distinguished name is allowed
+	///
+	/// Only directory name subtrees are considered, see
+	/// [`permits_subject_alt_name`](Self::permits_subject_alt_name) for the rules.
+	/// Empty distinguished names are always allowed.
+	pub fn permits_distinguished_name(&self, name :&DistinguishedName) -> bool {
+		if name.iter().next().is_none() {
+			return true;
+		}
+		self.permits(|subtree| match subtree {
+			GeneralSubtree::DirectoryName(base) => Some(directory_name_matches(name, base)),
+			_ => None,
+		})
+	}
+	/// `matches` returns `None` if the subtree has a different type than the checked name
+	fn permits(&self, matches :impl Fn(&GeneralSubtree) -> Option<bool>) -> bool {
+		let excluded = self.excluded_subtrees.iter()
+			.any(|subtree| matches(subtree) == Some(true));
+		if excluded {
+			return false;
+		}
+		let mut same_type_permitted = self.permitted_subtrees.iter()
+			.filter_map(|subtree| matches(subtree))
+			.peekable();
+		if same_type_permitted.peek().is_none() {
+			return true;
+		}
+		same_type_permitted.any(|m| m)
+	}
+}
+
+impl CertificateParams {
+	/// Checks the subject alternative names and the subject against the name constraints of the issuer
+	pub(crate) fn check_name_constraints(&self, issuer :&CertificateParams) -> Result<(), RcgenError> {
+		if issuer.unsupported_name_constraints {
+			return Err(RcgenError::UnsupportedIssuerNameConstraints);
+		}
+		let name_constraints = match &issuer.name_constraints {
+			Some(name_constraints) => name_constraints,
+			None => return Ok(()),
+		};
+		let not_permitted = self.subject_alt_names.iter()
+			.find(|name| !name_constraints.permits_subject_alt_name(name));
+		if let Some(name) = not_permitted {
+			return Err(RcgenError::SubjectAltNameNotPermitted(name.clone()));
+		}
+		if !name_constraints.permits_distinguished_name(&self.distinguished_name) {
+			return Err(RcgenError::SubjectNotPermitted(self.distinguished_name.clone()));
+		}
+		Ok(())
+	}
+}
+
+fn san_matches(name :&SanType, subtree :&GeneralSubtree) -> Option<bool> {
+	match (name, subtree) {
+		(SanType::DnsName(name), GeneralSubtree::DnsName(base)) => {
+			Some(dns_name_matches(name, base))
+		},
+		(SanType::Rfc822Name(name), GeneralSubtree::Rfc822Name(base)) => {
+			Some(rfc822_name_matches(name, base))
+		},
+		(SanType::IpAddress(addr), GeneralSubtree::IpAddress(subnet)) => {
+			Some(ip_address_matches(addr, subnet))
+		},
+		_ => None,
+	}
+}
+
+/// Any name that can be constructed by adding labels to the left of the base matches,
+/// a base with a leading dot only matches such names but not itself
+fn dns_name_matches(name :&str, base :&str) -> bool {
+	let name = name.trim_end_matches('.').to_ascii_lowercase();
+	let base = base.trim_end_matches('.').to_ascii_lowercase();
+	if base.is_empty() {
+		return true;
+	}
+	if base.starts_with('.') {
+		return name.ends_with(&base);
+	}
+	name == base || name.ends_with(&format!(".{}", base))
+}
+
+/// A base containing an `@` is a mailbox, a base with a leading dot
+/// is a domain whose subdomains match, otherwise it's a host
+fn rfc822_name_matches(name :&str, base :&str) -> bool {
+	let host = match name.rsplit_once('@') {
+		Some((_local, host)) => host,
+		None => return false,
+	};
+	if let Some((base_local, base_host)) = base.rsplit_once('@') {
+		let local = &name[..name.len() - host.len() - 1];
+		local == base_local && host.eq_ignore_ascii_case(base_host)
+	} else if base.starts_with('.') {
+		host.to_ascii_lowercase().ends_with(&base.to_ascii_lowercase())
+	} else {
+		host.eq_ignore_ascii_case(base)
+	}
+}
+
+pub(crate) fn ip_address_matches(addr :&IpAddr, subnet :&CidrSubnet) -> bool {
+	fn masked_eq(addr :&[u8], base :&[u8], mask :&[u8]) -> bool {
+		addr.iter().zip(base).zip(mask)
+			.all(|((a, b), m)| a & m == b & m)
+	}
+	match (addr, subnet) {
+		(IpAddr::V4(addr), CidrSubnet::V4(base, mask)) => masked_eq(&addr.octets(), base, mask),
+		(IpAddr::V6(addr), CidrSubnet::V6(base, mask)) => masked_eq(&addr.octets(), base, mask),
+		_ => false,
+	}
+}
+
+/// The base has to be a prefix of the name's sequence of attributes
+fn directory_name_matches(name :&DistinguishedName, base :&DistinguishedName) -> bool {
+	let mut name_iter = name.iter();
+	base.iter().all(|(base_ty, base_value)| {
+		match name_iter.next() {
+			Some((ty, value)) => ty == base_ty && dn_values_match(value, base_value),
+			None => false,
+		}
+	})
+}
+
+fn dn_values_match(value :&DnValue, base :&DnValue) -> bool {
+	fn as_str(value :&DnValue) -> Option<&str> {
+		match value {
+			DnValue::PrintableString(s) | DnValue::Utf8String(s) => Some(s),
+			_ => None,
+		}
+	}
+	match (as_str(value), as_str(base)) {
+		(Some(value), Some(base)) => case_ignore_match(value, base),
+		_ => value == base,
+	}
+}
+
+/// Approximation of the caseIgnoreMatch rule of RFC 4518
+pub(crate) fn case_ignore_match(value :&str, base :&str) -> bool {
+	let normalize = |s :&str| s.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ")
+		.to_lowercase();
+	normalize(value) == normalize(base)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_dns_name_matches() {
+		assert!(dns_name_matches("example.com", "example.com"));
+		assert!(dns_name_matches("www.Example.com", "example.com"));
+		assert!(!dns_name_matches("badexample.com", "example.com"));
+		assert!(!dns_name_matches("example.com", ".example.com"));
+		assert!(dns_name_matches("www.example.com", ".example.com"));
+		assert!(dns_name_matches("anything.test", ""));
+	}
+
+	#[test]
+	fn test_rfc822_name_matches() {
+		assert!(rfc822_name_matches("crab@example.com", "crab@example.com"));
+		assert!(!rfc822_name_matches("lobster@example.com", "crab@example.com"));
+		assert!(rfc822_name_matches("crab@example.com", "example.com"));
+		assert!(!rfc822_name_matches("crab@mail.example.com", "example.com"));
+		assert!(rfc822_name_matches("crab@mail.example.com", ".example.com"));
+	}
+
+	#[test]
+	fn test_permits() {
+		let constraints = NameConstraints {
+			permitted_subtrees : vec![
+				GeneralSubtree::DnsName("example.com".to_string()),
+				GeneralSubtree::IpAddress(CidrSubnet::from_v4_prefix([10, 0, 0, 0], 8)),
+			],
+			excluded_subtrees : vec![
+				GeneralSubtree::DnsName("secret.example.com".to_string()),
+			],
+		};
+		let dns = |s :&str| SanType::DnsName(s.to_string());
+		assert!(constraints.permits_subject_alt_name(&dns("www.example.com")));
+		assert!(!constraints.permits_subject_alt_name(&dns("example.org")));
+		assert!(!constraints.permits_subject_alt_name(&dns("db.secret.example.com")));
+		let ip = |s :&str| SanType::IpAddress(s.parse().unwrap());
+		assert!(constraints.permits_subject_alt_name(&ip("10.1.2.3")));
+		assert!(!constraints.permits_subject_alt_name(&ip("192.168.1.1")));
+		assert!(!constraints.permits_subject_alt_name(&ip("::1")));
+		// No e-mail subtrees at all
+		assert!(constraints.permits_subject_alt_name(&SanType::Rfc822Name("a@b.c".to_string())));
+	}
+}
@@ -0,0 +1,474 @@
+//! Password based encryption of private keys
+//!
+//! Implements the PBES2 scheme of [RFC 8018](https://tools.ietf.org/html/rfc8018#section-6.2)
+//! as used by the `EncryptedPrivateKeyInfo` structure of
+//! [RFC 5958](https://tools.ietf.org/html/rfc5958#section-3).
+
+use yasna::{ASN1Error, ASN1ErrorKind, DERWriter};
+use yasna::models::ObjectIdentifier;
+use ring::{aead, pbkdf2};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::convert::TryInto;
+use std::num::NonZeroU32;
+use crate::RcgenError;
+
+// id-PBES2 in RFC 8018
+const OID_PBES2 :&[u64] = &[1, 2, 840, 113549, 1, 5, 13];
+// id-PBKDF2 in RFC 8018
+const OID_PBKDF2 :&[u64] = &[1, 2, 840, 113549, 1, 5, 12];
+// id-scrypt in RFC 7914
+const OID_SCRYPT :&[u64] = &[1, 3, 6, 1, 4, 1, 11591, 4, 11];
+
+// PRFs for PBKDF2 in RFC 8018
+const OID_HMAC_WITH_SHA1 :&[u64] = &[1, 2, 840, 113549, 2, 7];
+const OID_HMAC_WITH_SHA256 :&[u64] = &[1, 2, 840, 113549, 2, 9];
+const OID_HMAC_WITH_SHA384 :&[u64] = &[1, 2, 840, 113549, 2, 10];
+const OID_HMAC_WITH_SHA512 :&[u64] = &[1, 2, 840, 113549, 2, 11];
+
+// https://tools.ietf.org/html/rfc3565#section-4.1
+const OID_AES_128_CBC :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 2];
+const OID_AES_256_CBC :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 42];
+// https://tools.ietf.org/html/rfc5084#section-3.2
+const OID_AES_128_GCM :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 6];
+const OID_AES_256_GCM :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 46];
+
+// Limits for the parameters of keys to decrypt, which may come from untrusted sources
+pub(crate) const MAX_PBKDF2_ITERATIONS :u32 = 10_000_000;
+const MAX_SCRYPT_MEMORY :u64 = 1 << 30;
+
+const SALT_LEN :usize = 16;
+const AES_BLOCK_LEN :usize = 16;
+const GCM_NONCE_LEN :usize = 12;
+const GCM_TAG_LEN :usize = 16;
+
+/// The function used to derive the encryption key from the password
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub enum KeyDerivationFunction {
+	/// PBKDF2 with HMAC-SHA256 as pseudorandom function, as per
+	/// [RFC 8018](https://tools.ietf.org/html/rfc8018#section-5.2)
+	Pbkdf2HmacSha256 {
+		/// The iteration count
+		iterations :u32,
+	},
+	/// scrypt as per [RFC 7914](https://tools.ietf.org/html/rfc7914)
+	Scrypt {
+		/// Base two logarithm of the CPU/memory cost parameter N
+		log_n :u8,
+		/// The block size parameter r
+		r :u32,
+		/// The parallelization parameter p
+		p :u32,
+	},
+}
+
+/// The cipher used to encrypt the private key with the derived key
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub enum KeyEncryptionCipher {
+	/// AES-256 in CBC mode as per [RFC 3565](https://tools.ietf.org/html/rfc3565)
+	Aes256Cbc,
+	/// AES-256 in GCM mode as per [RFC 5084](https://tools.ietf.org/html/rfc5084)
+	///
+	/// Note that OpenSSL can't read keys encrypted this way.
+	Aes256Gcm,
+}
+
+/// Parameters for the password based encryption of private keys
+///
+/// The default is PBKDF2 with HMAC-SHA256 and 600 000 iterations
+/// together with AES-256-CBC, which is widely supported.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct KeyEncryptionParams {
+	/// The key derivation function
+	pub kdf :KeyDerivationFunction,
+	/// The cipher
+	pub cipher :KeyEncryptionCipher,
+}
+
+impl Default for KeyEncryptionParams {
+	fn default() -> Self {
+		KeyEncryptionParams {
+			kdf : KeyDerivationFunction::Pbkdf2HmacSha256 { iterations : 600_000 },
+			cipher : KeyEncryptionCipher::Aes256Cbc,
+		}
+	}
+}
+
+/// Data encrypted with PBES2, together with the DER encoded
+/// AlgorithmIdentifier describing how to decrypt it.
+pub(crate) struct Pbes2Encrypted {
+	pub(crate) alg_ident :Vec<u8>,
+	pub(crate) data :Vec<u8>,
+}
+
+enum Prf {
+	Sha1,
+	Sha256,
+	Sha384,
+	Sha512,
+}
+
+enum Kdf {
+	Pbkdf2 {
+		salt :Vec<u8>,
+		iterations :u32,
+		prf :Prf,
+	},
+	Scrypt {
+		salt :Vec<u8>,
+		log_n :u8,
+		r :u32,
+		p :u32,
+	},
+}
+
+enum Cipher {
+	Aes128Cbc([u8; AES_BLOCK_LEN]),
+	Aes256Cbc([u8; AES_BLOCK_LEN]),
+	Aes128Gcm([u8; GCM_NONCE_LEN]),
+	Aes256Gcm([u8; GCM_NONCE_LEN]),
+}
+
+impl Cipher {
+	fn key_len(&self) -> usize {
+		match self {
+			Cipher::Aes128Cbc(_) | Cipher::Aes128Gcm(_) => 16,
+			Cipher::Aes256Cbc(_) | Cipher::Aes256Gcm(_) => 32,
+		}
+	}
+}
+
+impl Kdf {
+	fn derive(&self, password :&[u8], key :&mut [u8]) -> Result<(), RcgenError> {
+		match self {
+			Kdf::Pbkdf2 { salt, iterations, prf } => {
+				let alg = match prf {
+					Prf::Sha1 => pbkdf2::PBKDF2_HMAC_SHA1,
+					Prf::Sha256 => pbkdf2::PBKDF2_HMAC_SHA256,
+					Prf::Sha384 => pbkdf2::PBKDF2_HMAC_SHA384,
+					Prf::Sha512 => pbkdf2::PBKDF2_HMAC_SHA512,
+				};
+				let iterations = NonZeroU32::new(*iterations)
+					.ok_or(RcgenError::UnsupportedKeyEncryption)?;
+				pbkdf2::derive(alg, iterations, salt, password, key);
+			},
+			Kdf::Scrypt { salt, log_n, r, p } => {
+				let params = scrypt::Params::new(*log_n, *r, *p)
+					.map_err(|_| RcgenError::UnsupportedKeyEncryption)?;
+				scrypt::scrypt(password, salt, &params, key)
+					.map_err(|_| RcgenError::UnsupportedKeyEncryption)?;
+			},
+		}
+		Ok(())
+	}
+	fn write(&self, writer :DERWriter, key_len :usize) {
+		writer.write_sequence(|writer| {
+			match self {
+				Kdf::Pbkdf2 { salt, iterations, prf } => {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(OID_PBKDF2));
+					writer.next().write_sequence(|writer| {
+						writer.next().write_bytes(salt);
+						writer.next().write_u32(*iterations);
+						writer.next().write_u64(key_len as u64);
+						let prf_oid = match prf {
+							Prf::Sha1 => OID_HMAC_WITH_SHA1,
+							Prf::Sha256 => OID_HMAC_WITH_SHA256,
+							Prf::Sha384 => OID_HMAC_WITH_SHA384,
+							Prf::Sha512 => OID_HMAC_WITH_SHA512,
+						};
+						writer.next().write_sequence(|writer| {
+							writer.next().write_oid(&ObjectIdentifier::from_slice(prf_oid));
+							writer.next().write_null();
+						});
+					});
+				},
+				Kdf::Scrypt { salt, log_n, r, p } => {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(OID_SCRYPT));
+					writer.next().write_sequence(|writer| {
+						writer.next().write_bytes(salt);
+						// log_n < 64 is checked by encrypt
+						writer.next().write_u64(1 << *log_n);
+						writer.next().write_u32(*r);
+						writer.next().write_u32(*p);
+						writer.next().write_u64(key_len as u64);
+					});
+				},
+			}
+		});
+	}
+}
+
+fn oid_is(oid :&ObjectIdentifier, components :&[u64]) -> bool {
+	oid.components().as_slice() == components
+}
+
+fn invalid() -> ASN1Error {
+	ASN1Error::new(ASN1ErrorKind::Invalid)
+}
+
+fn parse_prf(reader :yasna::BERReader) -> yasna::ASN1Result<Prf> {
+	reader.read_sequence(|reader| {
+		let oid = reader.next().read_oid()?;
+		reader.read_optional(|reader| reader.read_null())?;
+		Ok(if oid_is(&oid, OID_HMAC_WITH_SHA1) {
+			Prf::Sha1
+		} else if oid_is(&oid, OID_HMAC_WITH_SHA256) {
+			Prf::Sha256
+		} else if oid_is(&oid, OID_HMAC_WITH_SHA384) {
+			Prf::Sha384
+		} else if oid_is(&oid, OID_HMAC_WITH_SHA512) {
+			Prf::Sha512
+		} else {
+			return Err(invalid());
+		})
+	})
+}
+
+fn parse_kdf(reader :yasna::BERReader) -> yasna::ASN1Result<(Kdf, Option<u64>)> {
+	reader.read_sequence(|reader| {
+		let oid = reader.next().read_oid()?;
+		if oid_is(&oid, OID_PBKDF2) {
+			reader.next().read_sequence(|reader| {
+				let salt = reader.next().read_bytes()?;
+				let iterations = reader.next().read_u32()?;
+				let key_len = reader.read_optional(|reader| reader.read_u64())?;
+				let prf = reader.read_optional(parse_prf)?.unwrap_or(Prf::Sha1);
+				if iterations > MAX_PBKDF2_ITERATIONS {
+					return Err(invalid());
+				}
+				Ok((Kdf::Pbkdf2 { salt, iterations, prf }, key_len))
+			})
+		} else if oid_is(&oid, OID_SCRYPT) {
+			reader.next().read_sequence(|reader| {
+				let salt = reader.next().read_bytes()?;
+				let cost = reader.next().read_u64()?;
+				let r = reader.next().read_u32()?;
+				let p = reader.next().read_u32()?;
+				let key_len = reader.read_optional(|reader| reader.read_u64())?;
+				if !cost.is_power_of_two() {
+					return Err(invalid());
+				}
+				// scrypt needs about 128 * r * (N + p) bytes of memory
+				let memory = cost.checked_add(p as u64)
+					.and_then(|blocks| blocks.checked_mul(128 * r as u64));
+				if memory.map(|memory| memory > MAX_SCRYPT_MEMORY).unwrap_or(true) {
+					return Err(invalid());
+				}
+				let log_n = cost.trailing_zeros() as u8;
+				Ok((Kdf::Scrypt { salt, log_n, r, p }, key_len))
+			})
+		} else {
+			Err(invalid())
+		}
+	})
+}
+
+fn parse_cipher(reader :yasna::BERReader) -> yasna::ASN1Result<Cipher> {
+	reader.read_sequence(|reader| {
+		let oid = reader.next().read_oid()?;
+		if oid_is(&oid, OID_AES_128_CBC) || oid_is(&oid, OID_AES_256_CBC) {
+			let iv = reader.next().read_bytes()?;
+			let iv = iv.as_slice().try_into().map_err(|_| invalid())?;
+			Ok(if oid_is(&oid, OID_AES_128_CBC) {
+				Cipher::Aes128Cbc(iv)
+			} else {
+				Cipher::Aes256Cbc(iv)
+			})
+		} else if oid_is(&oid, OID_AES_128_GCM) || oid_is(&oid, OID_AES_256_GCM) {
+			reader.next().read_sequence(|reader| {
+				let nonce = reader.next().read_bytes()?;
+				let nonce = nonce.as_slice().try_into().map_err(|_| invalid())?;
+				let tag_len = reader.read_default(12, |reader| reader.read_u8())?;
+				// ring only supports full length tags
+				if tag_len as usize != GCM_TAG_LEN {
+					return Err(invalid());
+				}
+				Ok(if oid_is(&oid, OID_AES_128_GCM) {
+					Cipher::Aes128Gcm(nonce)
+				} else {
+					Cipher::Aes256Gcm(nonce)
+				})
+			})
+		} else {
+			Err(invalid())
+		}
+	})
+}
+
+fn write_cipher(writer :DERWriter, cipher :&Cipher) {
+	writer.write_sequence(|writer| {
+		match cipher {
+			Cipher::Aes128Cbc(iv) | Cipher::Aes256Cbc(iv) => {
+				let oid = if let Cipher::Aes128Cbc(_) = cipher { OID_AES_128_CBC } else { OID_AES_256_CBC };
+				writer.next().write_oid(&ObjectIdentifier::from_slice(oid));
+				writer.next().write_bytes(iv);
+			},
+			Cipher::Aes128Gcm(nonce) | Cipher::Aes256Gcm(nonce) => {
+				let oid = if let Cipher::Aes128Gcm(_) = cipher { OID_AES_128_GCM } else { OID_AES_256_GCM };
+				writer.next().write_oid(&ObjectIdentifier::from_slice(oid));
+				writer.next().write_sequence(|writer| {
+					writer.next().write_bytes(nonce);
+					writer.next().write_u8(GCM_TAG_LEN as u8);
+				});
+			},
+		}
+	});
+}
+
+fn gcm_key(cipher :&Cipher, key :&[u8]) -> Result<aead::LessSafeKey, RcgenError> {
+	let alg = if let Cipher::Aes128Gcm(_) = cipher { &aead::AES_128_GCM } else { &aead::AES_256_GCM };
+	let key = aead::UnboundKey::new(alg, key)?;
+	Ok(aead::LessSafeKey::new(key))
+}
+
+fn cbc_encrypt(cipher :&Cipher, key :&[u8], iv :&[u8], plaintext :&[u8]) -> Vec<u8> {
+	use cbc::cipher::{BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+	// The key length is determined by the cipher and therefore always valid
+	match cipher {
+		Cipher::Aes128Cbc(_) => cbc::Encryptor::<aes::Aes128>::new_from_slices(key, iv)
+			.expect("invalid key or iv length")
+			.encrypt_padded_vec_mut::<Pkcs7>(plaintext),
+		_ => cbc::Encryptor::<aes::Aes256>::new_from_slices(key, iv)
+			.expect("invalid key or iv length")
+			.encrypt_padded_vec_mut::<Pkcs7>(plaintext),
+	}
+}
+
+fn cbc_decrypt(cipher :&Cipher, key :&[u8], iv :&[u8], ciphertext :&[u8]) -> Result<Vec<u8>, RcgenError> {
+	use cbc::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
+	let res = match cipher {
+		Cipher::Aes128Cbc(_) => cbc::Decryptor::<aes::Aes128>::new_from_slices(key, iv)
+			.expect("invalid key or iv length")
+			.decrypt_padded_vec_mut::<Pkcs7>(ciphertext),
+		_ => cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)
+			.expect("invalid key or iv length")
+			.decrypt_padded_vec_mut::<Pkcs7>(ciphertext),
+	};
+	// A padding error is what we usually get when the password is wrong
+	res.map_err(|_| RcgenError::KeyDecryptionFailed)
+}
+
+/// Encrypts the given data with a key derived from the password
+pub(crate) fn encrypt(plaintext :&[u8], password :&[u8], params :&KeyEncryptionParams) -> Result<Pbes2Encrypted, RcgenError> {
+	let system_random = SystemRandom::new();
+	let mut salt = vec![0; SALT_LEN];
+	system_random.fill(&mut salt)?;
+	let kdf = match params.kdf {
+		KeyDerivationFunction::Pbkdf2HmacSha256 { iterations } => Kdf::Pbkdf2 {
+			salt,
+			iterations,
+			prf : Prf::Sha256,
+		},
+		KeyDerivationFunction::Scrypt { log_n, r, p } => {
+			// Larger costs can't be encoded
+			if log_n >= 64 {
+				return Err(RcgenError::UnsupportedKeyEncryption);
+			}
+			Kdf::Scrypt {
+				salt,
+				log_n,
+				r,
+				p,
+			}
+		},
+	};
+	let cipher = match params.cipher {
+		KeyEncryptionCipher::Aes256Cbc => {
+			let mut iv = [0; AES_BLOCK_LEN];
+			system_random.fill(&mut iv)?;
+			Cipher::Aes256Cbc(iv)
+		},
+		KeyEncryptionCipher::Aes256Gcm => {
+			let mut nonce = [0; GCM_NONCE_LEN];
+			system_random.fill(&mut nonce)?;
+			Cipher::Aes256Gcm(nonce)
+		},
+	};
+	let mut key = vec![0; cipher.key_len()];
+	kdf.derive(password, &mut key)?;
+
+	let data = match &cipher {
+		Cipher::Aes128Cbc(iv) | Cipher::Aes256Cbc(iv) => cbc_encrypt(&cipher, &key, iv, plaintext),
+		Cipher::Aes128Gcm(nonce) | Cipher::Aes256Gcm(nonce) => {
+			let key = gcm_key(&cipher, &key)?;
+			let mut in_out = plaintext.to_vec();
+			key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(*nonce),
+				aead::Aad::empty(), &mut in_out)?;
+			in_out
+		},
+	};
+
+	let alg_ident = yasna::construct_der(|writer| {
+		writer.write_sequence(|writer| {
+			writer.next().write_oid(&ObjectIdentifier::from_slice(OID_PBES2));
+			writer.next().write_sequence(|writer| {
+				kdf.write(writer.next(), key.len());
+				write_cipher(writer.next(), &cipher);
+			});
+		});
+	});
+	Ok(Pbes2Encrypted {
+		alg_ident,
+		data,
+	})
+}
+
+/// Decrypts data encrypted with PBES2, described by the DER encoded AlgorithmIdentifier
+pub(crate) fn decrypt(alg_ident :&[u8], data :&[u8], password :&[u8]) -> Result<Vec<u8>, RcgenError> {
+	let (kdf, key_len, cipher) = yasna::parse_ber(alg_ident, |reader| {
+		reader.read_sequence(|reader| {
+			let oid = reader.next().read_oid()?;
+			if !oid_is(&oid, OID_PBES2) {
+				return Err(invalid());
+			}
+			reader.next().read_sequence(|reader| {
+				let (kdf, key_len) = parse_kdf(reader.next())?;
+				let cipher = parse_cipher(reader.next())?;
+				Ok((kdf, key_len, cipher))
+			})
+		})
+	}).map_err(|_| RcgenError::UnsupportedKeyEncryption)?;
+	if key_len.map(|l| l as usize != cipher.key_len()).unwrap_or(false) {
+		return Err(RcgenError::UnsupportedKeyEncryption);
+	}
+	let mut key = vec![0; cipher.key_len()];
+	kdf.derive(password, &mut key)?;
+
+	match &cipher {
+		Cipher::Aes128Cbc(iv) | Cipher::Aes256Cbc(iv) => cbc_decrypt(&cipher, &key, iv, data),
+		Cipher::Aes128Gcm(nonce) | Cipher::Aes256Gcm(nonce) => {
+			let key = gcm_key(&cipher, &key)?;
+			let mut in_out = data.to_vec();
+			let plaintext_len = key.open_in_place(aead::Nonce::assume_unique_for_key(*nonce),
+				aead::Aad::empty(), &mut in_out)
+				.map_err(|_| RcgenError::KeyDecryptionFailed)?
+				.len();
+			in_out.truncate(plaintext_len);
+			Ok(in_out)
+		},
+	}
+}
+
+/// Wraps a PKCS#8 document into an `EncryptedPrivateKeyInfo` structure
+pub(crate) fn encrypt_private_key_info(pkcs8 :&[u8], password :&[u8], params :&KeyEncryptionParams) -> Result<Vec<u8>, RcgenError> {
+	let encrypted = encrypt(pkcs8, password, params)?;
+	Ok(yasna::construct_der(|writer| {
+		writer.write_sequence(|writer| {
+			writer.next().write_der(&encrypted.alg_ident);
+			writer.next().write_bytes(&encrypted.data);
+		});
+	}))
+}
+
+/// Obtains the PKCS#8 document from an `EncryptedPrivateKeyInfo` structure
+pub(crate) fn decrypt_private_key_info(der :&[u8], password :&[u8]) -> Result<Vec<u8>, RcgenError> {
+	let (alg_ident, data) = yasna::parse_ber(der, |reader| {
+		reader.read_sequence(|reader| {
+			let alg_ident = reader.next().read_der()?;
+			let data = reader.next().read_bytes()?;
+			Ok((alg_ident, data))
+		})
+	}).map_err(|_| RcgenError::CouldNotParseKeyPair)?;
+	decrypt(&alg_ident, &data, password)
+}
@@ -0,0 +1,568 @@
+//! PKCS#12 (PFX) archives as per [RFC 7292](https://tools.ietf.org/html/rfc7292)
+//!
+//! The layout of the written archives follows what OpenSSL 3 produces by default:
+//! certificates are stored in an encrypted data content, the private key in a
+//! shrouded key bag, both using PBES2, and integrity is protected by a HMAC-SHA256
+//! based MAC.
+
+use yasna::{ASN1Error, ASN1ErrorKind, DERWriter, Tag};
+use yasna::models::ObjectIdentifier;
+use ring::{digest, hmac};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::{Certificate, KeyPair, KeyEncryptionParams, RcgenError};
+use crate::pbes2;
+
+// pkcs-7 content types in RFC 2315
+const OID_PKCS7_DATA :&[u64] = &[1, 2, 840, 113549, 1, 7, 1];
+const OID_PKCS7_ENCRYPTED_DATA :&[u64] = &[1, 2, 840, 113549, 1, 7, 6];
+
+// Bag types in RFC 7292 section 4.2
+const OID_KEY_BAG :&[u64] = &[1, 2, 840, 113549, 1, 12, 10, 1, 1];
+const OID_PKCS8_SHROUDED_KEY_BAG :&[u64] = &[1, 2, 840, 113549, 1, 12, 10, 1, 2];
+const OID_CERT_BAG :&[u64] = &[1, 2, 840, 113549, 1, 12, 10, 1, 3];
+// x509Certificate in RFC 7292 section 4.2.3
+const OID_X509_CERTIFICATE :&[u64] = &[1, 2, 840, 113549, 1, 9, 22, 1];
+
+// pkcs-9-at-friendlyName and pkcs-9-at-localKeyId in RFC 2985
+const OID_FRIENDLY_NAME :&[u64] = &[1, 2, 840, 113549, 1, 9, 20];
+const OID_LOCAL_KEY_ID :&[u64] = &[1, 2, 840, 113549, 1, 9, 21];
+
+// Digest algorithms for the MAC
+const OID_SHA1 :&[u64] = &[1, 3, 14, 3, 2, 26];
+const OID_SHA256 :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+const OID_SHA384 :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 2];
+const OID_SHA512 :&[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 3];
+
+const MAC_SALT_LEN :usize = 16;
+
+/// Parameters for the creation of a PKCS#12 archive
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Pkcs12Params {
+	/// Friendly name attached to the key and the certificate,
+	/// used by many tools as the alias of the entry
+	pub friendly_name :Option<String>,
+	/// DER encoded issuer certificates to include in the archive,
+	/// starting with the direct issuer of the certificate
+	pub ca_certificates :Vec<Vec<u8>>,
+	/// Parameters for the encryption of both the private key and the certificates
+	pub encryption :KeyEncryptionParams,
+	/// Iteration count for the derivation of the MAC key
+	pub mac_iterations :u32,
+}
+
+impl Default for Pkcs12Params {
+	fn default() -> Self {
+		Pkcs12Params {
+			friendly_name : None,
+			ca_certificates : Vec::new(),
+			encryption : KeyEncryptionParams::default(),
+			mac_iterations : 2048,
+		}
+	}
+}
+
+/// The contents of a PKCS#12 archive
+#[derive(Debug)]
+pub struct Pkcs12Contents {
+	/// The private key stored in the archive
+	pub key_pair :KeyPair,
+	/// The DER encoded certificate belonging to the private key
+	pub certificate_der :Vec<u8>,
+	/// All other DER encoded certificates stored in the archive,
+	/// usually the issuer chain
+	pub ca_certificates :Vec<Vec<u8>>,
+	/// The friendly name of the private key, if present
+	pub friendly_name :Option<String>,
+}
+
+impl Pkcs12Contents {
+	/// Parses and decrypts a PKCS#12 archive
+	///
+	/// Supports archives that use PBES2 for encryption, like the
+	/// ones created by rcgen or by OpenSSL 3. Archives only using the
+	/// legacy PKCS#12 encryption schemes (RC2 or Triple DES) are rejected
+	/// with [`RcgenError::UnsupportedKeyEncryption`].
+	///
+	/// Archives without MacData are rejected with
+	/// [`RcgenError::Pkcs12NotAuthenticated`], as their integrity can't be verified.
+	/// MacData with more than 10 000 000 iterations is rejected with
+	/// [`RcgenError::CouldNotParsePkcs12`].
+	///
+	/// The private key is matched to the certificate containing its public key,
+	/// preferring the one with the same localKeyID attribute if there are several.
+	/// If no certificate contains the public key,
+	/// [`RcgenError::CertificateKeyPairMismatch`] is returned.
+	pub fn from_der(der :&[u8], password :&str) -> Result<Self, RcgenError> {
+		let (auth_safe, mac_data) = yasna::parse_ber(der, |reader| {
+			reader.read_sequence(|reader| {
+				let version = reader.next().read_u8()?;
+				if version != 3 {
+					return Err(invalid());
+				}
+				let auth_safe = reader.next().read_sequence(|reader| {
+					let oid = reader.next().read_oid()?;
+					if !oid_is(&oid, OID_PKCS7_DATA) {
+						return Err(invalid());
+					}
+					reader.next().read_tagged(Tag::context(0), |reader| reader.read_bytes())
+				})?;
+				let mac_data = reader.read_optional(parse_mac_data)?;
+				Ok((auth_safe, mac_data))
+			})
+		}).map_err(|_| RcgenError::CouldNotParsePkcs12)?;
+
+		let mac_data = mac_data.ok_or(RcgenError::Pkcs12NotAuthenticated)?;
+		mac_data.verify(&auth_safe, password)?;
+
+		let contents = yasna::parse_ber(&auth_safe, |reader| {
+			reader.collect_sequence_of(parse_content_info)
+		}).map_err(|_| RcgenError::CouldNotParsePkcs12)?;
+
+		let mut bags = Vec::new();
+		for content in contents {
+			let safe_contents = match content {
+				ContentInfo::Data(data) => data,
+				ContentInfo::EncryptedData { alg_ident, data } => {
+					pbes2::decrypt(&alg_ident, &data, password.as_bytes())?
+				},
+			};
+			let safe_contents = yasna::parse_ber(&safe_contents, |reader| {
+				reader.collect_sequence_of(parse_safe_bag)
+			}).map_err(|_| RcgenError::CouldNotParsePkcs12)?;
+			bags.extend(safe_contents);
+		}
+
+		let mut key = None;
+		let mut certificates = Vec::new();
+		for bag in bags {
+			match bag.value {
+				BagValue::Key(pkcs8) => {
+					key = Some((pkcs8, bag.local_key_id, bag.friendly_name));
+				},
+				BagValue::ShroudedKey(encrypted) => {
+					let pkcs8 = pbes2::decrypt_private_key_info(&encrypted, password.as_bytes())?;
+					key = Some((pkcs8, bag.local_key_id, bag.friendly_name));
+				},
+				BagValue::Certificate(cert) => certificates.push((cert, bag.local_key_id)),
+				BagValue::Other => (),
+			}
+		}
+		let (pkcs8, local_key_id, friendly_name) = key.ok_or(RcgenError::CouldNotParsePkcs12)?;
+		let key_pair = KeyPair::from_der(&pkcs8)?;
+
+		let public_key_der = key_pair.public_key_der();
+		let matches_key = |cert :&[u8]| certificate_spki(cert).as_deref() == Some(&public_key_der[..]);
+		let cert_pos = certificates.iter()
+			.position(|(cert, id)| local_key_id.is_some() && id == &local_key_id && matches_key(cert))
+			.or_else(|| certificates.iter().position(|(cert, _id)| matches_key(cert)))
+			.ok_or(RcgenError::CertificateKeyPairMismatch)?;
+		let (certificate_der, _id) = certificates.remove(cert_pos);
+		Ok(Pkcs12Contents {
+			key_pair,
+			certificate_der,
+			ca_certificates : certificates.into_iter().map(|(cert, _id)| cert).collect(),
+			friendly_name,
+		})
+	}
+}
+
+impl KeyPair {
+	/// Serializes the key pair together with its certificate into a password protected PKCS#12 archive
+	///
+	/// `certificate_der` is the DER encoded certificate belonging to the key pair,
+	/// for example the output of [`Certificate::serialize_der_with_signer`].
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "encryption" feature*
+	pub fn serialize_pkcs12_der(&self, certificate_der :&[u8], password :&str, params :&Pkcs12Params) -> Result<Vec<u8>, RcgenError> {
+		let local_key_id = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, certificate_der);
+		let local_key_id = local_key_id.as_ref();
+		let friendly_name = params.friendly_name.as_deref();
+
+		// The certificates, in an encrypted data content
+		let cert_contents = yasna::construct_der(|writer| {
+			writer.write_sequence_of(|writer| {
+				write_cert_bag(writer.next(), certificate_der, Some(local_key_id), friendly_name);
+				for ca_cert in params.ca_certificates.iter() {
+					write_cert_bag(writer.next(), ca_cert, None, None);
+				}
+			});
+		});
+		let encrypted_certs = pbes2::encrypt(&cert_contents, password.as_bytes(), &params.encryption)?;
+
+		// The private key, in a shrouded key bag
+		let encrypted_key = pbes2::encrypt_private_key_info(&self.serialize_der(),
+			password.as_bytes(), &params.encryption)?;
+		let key_contents = yasna::construct_der(|writer| {
+			writer.write_sequence_of(|writer| {
+				writer.next().write_sequence(|writer| {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(OID_PKCS8_SHROUDED_KEY_BAG));
+					writer.next().write_tagged(Tag::context(0), |writer| {
+						writer.write_der(&encrypted_key);
+					});
+					write_bag_attributes(writer.next(), Some(local_key_id), friendly_name);
+				});
+			});
+		});
+
+		let auth_safe = yasna::construct_der(|writer| {
+			writer.write_sequence_of(|writer| {
+				writer.next().write_sequence(|writer| {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(OID_PKCS7_ENCRYPTED_DATA));
+					writer.next().write_tagged(Tag::context(0), |writer| {
+						writer.write_sequence(|writer| {
+							// version
+							writer.next().write_u8(0);
+							// EncryptedContentInfo
+							writer.next().write_sequence(|writer| {
+								writer.next().write_oid(&ObjectIdentifier::from_slice(OID_PKCS7_DATA));
+								writer.next().write_der(&encrypted_certs.alg_ident);
+								writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+									writer.write_bytes(&encrypted_certs.data);
+								});
+							});
+						});
+					});
+				});
+				write_data_content_info(writer.next(), &key_contents);
+			});
+		});
+
+		let mut mac_salt = [0; MAC_SALT_LEN];
+		SystemRandom::new().fill(&mut mac_salt)?;
+		let mac_key = pkcs12_kdf(&digest::SHA256, password, &mac_salt, 3,
+			params.mac_iterations, digest::SHA256.output_len);
+		let mac = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &mac_key), &auth_safe);
+
+		Ok(yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				writer.next().write_u8(3);
+				write_data_content_info(writer.next(), &auth_safe);
+				// MacData
+				writer.next().write_sequence(|writer| {
+					writer.next().write_sequence(|writer| {
+						writer.next().write_sequence(|writer| {
+							writer.next().write_oid(&ObjectIdentifier::from_slice(OID_SHA256));
+							writer.next().write_null();
+						});
+						writer.next().write_bytes(mac.as_ref());
+					});
+					writer.next().write_bytes(&mac_salt);
+					writer.next().write_u32(params.mac_iterations);
+				});
+			});
+		}))
+	}
+}
+
+impl Certificate {
+	/// Serializes the certificate's key pair together with the certificate
+	/// into a password protected PKCS#12 archive
+	///
+	/// See [`KeyPair::serialize_pkcs12_der`] for more details.
+	///
+	/// Panics if called on a remote key pair.
+	///
+	/// *This function is only available if rcgen is built with the "encryption" feature*
+	pub fn serialize_pkcs12_der(&self, certificate_der :&[u8], password :&str, params :&Pkcs12Params) -> Result<Vec<u8>, RcgenError> {
+		self.get_key_pair().serialize_pkcs12_der(certificate_der, password, params)
+	}
+}
+
+fn oid_is(oid :&ObjectIdentifier, components :&[u64]) -> bool {
+	oid.components().as_slice() == components
+}
+
+fn invalid() -> ASN1Error {
+	ASN1Error::new(ASN1ErrorKind::Invalid)
+}
+
+/// Extracts the DER encoded SubjectPublicKeyInfo of a DER encoded certificate
+fn certificate_spki(cert_der :&[u8]) -> Option<Vec<u8>> {
+	yasna::parse_der(cert_der, |reader| {
+		reader.read_sequence(|reader| {
+			let spki = reader.next().read_sequence(|reader| {
+				reader.read_optional(|reader| reader.read_tagged(Tag::context(0), |reader| reader.read_der()))?;
+				// serialNumber, signature, issuer, validity and subject
+				for _ in 0..5 {
+					reader.next().read_der()?;
+				}
+				let spki = reader.next().read_der()?;
+				// Unique identifiers and extensions
+				while reader.read_optional(|reader| reader.read_der())?.is_some() {}
+				Ok(spki)
+			})?;
+			reader.next().read_der()?;
+			reader.next().read_der()?;
+			Ok(spki)
+		})
+	}).ok()
+}
+
+fn write_data_content_info(writer :DERWriter, content :&[u8]) {
+	writer.write_sequence(|writer| {
+		writer.next().write_oid(&ObjectIdentifier::from_slice(OID_PKCS7_DATA));
+		writer.next().write_tagged(Tag::context(0), |writer| {
+			writer.write_bytes(content);
+		});
+	});
+}
+
+fn write_cert_bag(writer :DERWriter, cert_der :&[u8], local_key_id :Option<&[u8]>, friendly_name :Option<&str>) {
+	writer.write_sequence(|writer| {
+		writer.next().write_oid(&ObjectIdentifier::from_slice(OID_CERT_BAG));
+		writer.next().write_tagged(Tag::context(0), |writer| {
+			writer.write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(OID_X509_CERTIFICATE));
+				writer.next().write_tagged(Tag::context(0), |writer| {
+					writer.write_bytes(cert_der);
+				});
+			});
+		});
+		if local_key_id.is_some() || friendly_name.is_some() {
+			write_bag_attributes(writer.next(), local_key_id, friendly_name);
+		}
+	});
+}
+
+fn write_bag_attributes(writer :DERWriter, local_key_id :Option<&[u8]>, friendly_name :Option<&str>) {
+	writer.write_set_of(|writer| {
+		if let Some(friendly_name) = friendly_name {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(OID_FRIENDLY_NAME));
+				writer.next().write_set_of(|writer| {
+					writer.next().write_bmp_string(friendly_name);
+				});
+			});
+		}
+		if let Some(local_key_id) = local_key_id {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(OID_LOCAL_KEY_ID));
+				writer.next().write_set_of(|writer| {
+					writer.next().write_bytes(local_key_id);
+				});
+			});
+		}
+	});
+}
+
+struct MacData {
+	digest_alg :&'static digest::Algorithm,
+	hmac_alg :hmac::Algorithm,
+	mac :Vec<u8>,
+	salt :Vec<u8>,
+	iterations :u32,
+}
+
+impl MacData {
+	fn verify(&self, auth_safe :&[u8], password :&str) -> Result<(), RcgenError> {
+		let mac_key = pkcs12_kdf(self.digest_alg, password, &self.salt, 3,
+			self.iterations, self.digest_alg.output_len);
+		hmac::verify(&hmac::Key::new(self.hmac_alg, &mac_key), auth_safe, &self.mac)
+			.map_err(|_| RcgenError::KeyDecryptionFailed)
+	}
+}
+
+fn parse_mac_data(reader :yasna::BERReader) -> yasna::ASN1Result<MacData> {
+	reader.read_sequence(|reader| {
+		let (digest_alg, hmac_alg, mac) = reader.next().read_sequence(|reader| {
+			let oid = reader.next().read_sequence(|reader| {
+				let oid = reader.next().read_oid()?;
+				reader.read_optional(|reader| reader.read_null())?;
+				Ok(oid)
+			})?;
+			let (digest_alg, hmac_alg) = if oid_is(&oid, OID_SHA1) {
+				(&digest::SHA1_FOR_LEGACY_USE_ONLY, hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY)
+			} else if oid_is(&oid, OID_SHA256) {
+				(&digest::SHA256, hmac::HMAC_SHA256)
+			} else if oid_is(&oid, OID_SHA384) {
+				(&digest::SHA384, hmac::HMAC_SHA384)
+			} else if oid_is(&oid, OID_SHA512) {
+				(&digest::SHA512, hmac::HMAC_SHA512)
+			} else {
+				return Err(invalid());
+			};
+			let mac = reader.next().read_bytes()?;
+			Ok((digest_alg, hmac_alg, mac))
+		})?;
+		let salt = reader.next().read_bytes()?;
+		let iterations = reader.read_optional(|reader| reader.read_u32())?.unwrap_or(1);
+		if iterations > pbes2::MAX_PBKDF2_ITERATIONS {
+			return Err(invalid());
+		}
+		Ok(MacData {
+			digest_alg,
+			hmac_alg,
+			mac,
+			salt,
+			iterations,
+		})
+	})
+}
+
+enum ContentInfo {
+	Data(Vec<u8>),
+	EncryptedData {
+		alg_ident :Vec<u8>,
+		data :Vec<u8>,
+	},
+}
+
+fn parse_content_info(reader :yasna::BERReader) -> yasna::ASN1Result<ContentInfo> {
+	reader.read_sequence(|reader| {
+		let oid = reader.next().read_oid()?;
+		reader.next().read_tagged(Tag::context(0), |reader| {
+			if oid_is(&oid, OID_PKCS7_DATA) {
+				Ok(ContentInfo::Data(reader.read_bytes()?))
+			} else if oid_is(&oid, OID_PKCS7_ENCRYPTED_DATA) {
+				reader.read_sequence(|reader| {
+					let _version = reader.next().read_u8()?;
+					reader.next().read_sequence(|reader| {
+						let content_type = reader.next().read_oid()?;
+						if !oid_is(&content_type, OID_PKCS7_DATA) {
+							return Err(invalid());
+						}
+						let alg_ident = reader.next().read_der()?;
+						let data = reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+							reader.read_bytes()
+						})?;
+						Ok(ContentInfo::EncryptedData { alg_ident, data })
+					})
+				})
+			} else {
+				Err(invalid())
+			}
+		})
+	})
+}
+
+enum BagValue {
+	Key(Vec<u8>),
+	ShroudedKey(Vec<u8>),
+	Certificate(Vec<u8>),
+	Other,
+}
+
+struct SafeBag {
+	value :BagValue,
+	local_key_id :Option<Vec<u8>>,
+	friendly_name :Option<String>,
+}
+
+fn parse_safe_bag(reader :yasna::BERReader) -> yasna::ASN1Result<SafeBag> {
+	reader.read_sequence(|reader| {
+		let oid = reader.next().read_oid()?;
+		let value = reader.next().read_tagged(Tag::context(0), |reader| {
+			if oid_is(&oid, OID_KEY_BAG) {
+				Ok(BagValue::Key(reader.read_der()?))
+			} else if oid_is(&oid, OID_PKCS8_SHROUDED_KEY_BAG) {
+				Ok(BagValue::ShroudedKey(reader.read_der()?))
+			} else if oid_is(&oid, OID_CERT_BAG) {
+				reader.read_sequence(|reader| {
+					let cert_type = reader.next().read_oid()?;
+					let value = reader.next().read_tagged(Tag::context(0), |reader| reader.read_bytes())?;
+					Ok(if oid_is(&cert_type, OID_X509_CERTIFICATE) {
+						BagValue::Certificate(value)
+					} else {
+						BagValue::Other
+					})
+				})
+			} else {
+				reader.read_der()?;
+				Ok(BagValue::Other)
+			}
+		})?;
+		let mut local_key_id = None;
+		let mut friendly_name = None;
+		reader.read_optional(|reader| {
+			reader.read_set_of(|reader| {
+				reader.read_sequence(|reader| {
+					let attr_oid = reader.next().read_oid()?;
+					reader.next().read_set_of(|reader| {
+						if oid_is(&attr_oid, OID_LOCAL_KEY_ID) {
+							local_key_id = Some(reader.read_bytes()?);
+						} else if oid_is(&attr_oid, OID_FRIENDLY_NAME) {
+							friendly_name = Some(reader.read_bmp_string()?);
+						} else {
+							reader.read_der()?;
+						}
+						Ok(())
+					})
+				})
+			})
+		})?;
+		Ok(SafeBag {
+			value,
+			local_key_id,
+			friendly_name,
+		})
+	})
+}
+
+/// The key derivation function of [RFC 7292 appendix B](https://tools.ietf.org/html/rfc7292#appendix-B.2)
+///
+/// It's only needed for the MAC as the encryption uses PBES2.
+fn pkcs12_kdf(alg :&'static digest::Algorithm, password :&str, salt :&[u8], id :u8, iterations :u32, len :usize) -> Vec<u8> {
+	let u = alg.output_len;
+	let v = alg.block_len;
+
+	// The password is a null terminated BMPString
+	let mut password_bmp = password.encode_utf16()
+		.flat_map(|c| c.to_be_bytes())
+		.collect::<Vec<u8>>();
+	password_bmp.extend_from_slice(&[0, 0]);
+
+	let diversifier = vec![id; v];
+	let fill = |data :&[u8]| -> Vec<u8> {
+		let len = v * ((data.len() + v - 1) / v);
+		data.iter().copied().cycle().take(len).collect()
+	};
+	let mut i = fill(salt);
+	i.extend_from_slice(&fill(&password_bmp));
+
+	let mut res = Vec::with_capacity(len);
+	while res.len() < len {
+		let mut ctx = digest::Context::new(alg);
+		ctx.update(&diversifier);
+		ctx.update(&i);
+		let mut a = ctx.finish();
+		for _ in 1..iterations {
+			a = digest::digest(alg, a.as_ref());
+		}
+		let a = a.as_ref();
+		res.extend_from_slice(&a[..u.min(len - res.len())]);
+
+		// Set I_j = (I_j + B + 1) mod 2^(v*8) for each block I_j of I
+		let b = a.iter().copied().cycle().take(v).collect::<Vec<u8>>();
+		for block in i.chunks_mut(v) {
+			let mut carry = 1u16;
+			for (x, y) in block.iter_mut().zip(b.iter()).rev() {
+				let sum = *x as u16 + *y as u16 + carry;
+				*x = sum as u8;
+				carry = sum >> 8;
+			}
+		}
+	}
+	res
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::convert::TryInto;
+
+	#[test]
+	fn test_pkcs12_kdf() {
+		// Well known test vector for the PKCS#12 key derivation function
+		let salt = [0x0A, 0x58, 0xCF, 0x64, 0x53, 0x0D, 0x82, 0x3F];
+		let key = pkcs12_kdf(&digest::SHA1_FOR_LEGACY_USE_ONLY, "smeg", &salt, 1, 1, 24);
+		let expected :[u8; 24] = [
+			0x8A, 0xAA, 0xE6, 0x29, 0x7B, 0x6C, 0xB0, 0x46,
+			0x42, 0xAB, 0x5B, 0x07, 0x78, 0x51, 0x28, 0x4E,
+			0xB7, 0x12, 0x8F, 0x1A, 0x2A, 0x7F, 0xBC, 0xA3,
+		];
+		let key :[u8; 24] = key.as_slice().try_into().unwrap();
+		assert_eq!(key, expected);
+	}
+}
@@ -0,0 +1,124 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use crate::{BasicConstraints, CertificateParams, DistinguishedName, DnType,
+	ExtendedKeyUsagePurpose, IsCa, KeyUsagePurpose, SanType};
+
+/// Certificate profiles for common use cases
+///
+/// Each profile sets the subject, key usages, extended key usages and
+/// basic constraints the respective use case needs, and a validity period
+/// starting now. All fields can be adjusted afterwards.
+impl CertificateParams {
+	/// Profile for TLS server certificates
+	///
+	/// Names that parse as IP addresses become IP address subject alternative names,
+	/// all others DNS names. The first name is used as common name. Following the
+	/// CA/Browser Forum baseline requirements, the certificate is valid for 397 days,
+	/// with the `DigitalSignature` key usage and the `ServerAuth` extended key usage.
+	pub fn tls_server(names :impl Into<Vec<String>>) -> Self {
+		let names = names.into();
+		let mut params = Self::profile(names.first().map(|name| name.as_str()), 397);
+		params.subject_alt_names = names.into_iter()
+			.map(|name| match IpAddr::from_str(&name) {
+				Ok(addr) => SanType::IpAddress(addr),
+				Err(_) => SanType::DnsName(name),
+			})
+			.collect();
+		params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+		params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+		params
+	}
+	/// Profile for TLS client certificates
+	///
+	/// The identity is used as common name. If it contains an `@`, it is also added
+	/// as e-mail subject alternative name, otherwise as DNS name. The certificate
+	/// is valid for one year, with the `DigitalSignature` key usage and the
+	/// `ClientAuth` extended key usage.
+	pub fn tls_client(identity :&str) -> Self {
+		let mut params = Self::profile(Some(identity), 365);
+		params.subject_alt_names = if identity.contains('@') {
+			vec![SanType::Rfc822Name(identity.to_string())]
+		} else {
+			vec![SanType::DnsName(identity.to_string())]
+		};
+		params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+		params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+		params
+	}
+	/// Profile for code signing certificates
+	///
+	/// The given name, usually the one of the publisher, is used as common name.
+	/// The certificate is valid for three years, with the `DigitalSignature` key usage
+	/// and the `CodeSigning` extended key usage.
+	pub fn code_signing(common_name :&str) -> Self {
+		let mut params = Self::profile(Some(common_name), 3 * 365);
+		params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+		params.extended_key_usages = vec![ExtendedKeyUsagePurpose::CodeSigning];
+		params
+	}
+	/// Profile for time-stamp authority certificates
+	///
+	/// The given name is used as common name. The certificate is valid for
+	/// three years, with the `DigitalSignature` key usage and the `TimeStamping`
+	/// extended key usage as its only purpose in a critical extension, which RFC 3161 requires.
+	/// See [`TimeStampAuthority`](crate::TimeStampAuthority).
+	pub fn time_stamping(common_name :&str) -> Self {
+		let mut params = Self::profile(Some(common_name), 3 * 365);
+		params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+		params.extended_key_usages = vec![ExtendedKeyUsagePurpose::TimeStamping];
+		params.extended_key_usages_critical = true;
+		params
+	}
+	/// Profile for S/MIME certificates
+	///
+	/// The e-mail address is used both as common name and as subject alternative name.
+	/// The certificate is valid for two years, with the `DigitalSignature` key usage
+	/// and the `EmailProtection` extended key usage. For RSA keys that should be
+	/// usable for encryption, add the `KeyEncipherment` key usage.
+	pub fn smime(email :&str) -> Self {
+		let mut params = Self::profile(Some(email), 2 * 365);
+		params.subject_alt_names = vec![SanType::Rfc822Name(email.to_string())];
+		params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+		params.extended_key_usages = vec![ExtendedKeyUsagePurpose::EmailProtection];
+		params
+	}
+	/// Profile for self signed root CA certificates
+	///
+	/// The certificate is valid for 20 years, without path length constraint,
+	/// and with the `KeyCertSign`, `CrlSign` and `DigitalSignature` key usages.
+	pub fn root_ca(common_name :&str) -> Self {
+		let mut params = Self::profile(Some(common_name), 20 * 365);
+		params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+		params.key_usages = ca_key_usages();
+		params
+	}
+	/// Profile for intermediate CA certificates
+	///
+	/// `path_len` is the number of intermediate CAs that may follow this one.
+	/// The certificate is valid for ten years, with the `KeyCertSign`, `CrlSign`
+	/// and `DigitalSignature` key usages.
+	pub fn intermediate_ca(common_name :&str, path_len :u8) -> Self {
+		let mut params = Self::profile(Some(common_name), 10 * 365);
+		params.is_ca = IsCa::Ca(BasicConstraints::Constrained(path_len));
+		params.key_usages = ca_key_usages();
+		params
+	}
+	fn profile(common_name :Option<&str>, days :i64) -> Self {
+		let mut params = CertificateParams::new(Vec::new());
+		params.distinguished_name = DistinguishedName::new();
+		if let Some(common_name) = common_name {
+			params.distinguished_name.push(DnType::CommonName, common_name);
+		}
+		params.valid_from_now(days);
+		params.use_authority_key_identifier_extension = true;
+		params
+	}
+}
+
+fn ca_key_usages() -> Vec<KeyUsagePurpose> {
+	vec![
+		KeyUsagePurpose::KeyCertSign,
+		KeyUsagePurpose::CrlSign,
+		KeyUsagePurpose::DigitalSignature,
+	]
+}
@@ -0,0 +1,268 @@
+use std::convert::TryFrom;
+use time::OffsetDateTime;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{ParsedExtension, X509Extension};
+use crate::{random_serial_number, BasicConstraints, Certificate, CertificateParams, CustomExtension,
+	DistinguishedName, ExtendedKeyUsagePurpose, IsCa, KeyIdMethod, KeyPair, KeyUsagePurpose,
+	PublicKey, PublicKeyData, RcgenError, SanType};
+use crate::verify::convert_name_constraints;
+
+impl CertificateParams {
+	/// Obtains the parameters of an existing DER encoded certificate,
+	/// e.g. to issue an updated version of it
+	///
+	/// The subject, the subject alternative names and the extensions are
+	/// carried over, as well as the signature algorithm fitting the certificate's key.
+	/// Extensions that can't be represented by the fields of [`CertificateParams`]
+	/// are kept as [`CustomExtension`]s. The subject key identifier is kept as
+	/// [`KeyIdMethod::PreSpecified`], and the authority key identifier turns into
+	/// [`use_authority_key_identifier_extension`](Self::use_authority_key_identifier_extension),
+	/// as its value depends on the issuer. The validity, the serial number
+	/// and the key pair are left at their defaults.
+	///
+	/// See [`renew_certificate`], [`rekey_certificate`] and [`cross_sign_certificate`]
+	/// for the common use cases.
+	///
+	/// *This constructor is only available if rcgen is built with the "x509-parser" feature*
+	pub fn from_certificate_der(der :&[u8]) -> Result<Self, RcgenError> {
+		let (_, cert) = x509_parser::parse_x509_certificate(der)
+			.or(Err(RcgenError::CouldNotParseCertificate))?;
+		Self::from_x509(&cert)
+	}
+	fn from_x509(cert :&X509Certificate) -> Result<Self, RcgenError> {
+		let public_key = PublicKey::from_spki_der(cert.public_key().raw)?;
+		let mut params = CertificateParams::default();
+		params.alg = public_key.alg;
+		params.distinguished_name = DistinguishedName::from_name(cert.subject())?;
+		for ext in cert.extensions() {
+			if !params.take_extension(ext) {
+				params.custom_extensions.push(CustomExtension {
+					oid : oid_components(ext),
+					critical : ext.critical,
+					content : ext.value.to_vec(),
+				});
+			}
+		}
+		Ok(params)
+	}
+	/// Stores the extension in the corresponding field, returns `false` if there is none
+	///
+	/// Extensions are only taken if rcgen writes them back with the same
+	/// meaning and criticality.
+	fn take_extension(&mut self, ext :&X509Extension) -> bool {
+		let oid = oid_components(ext);
+		match ext.parsed_extension() {
+			ParsedExtension::SubjectKeyIdentifier(key_identifier) => {
+				self.key_identifier_method = KeyIdMethod::PreSpecified(key_identifier.0.to_vec());
+				true
+			},
+			ParsedExtension::AuthorityKeyIdentifier(_) => {
+				self.use_authority_key_identifier_extension = true;
+				true
+			},
+			ParsedExtension::SubjectAlternativeName(san) if !ext.critical => {
+				let names = san.general_names.iter()
+					.map(SanType::try_from_general)
+					.collect::<Result<Vec<_>, _>>();
+				match names {
+					Ok(names) => {
+						self.subject_alt_names = names;
+						true
+					},
+					Err(_) => false,
+				}
+			},
+			// Bits beyond decipherOnly are unknown to rcgen
+			ParsedExtension::KeyUsage(key_usage) if ext.critical && key_usage.flags != 0 && key_usage.flags >> 9 == 0 => {
+				self.key_usages = KEY_USAGES.iter()
+					.filter(|usage| key_usage.flags & (1 << usage.bit_index()) != 0)
+					.cloned()
+					.collect();
+				true
+			},
+			ParsedExtension::BasicConstraints(constraints) if ext.critical && constraints.ca => {
+				let constraint = match constraints.path_len_constraint {
+					Some(path_len) => match u8::try_from(path_len) {
+						Ok(path_len) => BasicConstraints::Constrained(path_len),
+						Err(_) => return false,
+					},
+					None => BasicConstraints::Unconstrained,
+				};
+				self.is_ca = IsCa::Ca(constraint);
+				true
+			},
+			ParsedExtension::NameConstraints(constraints) if ext.critical => {
+				match convert_name_constraints(constraints) {
+					Ok(name_constraints) => {
+						self.name_constraints = Some(name_constraints);
+						true
+					},
+					Err(_) => false,
+				}
+			},
+			_ if oid == crate::OID_EXT_KEY_USAGE => {
+				// Parsed by hand as x509-parser doesn't keep the order
+				let usages = yasna::parse_der(ext.value, |reader| {
+					reader.collect_sequence_of(|reader| reader.read_oid())
+				}).ok().and_then(|oids| {
+					oids.iter()
+						.map(|oid| EXTENDED_KEY_USAGES.iter()
+							.find(|usage| usage.oid() == oid.components().as_slice())
+							.cloned())
+						.collect::<Option<Vec<_>>>()
+				});
+				match usages {
+					Some(usages) => {
+						self.extended_key_usages = usages;
+						self.extended_key_usages_critical = ext.critical;
+						true
+					},
+					None => false,
+				}
+			},
+			_ => false,
+		}
+	}
+	/// Prepares the parameters for reissuance with the given public key
+	fn reissue<K :PublicKeyData>(&mut self, pub_key :&K, not_before :OffsetDateTime, not_after :OffsetDateTime) -> Result<(), RcgenError> {
+		self.not_before = not_before;
+		self.not_after = not_after;
+		self.serial_number = Some(random_serial_number()?);
+		// rcgen only writes the subject key identifier for CAs
+		if !matches!(self.is_ca, IsCa::Ca(_)) {
+			self.custom_extensions.push(CustomExtension {
+				oid : crate::OID_SUBJECT_KEY_IDENTIFIER.to_vec(),
+				critical : false,
+				content : yasna::construct_der(|writer| {
+					writer.write_bytes(&self.key_identifier(pub_key))
+				}),
+			});
+		}
+		Ok(())
+	}
+}
+
+/// Renews an existing DER encoded certificate, keeping its key
+///
+/// The new certificate has the subject, subject alternative names
+/// and extensions of the existing one, as obtained by
+/// [`CertificateParams::from_certificate_der`], a new random serial number,
+/// and the given validity. It's signed by `ca`, which has to be the
+/// certificate itself for self signed certificates.
+///
+/// ```
+/// # use rcgen::{renew_certificate, PkiBuilder};
+/// let chain = PkiBuilder::new()
+/// 	.intermediates(0)
+/// 	.leaf_subject_alt_names(vec!["crabs.crabs".to_string()])
+/// 	.build().unwrap();
+/// let not_before = time::OffsetDateTime::now_utc();
+/// let not_after = not_before + time::Duration::days(90);
+/// let renewed = renew_certificate(chain.leaf().der(), chain.root().certificate(),
+/// 	not_before, not_after).unwrap();
+/// ```
+///
+/// *This function is only available if rcgen is built with the "x509-parser" feature*
+pub fn renew_certificate(existing :&[u8], ca :&Certificate,
+		not_before :OffsetDateTime, not_after :OffsetDateTime) -> Result<Vec<u8>, RcgenError> {
+	let (_, cert) = x509_parser::parse_x509_certificate(existing)
+		.or(Err(RcgenError::CouldNotParseCertificate))?;
+	let params = CertificateParams::from_x509(&cert)?;
+	reissue_with_same_key(&cert, params, ca, not_before, not_after)
+}
+
+/// Cross-signs an existing DER encoded CA certificate with another issuer
+///
+/// The cross-certificate has the identically encoded subject, the key, the subject
+/// key identifier and constraints of the existing CA certificate, and an authority key identifier
+/// pointing to `issuer`. Certificates issued by the existing CA thus chain up
+/// to both the CA's own issuer and to `issuer`, e.g. to migrate to a new root.
+///
+/// Fails with [`RcgenError::NotACaCertificate`] if the existing certificate is not a CA.
+///
+/// ```
+/// # use rcgen::{cross_sign_certificate, Certificate, CertificateParams, PkiBuilder};
+/// let chain = PkiBuilder::new().build().unwrap();
+/// let new_root = Certificate::from_params(CertificateParams::root_ca("Crab root CA 2")).unwrap();
+/// let not_before = time::OffsetDateTime::now_utc();
+/// let not_after = not_before + time::Duration::days(365);
+/// let cross = cross_sign_certificate(chain.intermediates()[0].der(), &new_root,
+/// 	not_before, not_after).unwrap();
+/// ```
+///
+/// *This function is only available if rcgen is built with the "x509-parser" feature*
+pub fn cross_sign_certificate(existing_ca :&[u8], issuer :&Certificate,
+		not_before :OffsetDateTime, not_after :OffsetDateTime) -> Result<Vec<u8>, RcgenError> {
+	let (_, cert) = x509_parser::parse_x509_certificate(existing_ca)
+		.or(Err(RcgenError::CouldNotParseCertificate))?;
+	let mut params = CertificateParams::from_x509(&cert)?;
+	if !matches!(params.is_ca, IsCa::Ca(_)) {
+		return Err(RcgenError::NotACaCertificate);
+	}
+	params.use_authority_key_identifier_extension = true;
+	// Certificates issued by the CA name it by the exact encoding of its subject,
+	// which `DistinguishedName` doesn't preserve, e.g. for repeated attribute types
+	params.raw_subject = Some(cert.subject().as_raw().to_vec());
+	reissue_with_same_key(&cert, params, issuer, not_before, not_after)
+}
+
+fn reissue_with_same_key(cert :&X509Certificate, mut params :CertificateParams, issuer :&Certificate,
+		not_before :OffsetDateTime, not_after :OffsetDateTime) -> Result<Vec<u8>, RcgenError> {
+	let public_key = PublicKey::from_spki_der(cert.public_key().raw)?;
+	params.reissue(&public_key, not_before, not_after)?;
+	params.serialize_signed_der(&public_key, issuer)
+}
+
+/// Re-keys an existing DER encoded certificate
+///
+/// The returned certificate has the subject, subject alternative names
+/// and extensions of the existing one, as obtained by
+/// [`CertificateParams::from_certificate_der`], the given key pair and
+/// a new random serial number. It is valid from now on, for as long as the
+/// existing certificate was valid. Sign it with
+/// [`serialize_der_with_signer`](Certificate::serialize_der_with_signer),
+/// or [`serialize_der`](Certificate::serialize_der) for self signed certificates.
+///
+/// *This function is only available if rcgen is built with the "x509-parser" feature*
+pub fn rekey_certificate(existing :&[u8], new_key :KeyPair) -> Result<Certificate, RcgenError> {
+	let (_, cert) = x509_parser::parse_x509_certificate(existing)
+		.or(Err(RcgenError::CouldNotParseCertificate))?;
+	let mut params = CertificateParams::from_x509(&cert)?;
+	// The existing subject key identifier belongs to the old key
+	params.key_identifier_method = KeyIdMethod::Sha256;
+	let validity = cert.validity();
+	let lifetime = validity.not_after.to_datetime() - validity.not_before.to_datetime();
+	let not_before = OffsetDateTime::now_utc();
+	params.reissue(&new_key, not_before, not_before + lifetime)?;
+	params.alg = new_key.alg;
+	params.key_pair = Some(new_key);
+	Certificate::from_params(params)
+}
+
+static KEY_USAGES :&[KeyUsagePurpose] = &[
+	KeyUsagePurpose::DigitalSignature,
+	KeyUsagePurpose::ContentCommitment,
+	KeyUsagePurpose::KeyEncipherment,
+	KeyUsagePurpose::DataEncipherment,
+	KeyUsagePurpose::KeyAgreement,
+	KeyUsagePurpose::KeyCertSign,
+	KeyUsagePurpose::CrlSign,
+	KeyUsagePurpose::EncipherOnly,
+	KeyUsagePurpose::DecipherOnly,
+];
+
+static EXTENDED_KEY_USAGES :&[ExtendedKeyUsagePurpose] = &[
+	ExtendedKeyUsagePurpose::Any,
+	ExtendedKeyUsagePurpose::ServerAuth,
+	ExtendedKeyUsagePurpose::ClientAuth,
+	ExtendedKeyUsagePurpose::CodeSigning,
+	ExtendedKeyUsagePurpose::EmailProtection,
+	ExtendedKeyUsagePurpose::TimeStamping,
+	ExtendedKeyUsagePurpose::OcspSigning,
+];
+
+fn oid_components(ext :&X509Extension) -> Vec<u64> {
+	ext.oid.iter()
+		.map(|components| components.collect())
+		.unwrap_or_default()
+}
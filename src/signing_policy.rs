@@ -0,0 +1,278 @@
+use std::fmt;
+use time::{Duration, OffsetDateTime};
+use crate::{CertificateParams, CidrSubnet, ExtendedKeyUsagePurpose, IsCa,
+	KeyUsagePurpose, RcgenError, SanType};
+use crate::name_constraints::ip_address_matches;
+
+/// A hook that checks and adjusts the parameters of a certificate before it is signed
+///
+/// It is applied by [`CertificateSigningRequest::apply_policy`](crate::CertificateSigningRequest::apply_policy)
+/// and, if set, by the [`CertificateAuthority`](crate::CertificateAuthority) when signing CSRs.
+/// [`SigningRules`] covers the common checks, and closures can be used for anything else:
+///
+/// ```
+/// # use rcgen::{CertificateParams, RcgenError, SigningPolicy};
+/// let policy = |params :&mut CertificateParams| -> Result<(), RcgenError> {
+/// 	params.use_authority_key_identifier_extension = true;
+/// 	Ok(())
+/// };
+/// let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+/// policy.apply(&mut params).unwrap();
+/// ```
+pub trait SigningPolicy {
+	/// Checks the parameters, possibly changing them
+	///
+	/// Returning an error rejects the request, usually with
+	/// [`RcgenError::PolicyViolation`].
+	fn apply(&self, params :&mut CertificateParams) -> Result<(), RcgenError>;
+}
+
+impl<F> SigningPolicy for F
+where
+	F :Fn(&mut CertificateParams) -> Result<(), RcgenError>,
+{
+	fn apply(&self, params :&mut CertificateParams) -> Result<(), RcgenError> {
+		self(params)
+	}
+}
+
+/// The reason a [`SigningPolicy`] rejected a request
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub enum PolicyViolation {
+	/// The subject alternative name is not allowed
+	SubjectAltNameNotAllowed(SanType),
+	/// Subject alternative names were requested, but none of them is allowed
+	NoAllowedSubjectAltNames,
+	/// No subject alternative names were requested, although the allowed names are restricted
+	SubjectAltNamesMissing,
+	/// The key usage is not allowed
+	KeyUsageNotAllowed(KeyUsagePurpose),
+	/// The extended key usage is not allowed
+	ExtendedKeyUsageNotAllowed(ExtendedKeyUsagePurpose),
+	/// A CA certificate was requested
+	CaNotAllowed,
+	/// The requested validity period is longer than allowed, the contained value is the requested one
+	ValidityTooLong(Duration),
+}
+
+impl fmt::Display for PolicyViolation {
+	fn fmt(&self, f :&mut fmt::Formatter) -> fmt::Result {
+		use self::PolicyViolation::*;
+		match self {
+			SubjectAltNameNotAllowed(name) => write!(f, "The subject alternative name \
+				{:?} is not allowed", name)?,
+			NoAllowedSubjectAltNames => write!(f, "None of the subject alternative names \
+				is allowed")?,
+			SubjectAltNamesMissing => write!(f, "Subject alternative names are required \
+				as the allowed names are restricted")?,
+			KeyUsageNotAllowed(usage) => write!(f, "The key usage {:?} is not allowed", usage)?,
+			ExtendedKeyUsageNotAllowed(usage) => write!(f, "The extended key usage {:?} \
+				is not allowed", usage)?,
+			CaNotAllowed => write!(f, "CA certificates are not allowed")?,
+			ValidityTooLong(validity) => write!(f, "The validity of {} days is longer than \
+				allowed", validity.whole_days())?,
+		};
+		Ok(())
+	}
+}
+
+/// A [`SigningPolicy`] with allow lists for names and usages, and limits for the validity
+///
+/// The default rules allow any names, usages and validity, but reject CA certificates.
+/// Note that only the subject alternative names are checked, not the subject.
+/// So that names can't be passed in the subject's common name instead, requests
+/// without subject alternative names are rejected if the DNS names or IP addresses
+/// are restricted.
+///
+/// ```
+/// # use rcgen::{CertificateParams, PolicyViolation, RcgenError, SigningPolicy, SigningRules};
+/// let mut rules = SigningRules::default();
+/// rules.allowed_dns_names = Some(vec!["*.crabs.example".to_string()]);
+///
+/// let mut params = CertificateParams::new(vec!["shop.crabs.example".to_string()]);
+/// rules.apply(&mut params).unwrap();
+///
+/// let mut params = CertificateParams::new(vec!["shop.lobsters.example".to_string()]);
+/// assert!(matches!(rules.apply(&mut params),
+/// 	Err(RcgenError::PolicyViolation(PolicyViolation::SubjectAltNameNotAllowed(_)))));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct SigningRules {
+	/// Patterns of the allowed DNS names, or `None` to allow any
+	///
+	/// A pattern is either a name like `crabs.example`, allowing only that name,
+	/// `*.crabs.example`, allowing names with one more label like `shop.crabs.example`,
+	/// or `.crabs.example`, allowing any name below `crabs.example`.
+	/// Names are compared ignoring case.
+	pub allowed_dns_names :Option<Vec<String>>,
+	/// Subnets of the allowed IP addresses, or `None` to allow any
+	pub allowed_ip_addresses :Option<Vec<CidrSubnet>>,
+	/// Whether e-mail addresses and URIs are allowed, defaults to `true`
+	pub allow_other_names :bool,
+	/// The allowed key usages, or `None` to allow any
+	///
+	/// If no key usages were requested, all of these are used.
+	pub key_usages :Option<Vec<KeyUsagePurpose>>,
+	/// The allowed extended key usages, or `None` to allow any
+	///
+	/// If no extended key usages were requested, all of these are used.
+	pub extended_key_usages :Option<Vec<ExtendedKeyUsagePurpose>>,
+	/// If `true`, names and usages that are not allowed are removed instead of
+	/// rejecting the request, and a too long validity is shortened
+	///
+	/// Requests are still rejected if none of the requested names is allowed.
+	pub filter :bool,
+	/// Whether CA certificates may be issued, defaults to `false`
+	pub allow_ca :bool,
+	/// If set, replaces the requested validity with the given duration, starting now
+	///
+	/// CSRs carry no validity, so the validity of their parameters is
+	/// the far reaching default of [`CertificateParams`] unless it is set.
+	pub validity :Option<Duration>,
+	/// The maximum validity, checked after [`validity`](Self::validity) is applied
+	///
+	/// When [`filter`](Self::filter) shortens a validity that started in the past,
+	/// the certificate is made valid from now on.
+	pub max_validity :Option<Duration>,
+}
+
+impl Default for SigningRules {
+	fn default() -> Self {
+		SigningRules {
+			allowed_dns_names : None,
+			allowed_ip_addresses : None,
+			allow_other_names : true,
+			key_usages : None,
+			extended_key_usages : None,
+			filter : false,
+			allow_ca : false,
+			validity : None,
+			max_validity : None,
+		}
+	}
+}
+
+impl SigningRules {
+	fn permits_subject_alt_name(&self, name :&SanType) -> bool {
+		match name {
+			SanType::DnsName(name) => match &self.allowed_dns_names {
+				Some(patterns) => patterns.iter().any(|pattern| dns_name_matches_pattern(name, pattern)),
+				None => true,
+			},
+			SanType::IpAddress(addr) => match &self.allowed_ip_addresses {
+				Some(subnets) => subnets.iter().any(|subnet| ip_address_matches(addr, subnet)),
+				None => true,
+			},
+			_ => self.allow_other_names,
+		}
+	}
+	/// Removes or rejects the items that are not allowed, and uses all
+	/// allowed items if none were requested
+	fn filter_usages<T :Clone + PartialEq>(&self, requested :&mut Vec<T>, allowed :&Option<Vec<T>>,
+			violation :impl Fn(T) -> PolicyViolation) -> Result<(), RcgenError> {
+		let allowed = match allowed {
+			Some(allowed) => allowed,
+			None => return Ok(()),
+		};
+		if requested.is_empty() {
+			*requested = allowed.clone();
+			return Ok(());
+		}
+		if let Some(usage) = requested.iter().find(|usage| !allowed.contains(usage)) {
+			if !self.filter {
+				return Err(RcgenError::PolicyViolation(violation(usage.clone())));
+			}
+		}
+		requested.retain(|usage| allowed.contains(usage));
+		Ok(())
+	}
+}
+
+impl SigningPolicy for SigningRules {
+	fn apply(&self, params :&mut CertificateParams) -> Result<(), RcgenError> {
+		if let IsCa::Ca(_) = params.is_ca {
+			if !self.allow_ca {
+				return Err(RcgenError::PolicyViolation(PolicyViolation::CaNotAllowed));
+			}
+		}
+
+		let requested_names = params.subject_alt_names.len();
+		let names_restricted = self.allowed_dns_names.is_some() || self.allowed_ip_addresses.is_some();
+		if requested_names == 0 && names_restricted {
+			return Err(RcgenError::PolicyViolation(PolicyViolation::SubjectAltNamesMissing));
+		}
+		if let Some(name) = params.subject_alt_names.iter().find(|name| !self.permits_subject_alt_name(name)) {
+			if !self.filter {
+				let violation = PolicyViolation::SubjectAltNameNotAllowed(name.clone());
+				return Err(RcgenError::PolicyViolation(violation));
+			}
+		}
+		params.subject_alt_names.retain(|name| self.permits_subject_alt_name(name));
+		if requested_names > 0 && params.subject_alt_names.is_empty() {
+			return Err(RcgenError::PolicyViolation(PolicyViolation::NoAllowedSubjectAltNames));
+		}
+
+		self.filter_usages(&mut params.key_usages, &self.key_usages,
+			PolicyViolation::KeyUsageNotAllowed)?;
+		self.filter_usages(&mut params.extended_key_usages, &self.extended_key_usages,
+			PolicyViolation::ExtendedKeyUsageNotAllowed)?;
+
+		if let Some(validity) = self.validity {
+			params.valid_for(validity);
+		}
+		if let Some(max_validity) = self.max_validity {
+			let validity = params.not_after - params.not_before;
+			if validity > max_validity {
+				if !self.filter {
+					return Err(RcgenError::PolicyViolation(PolicyViolation::ValidityTooLong(validity)));
+				}
+				// The validity of CSRs starts in 1975 unless it was set,
+				// so shortened certificates start now at the earliest
+				if params.not_before < OffsetDateTime::now_utc() {
+					params.valid_for(max_validity);
+				} else {
+					params.not_after = params.not_before + max_validity;
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// A `*.` pattern matches exactly one additional label,
+/// a pattern with a leading dot any number of them
+fn dns_name_matches_pattern(name :&str, pattern :&str) -> bool {
+	let name = name.trim_end_matches('.').to_ascii_lowercase();
+	let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+	if let Some(base) = pattern.strip_prefix('*') {
+		match name.strip_suffix(base) {
+			Some(label) => base.starts_with('.') && !label.is_empty() && !label.contains('.'),
+			None => false,
+		}
+	} else if pattern.starts_with('.') {
+		name.len() > pattern.len() && name.ends_with(&pattern)
+	} else {
+		name == pattern
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_dns_name_matches_pattern() {
+		assert!(dns_name_matches_pattern("crabs.example", "crabs.example"));
+		assert!(dns_name_matches_pattern("Crabs.Example.", "crabs.example"));
+		assert!(!dns_name_matches_pattern("shop.crabs.example", "crabs.example"));
+		assert!(dns_name_matches_pattern("shop.crabs.example", "*.crabs.example"));
+		assert!(!dns_name_matches_pattern("a.shop.crabs.example", "*.crabs.example"));
+		assert!(!dns_name_matches_pattern("crabs.example", "*.crabs.example"));
+		assert!(!dns_name_matches_pattern("badcrabs.example", "*crabs.example"));
+		assert!(dns_name_matches_pattern("a.shop.crabs.example", ".crabs.example"));
+		assert!(!dns_name_matches_pattern("crabs.example", ".crabs.example"));
+		assert!(!dns_name_matches_pattern("badcrabs.example", ".crabs.example"));
+	}
+}
@@ -0,0 +1,335 @@
+//! Time-stamp protocol as per [RFC 3161](https://tools.ietf.org/html/rfc3161)
+
+use ring::digest;
+use time::{Duration, OffsetDateTime};
+use yasna::{ASN1Error, ASN1ErrorKind, DERWriter, Tag};
+use yasna::models::{ObjectIdentifier, TaggedDerValue};
+use yasna::tags::TAG_INTEGER;
+use crate::{dt_to_generalized, random_serial_number, Certificate, FingerprintAlgorithm, RcgenError};
+use crate::cms::{sign_signed_data, OID_SHA256, OID_SHA384, OID_SHA512};
+
+// Content type of TSTInfo in RFC 3161 section 2.4.2
+const OID_CT_TST_INFO :&[u64] = &[1, 2, 840, 113549, 1, 9, 16, 1, 4];
+
+// Signing certificate attribute in RFC 5035 section 3
+const OID_SIGNING_CERTIFICATE_V2 :&[u64] = &[1, 2, 840, 113549, 1, 9, 16, 2, 47];
+
+// SHA-1 in RFC 3279 section 2.2.1
+const OID_SHA1 :&[u64] = &[1, 3, 14, 3, 2, 26];
+
+/// A time-stamp request, as specified in
+/// [RFC 3161 section 2.4.1](https://tools.ietf.org/html/rfc3161#section-2.4.1)
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct TimeStampRequest {
+	/// The hash algorithm the data was hashed with
+	pub hash_algorithm :FingerprintAlgorithm,
+	/// The hash of the data to be time-stamped
+	pub hashed_message :Vec<u8>,
+	/// The OID of the policy the time-stamp should be issued under
+	pub policy :Option<Vec<u64>>,
+	/// The nonce to be echoed in the time-stamp, as the big-endian
+	/// content octets of the DER encoded INTEGER
+	pub nonce :Option<Vec<u8>>,
+	/// If `true`, the time-stamp authority's certificate is included in the response
+	pub cert_req :bool,
+}
+
+impl TimeStampRequest {
+	/// Creates a request for a time-stamp of the data,
+	/// hashed with the given algorithm
+	///
+	/// The request asks for the time-stamp authority's certificate,
+	/// without a nonce or policy.
+	pub fn new(hash_algorithm :FingerprintAlgorithm, data :&[u8]) -> Self {
+		TimeStampRequest {
+			hash_algorithm,
+			hashed_message : hash_algorithm.fingerprint(data),
+			policy : None,
+			nonce : None,
+			cert_req : true,
+		}
+	}
+	/// Parses a DER encoded time-stamp request, e.g. the output of `openssl ts -query`
+	///
+	/// Fails with [`RcgenError::CouldNotParseTimeStampRequest`] for requests
+	/// that are malformed, use an unsupported hash algorithm or contain extensions.
+	pub fn from_der(der :&[u8]) -> Result<Self, RcgenError> {
+		parse_request(der).or(Err(RcgenError::CouldNotParseTimeStampRequest))
+	}
+	/// Serializes the request in binary DER format
+	pub fn serialize_der(&self) -> Vec<u8> {
+		yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				writer.next().write_u8(1);
+				self.write_message_imprint(writer.next());
+				if let Some(policy) = &self.policy {
+					writer.next().write_oid(&ObjectIdentifier::from_slice(policy));
+				}
+				if let Some(nonce) = &self.nonce {
+					write_integer_bytes(writer.next(), nonce);
+				}
+				if self.cert_req {
+					writer.next().write_bool(true);
+				}
+			});
+		})
+	}
+	fn write_message_imprint(&self, writer :DERWriter) {
+		writer.write_sequence(|writer| {
+			// The parameters are absent, as for CMS
+			writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(hash_algorithm_oid(self.hash_algorithm)));
+			});
+			writer.next().write_bytes(&self.hashed_message);
+		});
+	}
+}
+
+/// Parameters of a [`TimeStampAuthority`]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub struct TimeStampAuthorityParams {
+	/// The OID of the policy the time-stamps are issued under
+	///
+	/// Requests for other policies are rejected.
+	pub policy :Vec<u64>,
+	/// The accuracy of the time-stamps, with microsecond precision
+	///
+	/// Defaults to one second.
+	pub accuracy :Option<Duration>,
+	/// DER encoded certificates to include in addition to the time-stamp
+	/// authority's certificate if the request asks for it, usually its issuer chain
+	pub ca_certificates :Vec<Vec<u8>>,
+}
+
+impl TimeStampAuthorityParams {
+	/// Creates parameters for time-stamps under the given policy
+	pub fn new(policy :&[u64]) -> Self {
+		TimeStampAuthorityParams {
+			policy : policy.to_vec(),
+			accuracy : Some(Duration::seconds(1)),
+			ca_certificates : Vec::new(),
+		}
+	}
+}
+
+/// A time-stamp authority (TSA) that answers time-stamp requests, e.g. for
+/// testing code signing offline
+///
+/// The TSA certificate needs the `TimeStamping` extended key usage as its only
+/// purpose, see [`CertificateParams::time_stamping`](crate::CertificateParams::time_stamping).
+/// Responses can be verified with `openssl ts -verify`.
+///
+/// ```
+/// # use rcgen::{Certificate, CertificateParams, FingerprintAlgorithm, TimeStampAuthority,
+/// # 	TimeStampAuthorityParams, TimeStampRequest};
+/// let ca = Certificate::from_params(CertificateParams::root_ca("Crab CA")).unwrap();
+/// let cert = Certificate::from_params(CertificateParams::time_stamping("Crab TSA")).unwrap();
+/// let cert_der = cert.serialize_der_with_signer(&ca).unwrap();
+/// let tsa = TimeStampAuthority::new(cert, cert_der,
+/// 	TimeStampAuthorityParams::new(&[1, 3, 6, 1, 4, 1, 55738, 1]));
+///
+/// let request = TimeStampRequest::new(FingerprintAlgorithm::Sha256, b"Hello crabs");
+/// let response = tsa.respond_der(&request.serialize_der()).unwrap();
+/// ```
+pub struct TimeStampAuthority {
+	certificate :Certificate,
+	certificate_der :Vec<u8>,
+	params :TimeStampAuthorityParams,
+}
+
+impl TimeStampAuthority {
+	/// Creates a TSA that signs with the key of the certificate
+	///
+	/// `certificate_der` is the DER encoded certificate as signed by its issuer.
+	pub fn new(certificate :Certificate, certificate_der :Vec<u8>, params :TimeStampAuthorityParams) -> Self {
+		TimeStampAuthority {
+			certificate,
+			certificate_der,
+			params,
+		}
+	}
+	/// Returns the parameters the TSA was created with
+	pub fn get_params(&self) -> &TimeStampAuthorityParams {
+		&self.params
+	}
+	/// Answers the DER encoded time-stamp request with a DER encoded
+	/// time-stamp response for the current time
+	///
+	/// See [`respond_der_at`](Self::respond_der_at) for details.
+	pub fn respond_der(&self, request_der :&[u8]) -> Result<Vec<u8>, RcgenError> {
+		self.respond_der_at(request_der, OffsetDateTime::now_utc())
+	}
+	/// Answers the DER encoded time-stamp request with a DER encoded
+	/// time-stamp response, as specified in
+	/// [RFC 3161 section 2.4.2](https://tools.ietf.org/html/rfc3161#section-2.4.2)
+	///
+	/// Malformed requests, requests with unsupported hash algorithms or extensions
+	/// and requests for other policies get a response with the rejection status and
+	/// the matching failure information. Errors are only returned if signing fails.
+	pub fn respond_der_at(&self, request_der :&[u8], gen_time :OffsetDateTime) -> Result<Vec<u8>, RcgenError> {
+		let request = match parse_request(request_der) {
+			Ok(request) => request,
+			Err(failure_info) => return Ok(rejection(failure_info)),
+		};
+		if matches!(&request.policy, Some(policy) if policy != &self.params.policy) {
+			return Ok(rejection(FailureInfo::UnacceptedPolicy));
+		}
+		let token = self.issue_token_der(&request, gen_time)?;
+		Ok(yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				// PKIStatusInfo with the granted status
+				writer.next().write_sequence(|writer| {
+					writer.next().write_u8(0);
+				});
+				writer.next().write_der(&token);
+			});
+		}))
+	}
+	/// Issues a time-stamp token for the request in DER format
+	///
+	/// The token is a CMS SignedData structure with the TSTInfo as content,
+	/// e.g. for embedding as unsigned attribute into other signatures.
+	/// The policy is always the one of the TSA.
+	pub fn issue_token_der(&self, request :&TimeStampRequest, gen_time :OffsetDateTime) -> Result<Vec<u8>, RcgenError> {
+		let serial_number = random_serial_number()?;
+		let tst_info = yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				writer.next().write_u8(1);
+				writer.next().write_oid(&ObjectIdentifier::from_slice(&self.params.policy));
+				request.write_message_imprint(writer.next());
+				writer.next().write_u64(serial_number);
+				writer.next().write_generalized_time(&dt_to_generalized(gen_time));
+				if let Some(accuracy) = self.params.accuracy {
+					write_accuracy(writer.next(), accuracy);
+				}
+				if let Some(nonce) = &request.nonce {
+					write_integer_bytes(writer.next(), nonce);
+				}
+			});
+		});
+		// The signing certificate attribute binds the signature to the TSA certificate
+		let cert_hash = digest::digest(&digest::SHA256, &self.certificate_der);
+		let signing_certificate = yasna::construct_der(|writer| {
+			writer.write_sequence(|writer| {
+				writer.next().write_sequence(|writer| {
+					// ESSCertIDv2, with the default hash algorithm SHA-256
+					writer.next().write_sequence(|writer| {
+						writer.next().write_bytes(cert_hash.as_ref());
+					});
+				});
+			});
+		});
+		let mut certificates = Vec::new();
+		if request.cert_req {
+			certificates.push(self.certificate_der.as_slice());
+			certificates.extend(self.params.ca_certificates.iter().map(|cert| cert.as_slice()));
+		}
+		sign_signed_data(&self.certificate.key_pair, &self.certificate_der, OID_CT_TST_INFO,
+			&tst_info, false, &certificates, &[(OID_SIGNING_CERTIFICATE_V2, signing_certificate)])
+	}
+}
+
+/// The bits of PKIFailureInfo in RFC 3161 section 2.4.2
+#[derive(Debug, Clone, Copy)]
+enum FailureInfo {
+	BadAlg = 0,
+	BadDataFormat = 5,
+	UnacceptedPolicy = 15,
+	UnacceptedExtension = 16,
+}
+
+/// Creates a time-stamp response with the rejection status
+fn rejection(failure_info :FailureInfo) -> Vec<u8> {
+	let bit = failure_info as usize;
+	let mut bytes = vec![0; bit / 8 + 1];
+	bytes[bit / 8] = 0x80 >> (bit % 8);
+	yasna::construct_der(|writer| {
+		writer.write_sequence(|writer| {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_u8(2);
+				writer.next().write_bitvec_bytes(&bytes, bit + 1);
+			});
+		});
+	})
+}
+
+fn write_accuracy(writer :DERWriter, accuracy :Duration) {
+	let micros = accuracy.whole_microseconds();
+	writer.write_sequence(|writer| {
+		if micros >= 1_000_000 {
+			writer.next().write_i64((micros / 1_000_000) as i64);
+		}
+		if micros / 1000 % 1000 != 0 {
+			writer.next().write_tagged_implicit(Tag::context(0), |writer| {
+				writer.write_i64((micros / 1000 % 1000) as i64);
+			});
+		}
+		if micros % 1000 != 0 {
+			writer.next().write_tagged_implicit(Tag::context(1), |writer| {
+				writer.write_i64((micros % 1000) as i64);
+			});
+		}
+	});
+}
+
+fn write_integer_bytes(writer :DERWriter, bytes :&[u8]) {
+	writer.write_tagged_der(&TaggedDerValue::from_tag_and_bytes(TAG_INTEGER, bytes.to_vec()));
+}
+
+fn hash_algorithm_oid(alg :FingerprintAlgorithm) -> &'static [u64] {
+	match alg {
+		FingerprintAlgorithm::Sha1 => OID_SHA1,
+		FingerprintAlgorithm::Sha256 => OID_SHA256,
+		FingerprintAlgorithm::Sha384 => OID_SHA384,
+		FingerprintAlgorithm::Sha512 => OID_SHA512,
+	}
+}
+
+fn parse_request(der :&[u8]) -> Result<TimeStampRequest, FailureInfo> {
+	let (hash_oid, hashed_message, policy, nonce, cert_req, has_extensions) = yasna::parse_der(der, |reader| {
+		reader.read_sequence(|reader| {
+			if reader.next().read_u8()? != 1 {
+				return Err(ASN1Error::new(ASN1ErrorKind::Invalid));
+			}
+			let (hash_oid, hashed_message) = reader.next().read_sequence(|reader| {
+				let hash_oid = reader.next().read_sequence(|reader| {
+					let oid = reader.next().read_oid()?;
+					// The parameters are either absent or NULL
+					reader.read_optional(|reader| reader.read_null())?;
+					Ok(oid)
+				})?;
+				Ok((hash_oid, reader.next().read_bytes()?))
+			})?;
+			let policy = reader.read_optional(|reader| reader.read_oid())?;
+			let nonce = reader.read_optional(|reader| {
+				if reader.lookahead_tag()? != TAG_INTEGER {
+					return Err(ASN1Error::new(ASN1ErrorKind::Invalid));
+				}
+				Ok(reader.read_tagged_der()?.value().to_vec())
+			})?;
+			let cert_req = reader.read_default(false, |reader| reader.read_bool())?;
+			// Only extensions can follow
+			let has_extensions = reader.read_optional(|reader| reader.read_der())?.is_some();
+			Ok((hash_oid, hashed_message, policy, nonce, cert_req, has_extensions))
+		})
+	}).or(Err(FailureInfo::BadDataFormat))?;
+	if has_extensions {
+		return Err(FailureInfo::UnacceptedExtension);
+	}
+	let hash_algorithm = [FingerprintAlgorithm::Sha1, FingerprintAlgorithm::Sha256,
+			FingerprintAlgorithm::Sha384, FingerprintAlgorithm::Sha512].iter()
+		.find(|alg| hash_oid.components().as_slice() == hash_algorithm_oid(**alg))
+		.copied()
+		.ok_or(FailureInfo::BadAlg)?;
+	if hashed_message.len() != hash_algorithm.fingerprint(b"").len() {
+		return Err(FailureInfo::BadDataFormat);
+	}
+	Ok(TimeStampRequest {
+		hash_algorithm,
+		hashed_message,
+		policy : policy.map(|oid| oid.components().clone()),
+		nonce,
+		cert_req,
+	})
+}
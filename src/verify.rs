@@ -0,0 +1,521 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::convert::{TryFrom, TryInto};
+use time::OffsetDateTime;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::error::X509Error;
+use x509_parser::der_parser::der::{DerObject, DerObjectContent};
+use x509_parser::extensions::GeneralName;
+use x509_parser::oid_registry::*;
+use x509_parser::x509::{AttributeTypeAndValue, RelativeDistinguishedName, X509Name};
+use crate::{CertificateChain, CidrSubnet, DistinguishedName, ExtendedKeyUsagePurpose,
+	GeneralSubtree, KeyUsagePurpose, NameConstraints, SanType};
+use crate::name_constraints::case_ignore_match;
+
+// Extensions that are understood by the path validation, and which
+// therefore may be marked critical.
+const HANDLED_EXTENSIONS :&[&[u64]] = &[
+	crate::OID_SUBJECT_ALT_NAME,
+	crate::OID_KEY_USAGE,
+	crate::OID_EXT_KEY_USAGE,
+	crate::OID_BASIC_CONSTRAINTS,
+	crate::OID_NAME_CONSTRAINTS,
+	crate::OID_SUBJECT_KEY_IDENTIFIER,
+	crate::OID_AUTHORITY_KEY_IDENTIFIER,
+];
+
+/// Options for [`verify_certificate_chain`]
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct PathValidationOptions {
+	/// The point in time at which all certificates have to be valid
+	///
+	/// Defaults to the current time.
+	pub time :OffsetDateTime,
+	/// The key usages the leaf certificate has to allow
+	///
+	/// A leaf without key usage extension allows all key usages.
+	pub key_usages :Vec<KeyUsagePurpose>,
+	/// An extended key usage that the leaf certificate, as well as any
+	/// CA certificate that restricts its extended key usages, has to allow
+	pub extended_key_usage :Option<ExtendedKeyUsagePurpose>,
+}
+
+impl Default for PathValidationOptions {
+	fn default() -> Self {
+		PathValidationOptions {
+			time : OffsetDateTime::now_utc(),
+			key_usages : Vec::new(),
+			extended_key_usage : None,
+		}
+	}
+}
+
+/// The reason a certification path was rejected by [`verify_certificate_chain`]
+///
+/// The contained index refers to the position of the offending certificate
+/// in the path: the leaf has index 0, the intermediate CAs follow,
+/// and the trust anchor has the highest index.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum PathValidationError {
+	/// No leaf certificate was given
+	EmptyChain,
+	/// The certificate couldn't be parsed
+	CouldNotParseCertificate(usize),
+	/// The certificate's issuer name doesn't match the subject name of the next certificate
+	IssuerMismatch(usize),
+	/// The certificate's signature couldn't be verified with the next certificate's key
+	InvalidSignature(usize),
+	/// The certificate is signed with an algorithm that isn't supported
+	UnsupportedSignatureAlgorithm(usize),
+	/// The certificate's validity period starts after the validation time
+	NotYetValid(usize),
+	/// The certificate's validity period ends before the validation time
+	Expired(usize),
+	/// The certificate issued another certificate, but isn't a CA
+	NotCa(usize),
+	/// The certificate's path length constraint is exceeded by the CAs below it
+	PathLenConstraintViolated(usize),
+	/// The certificate's key usage extension doesn't allow the given usage
+	KeyUsageNotAllowed(usize, KeyUsagePurpose),
+	/// The certificate's extended key usage extension doesn't allow the given usage
+	ExtendedKeyUsageNotAllowed(usize, ExtendedKeyUsagePurpose),
+	/// A name of the certificate at the first index violates
+	/// the name constraints of the certificate at the second index
+	NameConstraintViolation(usize, usize),
+	/// The certificate's name constraints contain a subtree of a name form
+	/// that isn't supported, e.g. a URI or otherName subtree
+	///
+	/// [RFC 5280 section 4.2.1.10](https://tools.ietf.org/html/rfc5280#section-4.2.1.10)
+	/// requires rejecting the certificate instead of ignoring the subtree.
+	UnsupportedNameConstraint(usize),
+	/// The certificate contains a critical extension with the given OID
+	/// that isn't understood
+	UnhandledCriticalExtension(usize, Vec<u64>),
+}
+
+impl fmt::Display for PathValidationError {
+	fn fmt(&self, f :&mut fmt::Formatter) -> fmt::Result {
+		use self::PathValidationError::*;
+		match self {
+			EmptyChain => write!(f, "The certificate chain is empty")?,
+			CouldNotParseCertificate(i) => write!(f, "Could not parse certificate {}", i)?,
+			IssuerMismatch(i) => write!(f, "The issuer of certificate {} doesn't match \
+				the subject of its issuing certificate", i)?,
+			InvalidSignature(i) => write!(f, "The signature of certificate {} is invalid", i)?,
+			UnsupportedSignatureAlgorithm(i) => write!(f, "The signature algorithm \
+				of certificate {} is not supported", i)?,
+			NotYetValid(i) => write!(f, "Certificate {} is not yet valid", i)?,
+			Expired(i) => write!(f, "Certificate {} is expired", i)?,
+			NotCa(i) => write!(f, "Certificate {} is not a CA certificate", i)?,
+			PathLenConstraintViolated(i) => write!(f, "The path length constraint \
+				of certificate {} is violated", i)?,
+			KeyUsageNotAllowed(i, usage) => write!(f, "Certificate {} doesn't allow \
+				key usage {:?}", i, usage)?,
+			ExtendedKeyUsageNotAllowed(i, usage) => write!(f, "Certificate {} doesn't allow \
+				extended key usage {:?}", i, usage)?,
+			NameConstraintViolation(i, ca) => write!(f, "A name of certificate {} violates \
+				the name constraints of certificate {}", i, ca)?,
+			UnsupportedNameConstraint(i) => write!(f, "The name constraints of certificate {} \
+				contain an unsupported name form", i)?,
+			UnhandledCriticalExtension(i, oid) => write!(f, "Certificate {} contains \
+				the unhandled critical extension {:?}", i, oid)?,
+		};
+		Ok(())
+	}
+}
+
+impl Error for PathValidationError {}
+
+/// Validates a certification path according to
+/// [RFC 5280 section 6](https://tools.ietf.org/html/rfc5280#section-6)
+///
+/// `chain` contains the DER encoded leaf certificate, followed by the
+/// intermediate CAs in issuing order, so that each certificate is issued
+/// by the one after it. The last one is issued by the `trust_anchor`.
+///
+/// The following is checked:
+///
+/// * issuer names and signatures, including the signature of the trust anchor on the top-most certificate
+/// * the validity period of every certificate except for the trust anchor
+/// * that all issuing certificates are CAs and allowed to sign certificates
+///   according to their key usage and path length constraints
+/// * the name constraints of all CAs, including the trust anchor
+/// * unhandled critical extensions
+/// * the key usages and extended key usage requested via `options`
+///
+/// Certificate policies are not supported, a critical policy
+/// extension leads to [`PathValidationError::UnhandledCriticalExtension`].
+/// Name constraints with subtrees of name forms other than those of
+/// [`GeneralSubtree`] lead to [`PathValidationError::UnsupportedNameConstraint`].
+///
+/// *This function is only available if rcgen is built with the "x509-parser" feature*
+pub fn verify_certificate_chain(chain :&[&[u8]], trust_anchor :&[u8],
+		options :&PathValidationOptions) -> Result<(), PathValidationError> {
+	if chain.is_empty() {
+		return Err(PathValidationError::EmptyChain);
+	}
+	let path = chain.iter()
+		.chain(std::iter::once(&trust_anchor))
+		.enumerate()
+		.map(|(i, der)| {
+			x509_parser::parse_x509_certificate(der)
+				.map(|(_remainder, cert)| cert)
+				.map_err(|_| PathValidationError::CouldNotParseCertificate(i))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	let anchor_index = chain.len();
+	let time = options.time.unix_timestamp();
+
+	for (i, cert) in path.iter().enumerate().take(anchor_index) {
+		let issuer = &path[i + 1];
+		if cert.issuer().as_raw() != issuer.subject().as_raw() {
+			return Err(PathValidationError::IssuerMismatch(i));
+		}
+		match verify_signature(chain[i], cert, issuer) {
+			Ok(()) => (),
+			Err(X509Error::SignatureUnsupportedAlgorithm) => {
+				return Err(PathValidationError::UnsupportedSignatureAlgorithm(i));
+			},
+			Err(_) => return Err(PathValidationError::InvalidSignature(i)),
+		}
+		let validity = cert.validity();
+		if time < validity.not_before.timestamp() {
+			return Err(PathValidationError::NotYetValid(i));
+		}
+		if time > validity.not_after.timestamp() {
+			return Err(PathValidationError::Expired(i));
+		}
+		check_critical_extensions(i, cert)?;
+	}
+
+	for (i, ca) in path.iter().enumerate().skip(1) {
+		check_ca(i, ca, &path)?;
+	}
+
+	let leaf = &path[0];
+	for usage in options.key_usages.iter() {
+		if !key_usage_allowed(leaf, usage)
+				.map_err(|_| PathValidationError::CouldNotParseCertificate(0))? {
+			return Err(PathValidationError::KeyUsageNotAllowed(0, usage.clone()));
+		}
+	}
+	if let Some(usage) = &options.extended_key_usage {
+		// The trust anchor's extensions are not checked
+		for (i, cert) in path.iter().enumerate().take(anchor_index) {
+			if !extended_key_usage_allowed(cert, usage)
+					.map_err(|_| PathValidationError::CouldNotParseCertificate(i))? {
+				return Err(PathValidationError::ExtendedKeyUsageNotAllowed(i, usage.clone()));
+			}
+		}
+	}
+	Ok(())
+}
+
+impl CertificateChain {
+	/// Validates the chain against its own root
+	///
+	/// See [`verify_certificate_chain`] for the performed checks.
+	///
+	/// *This function is only available if rcgen is built with the "x509-parser" feature*
+	pub fn verify(&self, options :&PathValidationOptions) -> Result<(), PathValidationError> {
+		let chain = self.chain_der();
+		let chain = chain.iter()
+			.map(|der| der.as_slice())
+			.collect::<Vec<_>>();
+		verify_certificate_chain(&chain, self.root().der(), options)
+	}
+}
+
+/// Verifies the signature of the certificate with the issuer's key
+///
+/// `X509Certificate::verify_signature` determines the curve of ECDSA signatures
+/// from the certificate's own key instead of the issuer's, so it fails
+/// whenever the two use different key types.
+fn verify_signature(der :&[u8], cert :&X509Certificate, issuer :&X509Certificate) -> Result<(), X509Error> {
+	use ring::signature;
+	let spki = issuer.public_key();
+	let signature_alg = &cert.signature_algorithm.algorithm;
+	let curve = spki.algorithm.parameters.as_ref()
+		.and_then(|params| params.as_oid().ok());
+	let verification_alg :&dyn signature::VerificationAlgorithm =
+		if *signature_alg == OID_PKCS1_SHA256WITHRSA {
+			&signature::RSA_PKCS1_2048_8192_SHA256
+		} else if *signature_alg == OID_PKCS1_SHA384WITHRSA {
+			&signature::RSA_PKCS1_2048_8192_SHA384
+		} else if *signature_alg == OID_PKCS1_SHA512WITHRSA {
+			&signature::RSA_PKCS1_2048_8192_SHA512
+		} else if *signature_alg == OID_SIG_ECDSA_WITH_SHA256 && curve == Some(&OID_EC_P256) {
+			&signature::ECDSA_P256_SHA256_ASN1
+		} else if *signature_alg == OID_SIG_ECDSA_WITH_SHA256 && curve == Some(&OID_NIST_EC_P384) {
+			&signature::ECDSA_P384_SHA256_ASN1
+		} else if *signature_alg == OID_SIG_ECDSA_WITH_SHA384 && curve == Some(&OID_EC_P256) {
+			&signature::ECDSA_P256_SHA384_ASN1
+		} else if *signature_alg == OID_SIG_ECDSA_WITH_SHA384 && curve == Some(&OID_NIST_EC_P384) {
+			&signature::ECDSA_P384_SHA384_ASN1
+		} else if *signature_alg == OID_SIG_ED25519 {
+			&signature::ED25519
+		} else {
+			return Err(X509Error::SignatureUnsupportedAlgorithm);
+		};
+	let tbs_certificate = yasna::parse_der(der, |reader| {
+		reader.read_sequence(|reader| {
+			let tbs_certificate = reader.next().read_der()?;
+			reader.next().read_der()?;
+			reader.next().read_der()?;
+			Ok(tbs_certificate)
+		})
+	}).map_err(|_| X509Error::InvalidCertificate)?;
+	let key = signature::UnparsedPublicKey::new(verification_alg, spki.subject_public_key.data);
+	key.verify(&tbs_certificate, cert.signature_value.data)
+		.map_err(|_| X509Error::SignatureVerificationError)
+}
+
+fn check_critical_extensions(i :usize, cert :&X509Certificate<'_>) -> Result<(), PathValidationError> {
+	for ext in cert.extensions().iter().filter(|ext| ext.critical) {
+		let oid = ext.oid.iter()
+			.ok_or(PathValidationError::CouldNotParseCertificate(i))?
+			.collect::<Vec<_>>();
+		if !HANDLED_EXTENSIONS.contains(&oid.as_slice()) {
+			return Err(PathValidationError::UnhandledCriticalExtension(i, oid));
+		}
+	}
+	Ok(())
+}
+
+/// Checks a certificate that issued the certificate at `i - 1`
+fn check_ca(i :usize, ca :&X509Certificate<'_>, path :&[X509Certificate<'_>]) -> Result<(), PathValidationError> {
+	let parse_err = |_| PathValidationError::CouldNotParseCertificate(i);
+	let basic_constraints = ca.basic_constraints()
+		.map_err(parse_err)?
+		.map(|ext| ext.value.clone())
+		.filter(|bc| bc.ca)
+		.ok_or(PathValidationError::NotCa(i))?;
+	if !key_usage_allowed(ca, &KeyUsagePurpose::KeyCertSign).map_err(parse_err)? {
+		return Err(PathValidationError::KeyUsageNotAllowed(i, KeyUsagePurpose::KeyCertSign));
+	}
+	if let Some(path_len) = basic_constraints.path_len_constraint {
+		// Self-issued intermediates don't count towards the path length
+		let cas_below = path[1..i].iter()
+			.filter(|cert| !is_self_issued(cert))
+			.count();
+		if cas_below as u64 > path_len as u64 {
+			return Err(PathValidationError::PathLenConstraintViolated(i));
+		}
+	}
+	if let Some(name_constraints) = ca.name_constraints().map_err(parse_err)? {
+		let name_constraints = PathNameConstraints::new(name_constraints.value)
+			.map_err(|e| match e {
+				NameConstraintsError::Invalid => PathValidationError::CouldNotParseCertificate(i),
+				NameConstraintsError::Unsupported => PathValidationError::UnsupportedNameConstraint(i),
+			})?;
+		for (j, cert) in path[..i].iter().enumerate() {
+			// Self-issued intermediates are exempt, the leaf never is
+			if j > 0 && is_self_issued(cert) {
+				continue;
+			}
+			let permitted = names_permitted(cert, &name_constraints)
+				.map_err(|_| PathValidationError::CouldNotParseCertificate(j))?;
+			if !permitted {
+				return Err(PathValidationError::NameConstraintViolation(j, i));
+			}
+		}
+	}
+	Ok(())
+}
+
+fn is_self_issued(cert :&X509Certificate<'_>) -> bool {
+	cert.issuer().as_raw() == cert.subject().as_raw()
+}
+
+fn key_usage_allowed(cert :&X509Certificate<'_>, usage :&KeyUsagePurpose) -> Result<bool, X509Error> {
+	Ok(match cert.key_usage()? {
+		Some(key_usage) => key_usage.value.flags & (1 << usage.bit_index()) != 0,
+		None => true,
+	})
+}
+
+fn extended_key_usage_allowed(cert :&X509Certificate<'_>, usage :&ExtendedKeyUsagePurpose) -> Result<bool, X509Error> {
+	let eku = match cert.extended_key_usage()? {
+		Some(eku) => eku.value,
+		None => return Ok(true),
+	};
+	Ok(eku.any || match usage {
+		ExtendedKeyUsagePurpose::Any => true,
+		ExtendedKeyUsagePurpose::ServerAuth => eku.server_auth,
+		ExtendedKeyUsagePurpose::ClientAuth => eku.client_auth,
+		ExtendedKeyUsagePurpose::CodeSigning => eku.code_signing,
+		ExtendedKeyUsagePurpose::EmailProtection => eku.email_protection,
+		ExtendedKeyUsagePurpose::TimeStamping => eku.time_stamping,
+		ExtendedKeyUsagePurpose::OcspSigning => eku.ocsp_signing,
+	})
+}
+
+fn names_permitted(cert :&X509Certificate<'_>, constraints :&PathNameConstraints<'_>) -> Result<bool, ()> {
+	if !constraints.permits_directory_name(cert.subject()) {
+		return Ok(false);
+	}
+	if let Some(san) = cert.subject_alternative_name().map_err(|_| ())? {
+		for name in san.value.general_names.iter() {
+			let name = match name {
+				GeneralName::RFC822Name(_) | GeneralName::DNSName(_) | GeneralName::URI(_) |
+				GeneralName::IPAddress(_) => SanType::try_from_general(name).map_err(|_| ())?,
+				GeneralName::DirectoryName(name) => {
+					if !constraints.permits_directory_name(name) {
+						return Ok(false);
+					}
+					continue;
+				},
+				// Subtrees of the other name forms are rejected by convert_subtree,
+				// so names of these forms are unconstrained
+				_ => continue,
+			};
+			if !constraints.name_constraints.permits_subject_alt_name(&name) {
+				return Ok(false);
+			}
+		}
+	}
+	Ok(true)
+}
+
+/// The name constraints of a CA certificate in the path
+///
+/// Directory name subtrees are compared as encoded, as [`DistinguishedName`]
+/// keeps only one value per attribute type and lacks some string types.
+struct PathNameConstraints<'a> {
+	name_constraints :NameConstraints,
+	permitted_directory_names :Vec<&'a X509Name<'a>>,
+	excluded_directory_names :Vec<&'a X509Name<'a>>,
+}
+
+impl<'a> PathNameConstraints<'a> {
+	fn new(name_constraints :&'a x509_parser::extensions::NameConstraints<'a>) -> Result<Self, NameConstraintsError> {
+		let split = |subtrees :&'a Option<Vec<x509_parser::extensions::GeneralSubtree<'a>>>| {
+			let mut converted = Vec::new();
+			let mut directory_names = Vec::new();
+			for subtree in subtrees.iter().flatten() {
+				match &subtree.base {
+					GeneralName::DirectoryName(name) => directory_names.push(name),
+					base => converted.push(convert_subtree(base)?),
+				}
+			}
+			Ok((converted, directory_names))
+		};
+		let (permitted_subtrees, permitted_directory_names) = split(&name_constraints.permitted_subtrees)?;
+		let (excluded_subtrees, excluded_directory_names) = split(&name_constraints.excluded_subtrees)?;
+		Ok(PathNameConstraints {
+			name_constraints : NameConstraints {
+				permitted_subtrees,
+				excluded_subtrees,
+			},
+			permitted_directory_names,
+			excluded_directory_names,
+		})
+	}
+	/// Follows the rules of [`NameConstraints::permits_distinguished_name`]
+	fn permits_directory_name(&self, name :&X509Name<'_>) -> bool {
+		if name.iter().next().is_none() {
+			return true;
+		}
+		if self.excluded_directory_names.iter().any(|base| directory_name_matches(name, base)) {
+			return false;
+		}
+		self.permitted_directory_names.is_empty() ||
+			self.permitted_directory_names.iter().any(|base| directory_name_matches(name, base))
+	}
+}
+
+/// The base has to be a prefix of the name's sequence of RDNs
+fn directory_name_matches(name :&X509Name<'_>, base :&X509Name<'_>) -> bool {
+	let mut rdns = name.iter();
+	base.iter().all(|base_rdn| match rdns.next() {
+		Some(rdn) => rdn_matches(rdn, base_rdn),
+		None => false,
+	})
+}
+
+/// The attributes of an RDN are a set, so they may match in any order
+fn rdn_matches(rdn :&RelativeDistinguishedName<'_>, base :&RelativeDistinguishedName<'_>) -> bool {
+	rdn.iter().count() == base.iter().count() &&
+		base.iter().all(|base_attr| rdn.iter().any(|attr| attribute_matches(attr, base_attr)))
+}
+
+fn attribute_matches(attr :&AttributeTypeAndValue<'_>, base :&AttributeTypeAndValue<'_>) -> bool {
+	fn as_str<'a>(value :&'a DerObject<'_>) -> Option<&'a str> {
+		match &value.content {
+			DerObjectContent::PrintableString(s) | DerObjectContent::UTF8String(s) => Some(s),
+			_ => None,
+		}
+	}
+	if attr.attr_type() != base.attr_type() {
+		return false;
+	}
+	match (as_str(attr.attr_value()), as_str(base.attr_value())) {
+		(Some(value), Some(base)) => case_ignore_match(value, base),
+		_ => attr.attr_value() == base.attr_value(),
+	}
+}
+
+/// The reason name constraints couldn't be converted by [`convert_name_constraints`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum NameConstraintsError {
+	/// A subtree is malformed
+	Invalid,
+	/// A subtree has a name form that [`GeneralSubtree`] can't represent
+	Unsupported,
+}
+
+/// Directory names are only converted if [`DistinguishedName`] represents them
+/// exactly, as they would match different names otherwise
+pub(crate) fn convert_name_constraints(name_constraints :&x509_parser::extensions::NameConstraints<'_>) -> Result<NameConstraints, NameConstraintsError> {
+	let convert = |subtrees :&Option<Vec<x509_parser::extensions::GeneralSubtree<'_>>>| {
+		let mut converted = Vec::new();
+		for subtree in subtrees.iter().flatten() {
+			converted.push(match &subtree.base {
+				GeneralName::DirectoryName(name) => {
+					GeneralSubtree::DirectoryName(DistinguishedName::from_name_exact(name)
+						.map_err(|_| NameConstraintsError::Unsupported)?)
+				},
+				base => convert_subtree(base)?,
+			});
+		}
+		Ok(converted)
+	};
+	Ok(NameConstraints {
+		permitted_subtrees : convert(&name_constraints.permitted_subtrees)?,
+		excluded_subtrees : convert(&name_constraints.excluded_subtrees)?,
+	})
+}
+
+/// Converts subtrees of all name forms but directory names
+fn convert_subtree(base :&GeneralName<'_>) -> Result<GeneralSubtree, NameConstraintsError> {
+	Ok(match base {
+		GeneralName::RFC822Name(name) => GeneralSubtree::Rfc822Name(name.to_string()),
+		GeneralName::DNSName(name) => GeneralSubtree::DnsName(name.to_string()),
+		GeneralName::IPAddress(bytes) => GeneralSubtree::IpAddress(cidr_subnet_from_bytes(bytes)
+			.map_err(|()| NameConstraintsError::Invalid)?),
+		// Names can't be checked against subtrees of other forms, so they
+		// can't be ignored either without accepting names they exclude
+		_ => return Err(NameConstraintsError::Unsupported),
+	})
+}
+
+pub(crate) fn ip_addr_from_bytes(bytes :&[u8]) -> Result<IpAddr, ()> {
+	if let Ok(octets) = <[u8; 4]>::try_from(bytes) {
+		Ok(IpAddr::V4(Ipv4Addr::from(octets)))
+	} else if let Ok(octets) = <[u8; 16]>::try_from(bytes) {
+		Ok(IpAddr::V6(Ipv6Addr::from(octets)))
+	} else {
+		Err(())
+	}
+}
+
+pub(crate) fn cidr_subnet_from_bytes(bytes :&[u8]) -> Result<CidrSubnet, ()> {
+	match bytes.len() {
+		8 => Ok(CidrSubnet::V4(bytes[..4].try_into().unwrap(), bytes[4..].try_into().unwrap())),
+		32 => Ok(CidrSubnet::V6(bytes[..16].try_into().unwrap(), bytes[16..].try_into().unwrap())),
+		_ => Err(()),
+	}
+}
@@ -0,0 +1,237 @@
+#![cfg(feature = "acme-server")]
+
+mod util;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::{json, Value};
+use rcgen::{AcmeTestServer, AcmeTestServerOptions, Certificate, CertificateParams,
+	ChallengeValidation, DistinguishedName, ExtendedKeyUsagePurpose, PathValidationOptions};
+use util::{http, HttpResponse};
+
+const JOSE_JSON :&str = "application/jose+json";
+
+fn b64(data :&[u8]) -> String {
+	base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+struct Client {
+	key :EcdsaKeyPair,
+	directory :Value,
+	kid :Option<String>,
+}
+
+impl Client {
+	fn new(directory_url :&str) -> Self {
+		let rng = SystemRandom::new();
+		let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+		let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref()).unwrap();
+		let directory = http("GET", directory_url, JOSE_JSON, &[]).json();
+		Client { key, directory, kid : None }
+	}
+	fn jwk(&self) -> Value {
+		let point = self.key.public_key().as_ref();
+		json!({
+			"kty" : "EC",
+			"crv" : "P-256",
+			"x" : b64(&point[1..33]),
+			"y" : b64(&point[33..]),
+		})
+	}
+	fn thumbprint(&self) -> String {
+		let jwk = self.jwk();
+		let canonical = format!(r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+			jwk["x"].as_str().unwrap(), jwk["y"].as_str().unwrap());
+		b64(ring::digest::digest(&ring::digest::SHA256, canonical.as_bytes()).as_ref())
+	}
+	fn nonce(&self) -> String {
+		let response = http("HEAD", self.directory["newNonce"].as_str().unwrap(), JOSE_JSON, &[]);
+		assert_eq!(response.status, 200);
+		response.header("Replay-Nonce").unwrap().to_string()
+	}
+	fn post_with_nonce(&self, url :&str, payload :Option<&Value>, nonce :&str) -> HttpResponse {
+		let mut protected = json!({ "alg" : "ES256", "nonce" : nonce, "url" : url });
+		match &self.kid {
+			Some(kid) => protected["kid"] = json!(kid),
+			None => protected["jwk"] = self.jwk(),
+		}
+		let protected = b64(protected.to_string().as_bytes());
+		let payload = payload.map(|p| b64(p.to_string().as_bytes())).unwrap_or_default();
+		let signature = self.key.sign(&SystemRandom::new(), format!("{}.{}", protected, payload).as_bytes()).unwrap();
+		let body = json!({
+			"protected" : protected,
+			"payload" : payload,
+			"signature" : b64(signature.as_ref()),
+		});
+		http("POST", url, JOSE_JSON, body.to_string().as_bytes())
+	}
+	fn post(&self, url :&str, payload :Option<&Value>) -> HttpResponse {
+		self.post_with_nonce(url, payload, &self.nonce())
+	}
+	fn register(&mut self) {
+		let url = self.directory["newAccount"].as_str().unwrap().to_string();
+		let response = self.post(&url, Some(&json!({ "termsOfServiceAgreed" : true })));
+		assert_eq!(response.status, 201);
+		self.kid = Some(response.header("Location").unwrap().to_string());
+	}
+	fn new_order(&self, identifiers :Value) -> (String, Value) {
+		let url = self.directory["newOrder"].as_str().unwrap();
+		let response = self.post(url, Some(&json!({ "identifiers" : identifiers })));
+		assert_eq!(response.status, 201);
+		(response.header("Location").unwrap().to_string(), response.json())
+	}
+	fn respond(&self, order :&Value, kind :&str) -> (String, Value) {
+		let authz_url = order["authorizations"][0].as_str().unwrap();
+		let authz = self.post(authz_url, None).json();
+		assert_eq!(authz["status"], "pending");
+		let challenge = authz["challenges"].as_array().unwrap().iter()
+			.find(|c| c["type"] == kind)
+			.unwrap()
+			.clone();
+		let response = self.post(challenge["url"].as_str().unwrap(), Some(&json!({})));
+		assert_eq!(response.status, 200);
+		(challenge["token"].as_str().unwrap().to_string(), response.json())
+	}
+	fn finalize(&self, order :&Value, names :&[&str]) -> HttpResponse {
+		let mut params = CertificateParams::new(names.iter().map(|n| n.to_string()).collect::<Vec<_>>());
+		params.distinguished_name = DistinguishedName::new();
+		let csr = Certificate::from_params(params).unwrap().serialize_request_der().unwrap();
+		self.post(order["finalize"].as_str().unwrap(), Some(&json!({ "csr" : b64(&csr) })))
+	}
+}
+
+fn start_server(challenge_validation :ChallengeValidation) -> AcmeTestServer {
+	let ca = Certificate::from_params(CertificateParams::root_ca("Crab ACME CA")).unwrap();
+	let mut options = AcmeTestServerOptions::default();
+	options.challenge_validation = challenge_validation;
+	AcmeTestServer::start(ca, options).unwrap()
+}
+
+fn pem_chain(body :&[u8]) -> Vec<Vec<u8>> {
+	pem::parse_many(body).unwrap().into_iter()
+		.map(|p| p.contents)
+		.collect()
+}
+
+#[test]
+fn test_acme_server_automatic() {
+	let server = start_server(ChallengeValidation::Automatic);
+	let mut client = Client::new(&server.directory_url());
+	client.register();
+
+	let (order_url, order) = client.new_order(json!([
+		{ "type" : "dns", "value" : "crabs.crabs" },
+	]));
+	assert_eq!(order["status"], "pending");
+
+	// Finalizing before the challenges are done fails
+	let response = client.finalize(&order, &["crabs.crabs"]);
+	assert_eq!(response.status, 403);
+	assert_eq!(response.json()["type"], "urn:ietf:params:acme:error:orderNotReady");
+
+	let (_, challenge) = client.respond(&order, "dns-01");
+	assert_eq!(challenge["status"], "valid");
+	assert_eq!(client.post(&order_url, None).json()["status"], "ready");
+
+	// The CSR's names have to match the identifiers
+	let response = client.finalize(&order, &["crabs.crabs", "other.crabs"]);
+	assert_eq!(response.status, 400);
+	assert_eq!(response.json()["type"], "urn:ietf:params:acme:error:badCSR");
+
+	let response = client.finalize(&order, &["crabs.crabs"]);
+	assert_eq!(response.status, 200);
+	let order = response.json();
+	assert_eq!(order["status"], "valid");
+
+	let response = client.post(order["certificate"].as_str().unwrap(), None);
+	assert_eq!(response.status, 200);
+	assert_eq!(response.header("Content-Type"), Some("application/pem-certificate-chain"));
+	let chain = pem_chain(&response.body);
+	assert_eq!(chain.len(), 2);
+	assert_eq!(chain[1], server.ca_certificate_der());
+
+	// The account lists its orders
+	let account = client.post(client.kid.as_ref().unwrap(), None).json();
+	let response = client.post(account["orders"].as_str().unwrap(), None);
+	assert_eq!(response.status, 200);
+	assert_eq!(response.json()["orders"], json!([order_url]));
+
+	let mut options = PathValidationOptions::default();
+	options.extended_key_usage = Some(ExtendedKeyUsagePurpose::ServerAuth);
+	rcgen::verify_certificate_chain(&[&chain[0]], &chain[1], &options).unwrap();
+}
+
+#[test]
+fn test_acme_server_rejects_replayed_nonce() {
+	let server = start_server(ChallengeValidation::Automatic);
+	let mut client = Client::new(&server.directory_url());
+	client.register();
+	let url = client.kid.clone().unwrap();
+
+	let nonce = client.nonce();
+	assert_eq!(client.post_with_nonce(&url, None, &nonce).status, 200);
+	let response = client.post_with_nonce(&url, None, &nonce);
+	assert_eq!(response.status, 400);
+	assert_eq!(response.json()["type"], "urn:ietf:params:acme:error:badNonce");
+
+	// Other clients can't access the account
+	let mut other = Client::new(&server.directory_url());
+	other.register();
+	assert_eq!(other.post(&url, None).status, 403);
+}
+
+#[test]
+fn test_acme_server_loopback() {
+	// Serves the key authorization for any token
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let http_port = listener.local_addr().unwrap().port();
+	let server = start_server(ChallengeValidation::Loopback { http_port });
+	let mut client = Client::new(&server.directory_url());
+	client.register();
+	let thumbprint = client.thumbprint();
+	let directory_url = server.directory_url();
+	let responder = std::thread::spawn(move || {
+		let (stream, _) = listener.accept().unwrap();
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+		let mut request_line = String::new();
+		reader.read_line(&mut request_line).unwrap();
+		let mut host = String::new();
+		loop {
+			let mut line = String::new();
+			reader.read_line(&mut line).unwrap();
+			if line.trim_end().is_empty() {
+				break;
+			}
+			if let Some(value) = line.strip_prefix("Host:") {
+				host = value.trim().to_string();
+			}
+		}
+		let path = request_line.split_whitespace().nth(1).unwrap();
+		let token = path.strip_prefix("/.well-known/acme-challenge/").unwrap();
+		let body = format!("{}.{}", token, thumbprint);
+		// The server keeps answering requests while it waits for the validation
+		assert_eq!(http("GET", &directory_url, JOSE_JSON, &[]).status, 200);
+		let mut stream = stream;
+		write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+		host
+	});
+
+	let (_, order) = client.new_order(json!([{ "type" : "dns", "value" : "crabs.crabs" }]));
+	let (_, challenge) = client.respond(&order, "http-01");
+	assert_eq!(challenge["status"], "valid");
+	assert_eq!(responder.join().unwrap(), "crabs.crabs");
+	assert_eq!(client.finalize(&order, &["crabs.crabs"]).status, 200);
+
+	// Without a responder, validation fails
+	let (order_url, order) = client.new_order(json!([{ "type" : "dns", "value" : "other.crabs" }]));
+	let (_, challenge) = client.respond(&order, "http-01");
+	assert_eq!(challenge["status"], "invalid");
+	assert_eq!(client.post(&order_url, None).json()["status"], "invalid");
+
+	// Other challenge types can't be validated
+	let (_, order) = client.new_order(json!([{ "type" : "dns", "value" : "third.crabs" }]));
+	let (_, challenge) = client.respond(&order, "tls-alpn-01");
+	assert_eq!(challenge["status"], "invalid");
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::complexity, clippy::style, clippy::pedantic)]
+
 #[cfg(feature = "x509-parser")]
 use rcgen::DnValue;
 use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
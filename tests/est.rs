@@ -0,0 +1,215 @@
+#![cfg(feature = "est")]
+
+mod util;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use yasna::Tag;
+use yasna::models::ObjectIdentifier;
+use rcgen::{Certificate, CertificateParams, DnType, EstRequest, EstServer, EstServerOptions,
+	ExtendedKeyUsagePurpose, PathValidationOptions};
+use x509_parser::extensions::GeneralName;
+use util::http;
+
+/// A minimal HTTP server passing all requests to the EST server,
+/// as a stand-in for a real HTTP framework
+fn serve(server :EstServer) -> String {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+	let server = Arc::new(server);
+	std::thread::spawn(move || {
+		for stream in listener.incoming() {
+			let mut stream = stream.unwrap();
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut request_line = String::new();
+			reader.read_line(&mut request_line).unwrap();
+			let mut parts = request_line.split_whitespace();
+			let (method, path) = (parts.next().unwrap().to_string(), parts.next().unwrap().to_string());
+			let mut content_type = None;
+			let mut content_length = 0;
+			loop {
+				let mut line = String::new();
+				reader.read_line(&mut line).unwrap();
+				let line = line.trim_end();
+				if line.is_empty() {
+					break;
+				}
+				let (name, value) = line.split_once(':').unwrap();
+				if name.eq_ignore_ascii_case("content-type") {
+					content_type = Some(value.trim().to_string());
+				} else if name.eq_ignore_ascii_case("content-length") {
+					content_length = value.trim().parse().unwrap();
+				}
+			}
+			let mut body = vec![0; content_length];
+			reader.read_exact(&mut body).unwrap();
+
+			let response = server.handle(&EstRequest {
+				method : &method,
+				path : &path,
+				content_type : content_type.as_deref(),
+				body : &body,
+			});
+			write!(stream, "HTTP/1.1 {} EST\r\nContent-Length: {}\r\nConnection: close\r\n",
+				response.status, response.body.len()).unwrap();
+			for (name, value) in response.headers.iter() {
+				write!(stream, "{}: {}\r\n", name, value).unwrap();
+			}
+			write!(stream, "\r\n").unwrap();
+			stream.write_all(&response.body).unwrap();
+		}
+	});
+	format!("http://{}/.well-known/est", addr)
+}
+
+/// Extracts the certificates from a certs-only PKCS#7 structure
+fn pkcs7_certificates(der :&[u8]) -> Vec<Vec<u8>> {
+	yasna::parse_der(der, |reader| {
+		reader.read_sequence(|reader| {
+			assert_eq!(reader.next().read_oid()?, ObjectIdentifier::from_slice(&[1, 2, 840, 113549, 1, 7, 2]));
+			reader.next().read_tagged(Tag::context(0), |reader| {
+				reader.read_sequence(|reader| {
+					reader.next().read_u8()?;
+					reader.next().read_der()?;
+					reader.next().read_der()?;
+					let certificates = reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+						reader.collect_set_of(|reader| reader.read_der())
+					})?;
+					reader.next().read_der()?;
+					Ok(certificates)
+				})
+			})
+		})
+	}).unwrap()
+}
+
+fn start_server(options :EstServerOptions) -> (String, Vec<u8>) {
+	let ca = Certificate::from_params(CertificateParams::root_ca("Crab EST CA")).unwrap();
+	let ca_der = ca.serialize_der().unwrap();
+	(serve(EstServer::new(ca, ca_der.clone(), options)), ca_der)
+}
+
+fn csr_base64(names :&[&str]) -> Vec<u8> {
+	let mut params = CertificateParams::new(names.iter().map(|n| n.to_string()).collect::<Vec<_>>());
+	params.distinguished_name.push(DnType::CommonName, "Crab device 42");
+	let csr = Certificate::from_params(params).unwrap().serialize_request_der().unwrap();
+	// Clients may wrap the base64 encoding into lines
+	let encoded = base64::encode(&csr);
+	let lines = encoded.as_bytes().chunks(64).collect::<Vec<_>>();
+	lines.join(&b"\r\n"[..])
+}
+
+#[test]
+fn test_est_enrollment() {
+	let (url, ca_der) = start_server(EstServerOptions::default());
+
+	let response = http("GET", &format!("{}/cacerts", url), "text/plain", &[]);
+	assert_eq!(response.status, 200);
+	assert_eq!(response.header("Content-Type"), Some("application/pkcs7-mime"));
+	assert_eq!(pkcs7_certificates(&response.base64_body()), vec![ca_der.clone()]);
+
+	for operation in ["simpleenroll", "simplereenroll"] {
+		let response = http("POST", &format!("{}/{}", url, operation), "application/pkcs10",
+			&csr_base64(&["device42.crabs"]));
+		assert_eq!(response.status, 200);
+		assert_eq!(response.header("Content-Type"), Some("application/pkcs7-mime; smime-type=certs-only"));
+		let certificates = pkcs7_certificates(&response.base64_body());
+		assert_eq!(certificates.len(), 1);
+
+		let mut options = PathValidationOptions::default();
+		options.extended_key_usage = Some(ExtendedKeyUsagePurpose::ClientAuth);
+		rcgen::verify_certificate_chain(&[&certificates[0]], &ca_der, &options).unwrap();
+		let (_, cert) = x509_parser::parse_x509_certificate(&certificates[0]).unwrap();
+		assert_eq!(cert.subject().to_string(), "CN=Crab device 42");
+		let san = cert.subject_alternative_name().unwrap().unwrap();
+		assert_eq!(san.value.general_names, vec![GeneralName::DNSName("device42.crabs")]);
+	}
+
+	// CA labels are accepted
+	let response = http("POST", &format!("{}/devices/simpleenroll", url), "application/pkcs10",
+		&csr_base64(&["device43.crabs"]));
+	assert_eq!(response.status, 200);
+}
+
+#[test]
+fn test_est_errors() {
+	let mut options = EstServerOptions::default();
+	options.csr_attributes = vec![vec![1, 2, 840, 113549, 1, 9, 7]];
+	let (url, _) = start_server(options);
+
+	let response = http("GET", &format!("{}/csrattrs", url), "text/plain", &[]);
+	assert_eq!(response.status, 200);
+	assert_eq!(response.header("Content-Type"), Some("application/csrattrs"));
+	let attributes = yasna::parse_der(&response.base64_body(), |reader| {
+		reader.collect_sequence_of(|reader| reader.read_oid())
+	}).unwrap();
+	assert_eq!(attributes, vec![ObjectIdentifier::from_slice(&[1, 2, 840, 113549, 1, 9, 7])]);
+	let (empty_url, _) = start_server(EstServerOptions::default());
+	assert_eq!(http("GET", &format!("{}/csrattrs", empty_url), "text/plain", &[]).status, 204);
+
+	let enroll_url = format!("{}/simpleenroll", url);
+	assert_eq!(http("POST", &enroll_url, "application/pkcs10", b"not base64!").status, 400);
+	assert_eq!(http("POST", &enroll_url, "application/pkcs10", base64::encode(b"no CSR").as_bytes()).status, 400);
+	assert_eq!(http("POST", &enroll_url, "application/json", &csr_base64(&["a.crabs"])).status, 415);
+	let response = http("GET", &enroll_url, "text/plain", &[]);
+	assert_eq!(response.status, 405);
+	assert_eq!(response.header("Allow"), Some("POST"));
+	assert_eq!(http("POST", &format!("{}/serverkeygen", url), "application/pkcs10", &[]).status, 404);
+	assert_eq!(http("GET", &format!("{}/a/b/cacerts", url), "text/plain", &[]).status, 404);
+
+	let mut rules = rcgen::SigningRules::default();
+	rules.allowed_dns_names = Some(vec![".crabs".to_string()]);
+	let mut options = EstServerOptions::default();
+	options.signing_rules = Some(rules);
+	let (url, _) = start_server(options);
+	let enroll_url = format!("{}/simpleenroll", url);
+	assert_eq!(http("POST", &enroll_url, "application/pkcs10", &csr_base64(&["a.crabs"])).status, 200);
+	let response = http("POST", &enroll_url, "application/pkcs10", &csr_base64(&["a.lobsters"]));
+	assert_eq!(response.status, 403);
+	assert!(String::from_utf8(response.body).unwrap().contains("a.lobsters"));
+	// CSRs without subject alternative names can't bypass the allowed names
+	assert_eq!(http("POST", &enroll_url, "application/pkcs10", &csr_base64(&[])).status, 403);
+}
+
+#[test]
+fn test_scep_ca_certificates() {
+	let scep = |server :&EstServer, method :&str, path :&str| server.handle_scep(&EstRequest {
+		method,
+		path,
+		content_type : None,
+		body : &[],
+	});
+	let ca = Certificate::from_params(CertificateParams::root_ca("Crab SCEP CA")).unwrap();
+	let ca_der = ca.serialize_der().unwrap();
+	let server = EstServer::new(ca, ca_der.clone(), EstServerOptions::default());
+
+	let response = scep(&server, "GET", "/cgi-bin/pkiclient.exe?operation=GetCACaps");
+	assert_eq!(response.status, 200);
+	assert_eq!(response.body, b"SHA-256\n");
+
+	let response = scep(&server, "GET", "/scep?operation=GetCACert&message=crabs");
+	assert_eq!(response.status, 200);
+	assert_eq!(response.header("Content-Type"), Some("application/x-x509-ca-cert"));
+	assert_eq!(response.body, ca_der);
+
+	let response = scep(&server, "POST", "/scep?operation=PKIOperation");
+	assert_eq!(response.status, 501);
+	assert_eq!(scep(&server, "POST", "/scep?operation=GetCACert").status, 405);
+	assert_eq!(scep(&server, "GET", "/scep").status, 400);
+
+	// With further CA certificates, the whole chain is returned
+	let root_der = Certificate::from_params(CertificateParams::root_ca("Crab root CA")).unwrap()
+		.serialize_der().unwrap();
+	let mut options = EstServerOptions::default();
+	options.ca_certificates = vec![root_der.clone()];
+	let ca = Certificate::from_params(CertificateParams::root_ca("Crab SCEP CA")).unwrap();
+	let ca_der = ca.serialize_der().unwrap();
+	let server = EstServer::new(ca, ca_der.clone(), options);
+	let response = scep(&server, "GET", "/scep?operation=GetCACert");
+	assert_eq!(response.header("Content-Type"), Some("application/x-x509-ca-ra-cert"));
+	// The certificates are sorted as required for a DER encoded SET OF
+	let mut chain = vec![ca_der, root_der];
+	chain.sort();
+	assert_eq!(pkcs7_certificates(&response.body), chain);
+}
@@ -1,9 +1,12 @@
 mod util;
 
 use rcgen::{RcgenError, KeyPair, Certificate};
+#[cfg(feature = "pem")]
 use std::hash::{Hash, Hasher};
+#[cfg(feature = "pem")]
 use std::collections::hash_map::DefaultHasher;
 
+#[cfg(feature = "pem")]
 fn generate_hash<T: Hash>(subject: &T) -> u64 {
 	let mut hasher = DefaultHasher::new();
 	subject.hash(&mut hasher);
@@ -11,6 +14,7 @@ fn generate_hash<T: Hash>(subject: &T) -> u64 {
 }
 
 #[test]
+#[cfg(feature = "pem")]
 fn test_key_params_mismatch() {
 	let available_key_params = [
 		&rcgen::PKCS_RSA_SHA256,
@@ -45,3 +49,1633 @@ fn test_key_params_mismatch() {
 		}
 	}
 }
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_key_roundtrip() {
+	let ciphers = [
+		rcgen::KeyEncryptionCipher::Aes256Cbc,
+		rcgen::KeyEncryptionCipher::Aes256Gcm,
+	];
+	for cipher in ciphers.iter() {
+		let enc_params = rcgen::KeyEncryptionParams {
+			kdf : rcgen::KeyDerivationFunction::Pbkdf2HmacSha256 { iterations : 1000 },
+			cipher : cipher.clone(),
+		};
+		let key_pair = KeyPair::generate(&rcgen::PKCS_ED25519).unwrap();
+		let der = key_pair.serialize_der_encrypted_with_params("crabs", &enc_params).unwrap();
+
+		let decrypted = KeyPair::from_der_encrypted(&der, "crabs").unwrap();
+		assert_eq!(decrypted.serialize_der(), key_pair.serialize_der());
+		assert_eq!(decrypted.public_key_raw(), key_pair.public_key_raw());
+
+		assert_eq!(KeyPair::from_der_encrypted(&der, "lobsters").err(),
+			Some(RcgenError::KeyDecryptionFailed), "cipher: {:?}", cipher);
+	}
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_key_limits() {
+	use yasna::models::ObjectIdentifier;
+
+	// An EncryptedPrivateKeyInfo with the given key derivation function and AES-256-CBC
+	let encrypted_key = |kdf_oid :&[u64], kdf_params :&dyn Fn(&mut yasna::DERWriterSeq)| {
+		yasna::construct_der(|writer| writer.write_sequence(|writer| {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&ObjectIdentifier::from_slice(&[1, 2, 840, 113549, 1, 5, 13]));
+				writer.next().write_sequence(|writer| {
+					writer.next().write_sequence(|writer| {
+						writer.next().write_oid(&ObjectIdentifier::from_slice(kdf_oid));
+						writer.next().write_sequence(|writer| kdf_params(writer));
+					});
+					writer.next().write_sequence(|writer| {
+						writer.next().write_oid(&ObjectIdentifier::from_slice(&[2, 16, 840, 1, 101, 3, 4, 1, 42]));
+						writer.next().write_bytes(&[0; 16]);
+					});
+				});
+			});
+			writer.next().write_bytes(&[0; 64]);
+		}))
+	};
+	let pbkdf2 = encrypted_key(&[1, 2, 840, 113549, 1, 5, 12], &|writer| {
+		writer.next().write_bytes(&[0; 16]);
+		writer.next().write_u32(u32::MAX);
+	});
+	assert_eq!(KeyPair::from_der_encrypted(&pbkdf2, "crabs").err(),
+		Some(RcgenError::UnsupportedKeyEncryption));
+	// N = 2^30 and r = 8 would need 1 TiB
+	let scrypt = encrypted_key(&[1, 3, 6, 1, 4, 1, 11591, 4, 11], &|writer| {
+		writer.next().write_bytes(&[0; 16]);
+		writer.next().write_u64(1 << 30);
+		writer.next().write_u32(8);
+		writer.next().write_u32(1);
+	});
+	assert_eq!(KeyPair::from_der_encrypted(&scrypt, "crabs").err(),
+		Some(RcgenError::UnsupportedKeyEncryption));
+
+	let enc_params = rcgen::KeyEncryptionParams {
+		kdf : rcgen::KeyDerivationFunction::Scrypt { log_n : 64, r : 8, p : 1 },
+		cipher : rcgen::KeyEncryptionCipher::Aes256Cbc,
+	};
+	let key_pair = KeyPair::generate(&rcgen::PKCS_ED25519).unwrap();
+	assert_eq!(key_pair.serialize_der_encrypted_with_params("crabs", &enc_params).err(),
+		Some(RcgenError::UnsupportedKeyEncryption));
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_pkcs12_roundtrip() {
+	let cert = Certificate::from_params(util::default_params()).unwrap();
+	let cert_der = cert.serialize_der().unwrap();
+	let p12_params = rcgen::Pkcs12Params {
+		encryption : rcgen::KeyEncryptionParams {
+			kdf : rcgen::KeyDerivationFunction::Pbkdf2HmacSha256 { iterations : 1000 },
+			cipher : rcgen::KeyEncryptionCipher::Aes256Gcm,
+		},
+		.. Default::default()
+	};
+	let p12 = cert.serialize_pkcs12_der(&cert_der, "crabs", &p12_params).unwrap();
+
+	let contents = rcgen::Pkcs12Contents::from_der(&p12, "crabs").unwrap();
+	assert_eq!(contents.certificate_der, cert_der);
+	assert_eq!(contents.key_pair.serialize_der(), cert.serialize_private_key_der());
+	assert_eq!(contents.friendly_name, None);
+
+	assert_eq!(rcgen::Pkcs12Contents::from_der(&p12, "lobsters").err(),
+		Some(RcgenError::KeyDecryptionFailed));
+
+	// The key is paired by its public key, not by the position of the certificate
+	let ca_der = Certificate::from_params(util::default_params()).unwrap().serialize_der().unwrap();
+	let p12_params = rcgen::Pkcs12Params {
+		ca_certificates : vec![cert_der.clone()],
+		.. Default::default()
+	};
+	let p12_mismatch = cert.serialize_pkcs12_der(&ca_der, "crabs", &p12_params).unwrap();
+	let contents = rcgen::Pkcs12Contents::from_der(&p12_mismatch, "crabs").unwrap();
+	assert_eq!(contents.certificate_der, cert_der);
+	assert_eq!(contents.ca_certificates, vec![ca_der.clone()]);
+	let p12_mismatch = cert.serialize_pkcs12_der(&ca_der, "crabs", &Default::default()).unwrap();
+	assert_eq!(rcgen::Pkcs12Contents::from_der(&p12_mismatch, "crabs").err(),
+		Some(RcgenError::CertificateKeyPairMismatch));
+
+	// Archives without MacData are rejected
+	let p12_without_mac = yasna::parse_der(&p12, |reader| {
+		reader.read_sequence(|reader| {
+			let version = reader.next().read_der()?;
+			let auth_safe = reader.next().read_der()?;
+			reader.next().read_der()?;
+			Ok(yasna::construct_der(|writer| writer.write_sequence(|writer| {
+				writer.next().write_der(&version);
+				writer.next().write_der(&auth_safe);
+			})))
+		})
+	}).unwrap();
+	assert_eq!(rcgen::Pkcs12Contents::from_der(&p12_without_mac, "crabs").err(),
+		Some(RcgenError::Pkcs12NotAuthenticated));
+
+	// Excessive MAC iteration counts are rejected before deriving the key
+	let p12_slow_mac = yasna::parse_der(&p12, |reader| {
+		reader.read_sequence(|reader| {
+			let version = reader.next().read_der()?;
+			let auth_safe = reader.next().read_der()?;
+			let (mac, salt) = reader.next().read_sequence(|reader| {
+				let mac = reader.next().read_der()?;
+				let salt = reader.next().read_bytes()?;
+				reader.read_optional(|reader| reader.read_u32())?;
+				Ok((mac, salt))
+			})?;
+			Ok(yasna::construct_der(|writer| writer.write_sequence(|writer| {
+				writer.next().write_der(&version);
+				writer.next().write_der(&auth_safe);
+				writer.next().write_sequence(|writer| {
+					writer.next().write_der(&mac);
+					writer.next().write_bytes(&salt);
+					writer.next().write_u32(u32::MAX);
+				});
+			})))
+		})
+	}).unwrap();
+	assert_eq!(rcgen::Pkcs12Contents::from_der(&p12_slow_mac, "crabs").err(),
+		Some(RcgenError::CouldNotParsePkcs12));
+}
+
+#[test]
+#[cfg(feature = "pem")]
+fn test_public_key_from_spki() {
+	let key_pairs = [
+		KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap(),
+		KeyPair::generate(&rcgen::PKCS_ECDSA_P384_SHA384).unwrap(),
+		KeyPair::generate(&rcgen::PKCS_ED25519).unwrap(),
+		KeyPair::from_pem(util::RSA_TEST_KEY_PAIR_PEM).unwrap(),
+	];
+	for key_pair in key_pairs.iter() {
+		let spki = key_pair.public_key_der();
+		let public_key = rcgen::PublicKey::from_spki_der(&spki).unwrap();
+		assert!(key_pair.is_compatible(public_key.algorithm()));
+		assert_eq!(public_key.public_key_raw(), key_pair.public_key_raw());
+		assert_eq!(public_key.public_key_der(), spki);
+	}
+
+	let rsa_spki = key_pairs[3].public_key_der();
+	let public_key = rcgen::PublicKey::from_spki_der_and_sign_algo(&rsa_spki, &rcgen::PKCS_RSA_SHA512).unwrap();
+	assert_eq!(public_key.algorithm(), &rcgen::PKCS_RSA_SHA512);
+	assert_eq!(rcgen::PublicKey::from_spki_der_and_sign_algo(&rsa_spki, &rcgen::PKCS_ED25519).err(),
+		Some(RcgenError::CertificateKeyPairMismatch));
+
+	assert_eq!(rcgen::PublicKey::from_spki_der(&rsa_spki[1..]).err(),
+		Some(RcgenError::CouldNotParsePublicKey));
+}
+
+fn sha256_hex(data :&[u8]) -> String {
+	ring::digest::digest(&ring::digest::SHA256, data).as_ref().iter()
+		.map(|b| format!("{:02x}", b))
+		.collect()
+}
+
+#[test]
+fn test_key_pair_from_seed() {
+	let kp_1 = KeyPair::generate_from_seed(&rcgen::PKCS_ED25519, b"crabs").unwrap();
+	let kp_2 = KeyPair::generate_from_seed(&rcgen::PKCS_ED25519, b"crabs").unwrap();
+	let kp_3 = KeyPair::generate_from_seed(&rcgen::PKCS_ED25519, b"lobsters").unwrap();
+	assert!(kp_1.is_compatible(&rcgen::PKCS_ED25519));
+	assert_eq!(kp_1.serialize_der(), kp_2.serialize_der());
+	assert_ne!(kp_1.public_key_raw(), kp_3.public_key_raw());
+	assert_eq!(sha256_hex(&kp_1.serialize_der()), "44857afc506b70de603daeb810bd7c672ab7111f0a35164055b6cd9b29f77ee2");
+	// The serialized key can be imported again
+	let kp_4 = KeyPair::from_der(&kp_1.serialize_der()).unwrap();
+	assert_eq!(kp_4.public_key_raw(), kp_1.public_key_raw());
+
+	// ECDSA signatures are randomized, so seeding ECDSA keys is rejected
+	for alg in [&rcgen::PKCS_ECDSA_P256_SHA256, &rcgen::PKCS_ECDSA_P384_SHA384, &rcgen::PKCS_RSA_SHA256].iter() {
+		assert_eq!(KeyPair::generate_from_seed(alg, b"crabs").err(),
+			Some(RcgenError::KeyGenerationUnavailable));
+	}
+}
+
+#[test]
+fn test_reproducible_certificate() {
+	let mut params = util::default_params();
+	params.alg = &rcgen::PKCS_ED25519;
+	params.not_before = rcgen::date_time_ymd_hms(2022, 4, 1, 12, 30, 0);
+	params.not_after = rcgen::date_time_ymd(2023, 4, 1);
+	params.key_pair = Some(KeyPair::generate_from_seed(&rcgen::PKCS_ED25519, b"crabs").unwrap());
+	let cert = Certificate::from_params(params).unwrap();
+	assert_eq!(sha256_hex(&cert.serialize_der().unwrap()), "f893cbc63d61b88299c6ba5eb61683e8a20b2d22e28b485089d58190231caf7e");
+	assert_eq!(sha256_hex(&cert.serialize_request_der().unwrap()), "ed8fe749eacad8e375c4c69a0ae6a7f88c4ac2dfcf907296e39ff9c625aecbd5");
+}
+
+#[test]
+fn test_pki_builder_without_intermediates() {
+	let chain = rcgen::PkiBuilder::new()
+		.intermediates(0)
+		.build()
+		.unwrap();
+	assert!(chain.intermediates().is_empty());
+	assert_eq!(chain.certificates().len(), 2);
+	assert_eq!(chain.chain_der(), vec![chain.leaf().der().to_vec()]);
+}
+
+#[cfg(feature = "x509-parser")]
+fn issue(params :rcgen::CertificateParams, issuer :Option<&Certificate>) -> (Certificate, Vec<u8>) {
+	let cert = Certificate::from_params(params).unwrap();
+	let der = match issuer {
+		Some(issuer) => cert.serialize_der_with_signer(issuer).unwrap(),
+		None => cert.serialize_der().unwrap(),
+	};
+	(cert, der)
+}
+
+#[cfg(feature = "x509-parser")]
+fn ca_params(name :&str, constraint :rcgen::BasicConstraints) -> rcgen::CertificateParams {
+	let mut params = util::default_params();
+	params.subject_alt_names = Vec::new();
+	params.distinguished_name.push(rcgen::DnType::CommonName, name);
+	params.is_ca = rcgen::IsCa::Ca(constraint);
+	params
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_verify_pki_builder_chain() {
+	use rcgen::{ExtendedKeyUsagePurpose, KeyUsagePurpose, PathValidationError, PathValidationOptions};
+
+	let chain = rcgen::PkiBuilder::new()
+		.intermediates(3)
+		.leaf_subject_alt_names(vec!["crabs.crabs".to_string()])
+		.build()
+		.unwrap();
+	let mut options = PathValidationOptions::default();
+	options.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+	options.extended_key_usage = Some(ExtendedKeyUsagePurpose::ServerAuth);
+	chain.verify(&options).unwrap();
+
+	options.key_usages = vec![KeyUsagePurpose::KeyEncipherment];
+	assert_eq!(chain.verify(&options),
+		Err(PathValidationError::KeyUsageNotAllowed(0, KeyUsagePurpose::KeyEncipherment)));
+
+	let mut options = PathValidationOptions::default();
+	options.extended_key_usage = Some(ExtendedKeyUsagePurpose::CodeSigning);
+	assert_eq!(chain.verify(&options),
+		Err(PathValidationError::ExtendedKeyUsageNotAllowed(0, ExtendedKeyUsagePurpose::CodeSigning)));
+
+	options.extended_key_usage = None;
+	options.time = rcgen::date_time_ymd(1970, 1, 1);
+	assert_eq!(chain.verify(&options), Err(PathValidationError::NotYetValid(0)));
+
+	// Same root name, different key
+	let other_chain = rcgen::PkiBuilder::new().build().unwrap();
+	let chain_der = chain.chain_der();
+	let chain_der = chain_der.iter().map(|der| der.as_slice()).collect::<Vec<_>>();
+	let res = rcgen::verify_certificate_chain(&chain_der, other_chain.root().der(),
+		&PathValidationOptions::default());
+	assert_eq!(res, Err(PathValidationError::InvalidSignature(3)));
+
+	// Intermediates in the wrong order
+	let swapped = [chain_der[0], chain_der[2], chain_der[1], chain_der[3]];
+	let res = rcgen::verify_certificate_chain(&swapped, chain.root().der(),
+		&PathValidationOptions::default());
+	assert_eq!(res, Err(PathValidationError::IssuerMismatch(0)));
+
+	assert_eq!(rcgen::verify_certificate_chain(&[], chain.root().der(),
+		&PathValidationOptions::default()), Err(PathValidationError::EmptyChain));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_verify_ca_constraints() {
+	use rcgen::{BasicConstraints, KeyUsagePurpose, PathValidationError, PathValidationOptions};
+
+	let options = PathValidationOptions::default();
+
+	// Path length constraint
+	let (root, root_der) = issue(ca_params("Root", BasicConstraints::Constrained(0)), None);
+	let (intermediate, intermediate_der) = issue(ca_params("Intermediate",
+		BasicConstraints::Unconstrained), Some(&root));
+	let (_, leaf_der) = issue(util::default_params(), Some(&intermediate));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::PathLenConstraintViolated(2)));
+
+	// Issuer that is no CA
+	let mut params = util::default_params();
+	params.distinguished_name.push(rcgen::DnType::CommonName, "Not a CA");
+	let (not_ca, not_ca_der) = issue(params, None);
+	let (_, leaf_der) = issue(util::default_params(), Some(&not_ca));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &not_ca_der, &options);
+	assert_eq!(res, Err(PathValidationError::NotCa(1)));
+
+	// CA without the keyCertSign key usage
+	let mut params = ca_params("Root", BasicConstraints::Unconstrained);
+	params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+	let (root, root_der) = issue(params, None);
+	let (_, leaf_der) = issue(util::default_params(), Some(&root));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::KeyUsageNotAllowed(1, KeyUsagePurpose::KeyCertSign)));
+
+	// Unknown critical extension
+	let (root, root_der) = issue(ca_params("Root", BasicConstraints::Unconstrained), None);
+	let mut params = util::default_params();
+	let mut ext = rcgen::CustomExtension::from_oid_content(&[1, 2, 3, 4], vec![0x05, 0x00]);
+	ext.set_criticality(true);
+	params.custom_extensions.push(ext);
+	let (_, leaf_der) = issue(params, Some(&root));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::UnhandledCriticalExtension(0, vec![1, 2, 3, 4])));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_verify_name_constraints() {
+	use rcgen::{BasicConstraints, CidrSubnet, GeneralSubtree, NameConstraints,
+		PathValidationError, PathValidationOptions, SanType};
+
+	let options = PathValidationOptions::default();
+	let mut params = ca_params("Root", BasicConstraints::Unconstrained);
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![
+			GeneralSubtree::DnsName("crabs.crabs".to_string()),
+			GeneralSubtree::IpAddress(CidrSubnet::from_v4_prefix([10, 0, 0, 0], 8)),
+		],
+		excluded_subtrees : vec![GeneralSubtree::DnsName("secret.crabs.crabs".to_string())],
+	});
+	let (root, root_der) = issue(params, None);
+	let (intermediate, intermediate_der) = issue(ca_params("Intermediate",
+		BasicConstraints::Unconstrained), Some(&root));
+
+	let mut params = util::default_params();
+	params.subject_alt_names = vec![
+		SanType::DnsName("www.crabs.crabs".to_string()),
+		SanType::IpAddress("10.1.2.3".parse().unwrap()),
+	];
+	let (_, leaf_der) = issue(params, Some(&intermediate));
+	rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options).unwrap();
+
+	// "localhost" is not permitted
+	let (_, leaf_der) = issue(util::default_params(), Some(&intermediate));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 2)));
+
+	let mut params = util::default_params();
+	params.subject_alt_names = vec![SanType::DnsName("db.secret.crabs.crabs".to_string())];
+	let (_, leaf_der) = issue(params, Some(&intermediate));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 2)));
+
+	let mut params = util::default_params();
+	params.subject_alt_names = vec![SanType::IpAddress("192.168.0.1".parse().unwrap())];
+	let (_, leaf_der) = issue(params, Some(&intermediate));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 2)));
+
+	// URI subtrees can't be checked, so they must not be ignored
+	let name_constraints = yasna::construct_der(|writer| writer.write_sequence(|writer| {
+		writer.next().write_tagged_implicit(yasna::Tag::context(0), |writer| writer.write_sequence(|writer| {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_tagged_implicit(yasna::Tag::context(6), |writer| {
+					writer.write_ia5_string(".crabs.crabs")
+				});
+			});
+		}));
+	}));
+	let mut ext = rcgen::CustomExtension::from_oid_content(&[2, 5, 29, 30], name_constraints);
+	ext.set_criticality(true);
+	let mut params = ca_params("URI constrained root", BasicConstraints::Unconstrained);
+	params.custom_extensions.push(ext);
+	let (root, root_der) = issue(params, None);
+	let mut params = util::default_params();
+	params.subject_alt_names = vec![SanType::URI("https://evil.test".to_string())];
+	let (_, leaf_der) = issue(params, Some(&root));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::UnsupportedNameConstraint(1)));
+
+	// Directory name subject alternative names are checked as well
+	let mut permitted = rcgen::DistinguishedName::new();
+	permitted.push(rcgen::DnType::OrganizationName, "Crab widgits SE");
+	let mut params = ca_params("Directory name constrained root", BasicConstraints::Unconstrained);
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![GeneralSubtree::DirectoryName(permitted)],
+		excluded_subtrees : Vec::new(),
+	});
+	let (root, root_der) = issue(params, None);
+	let san = yasna::construct_der(|writer| writer.write_sequence(|writer| {
+		writer.next().write_tagged(yasna::Tag::context(4), |writer| writer.write_sequence(|writer| {
+			writer.next().write_set(|writer| writer.next().write_sequence(|writer| {
+				writer.next().write_oid(&yasna::models::ObjectIdentifier::from_slice(&[2, 5, 4, 10]));
+				writer.next().write_utf8_string("Lobster inc");
+			}));
+		}));
+	}));
+	let mut params = util::default_params();
+	params.distinguished_name = rcgen::DistinguishedName::new();
+	params.subject_alt_names = Vec::new();
+	params.custom_extensions.push(rcgen::CustomExtension::from_oid_content(&[2, 5, 29, 17], san));
+	let (_, leaf_der) = issue(params, Some(&root));
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 1)));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_verify_name_constraints_repeated_attributes() {
+	use rcgen::{BasicConstraints, CustomExtension, DistinguishedName, DnType, GeneralSubtree,
+		NameConstraints, PathValidationError, PathValidationOptions};
+
+	// Writes a directory name with one RDN per attribute
+	fn write_name(writer :yasna::DERWriter, attrs :&[(u64, &str)]) {
+		writer.write_sequence(|writer| {
+			for (ty, value) in attrs {
+				writer.next().write_set(|writer| writer.next().write_sequence(|writer| {
+					writer.next().write_oid(&yasna::models::ObjectIdentifier::from_slice(&[2, 5, 4, *ty]));
+					writer.next().write_utf8_string(value);
+				}));
+			}
+		});
+	}
+	fn leaf_with_directory_name(attrs :&[(u64, &str)], issuer :&rcgen::Certificate) -> Vec<u8> {
+		let san = yasna::construct_der(|writer| writer.write_sequence(|writer| {
+			writer.next().write_tagged(yasna::Tag::context(4), |writer| write_name(writer, attrs));
+		}));
+		let mut params = util::default_params();
+		params.distinguished_name = DistinguishedName::new();
+		params.subject_alt_names = Vec::new();
+		params.custom_extensions.push(CustomExtension::from_oid_content(&[2, 5, 29, 17], san));
+		issue(params, Some(issuer)).1
+	}
+	const O :u64 = 10;
+	const OU :u64 = 11;
+
+	let options = PathValidationOptions::default();
+	let mut good = DistinguishedName::new();
+	good.push(DnType::OrganizationName, "Good");
+	let mut evil = DistinguishedName::new();
+	evil.push(DnType::OrganizationName, "Evil");
+	let mut params = ca_params("Root", BasicConstraints::Unconstrained);
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![GeneralSubtree::DirectoryName(good)],
+		excluded_subtrees : vec![GeneralSubtree::DirectoryName(evil)],
+	});
+	let (root, root_der) = issue(params, None);
+
+	let leaf_der = leaf_with_directory_name(&[(O, "Good"), (O, "X")], &root);
+	rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options).unwrap();
+	// The first organization is not permitted
+	let leaf_der = leaf_with_directory_name(&[(O, "Evil"), (O, "Good")], &root);
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 1)));
+	// The first organization is excluded
+	let leaf_der = leaf_with_directory_name(&[(O, "Evil"), (O, "X")], &root);
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 1)));
+
+	// Subtrees with repeated attribute types are matched as a whole
+	let name_constraints = yasna::construct_der(|writer| writer.write_sequence(|writer| {
+		writer.next().write_tagged_implicit(yasna::Tag::context(0), |writer| writer.write_sequence(|writer| {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_tagged(yasna::Tag::context(4), |writer| {
+					write_name(writer, &[(OU, "A"), (OU, "B")])
+				});
+			});
+		}));
+	}));
+	let mut ext = CustomExtension::from_oid_content(&[2, 5, 29, 30], name_constraints);
+	ext.set_criticality(true);
+	let mut params = ca_params("OU constrained root", BasicConstraints::Unconstrained);
+	params.custom_extensions.push(ext);
+	let (root, root_der) = issue(params, None);
+	let leaf_der = leaf_with_directory_name(&[(OU, "A"), (OU, "B"), (O, "X")], &root);
+	rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options).unwrap();
+	let leaf_der = leaf_with_directory_name(&[(OU, "B")], &root);
+	let res = rcgen::verify_certificate_chain(&[&leaf_der], &root_der, &options);
+	assert_eq!(res, Err(PathValidationError::NameConstraintViolation(0, 1)));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_directory_name_subtree() {
+	use rcgen::{BasicConstraints, DistinguishedName, DnType, GeneralSubtree, NameConstraints};
+	use x509_parser::extensions::GeneralName;
+
+	let mut name = DistinguishedName::new();
+	name.push(DnType::OrganizationName, "Crab widgits SE");
+	let mut params = ca_params("Root", BasicConstraints::Unconstrained);
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![GeneralSubtree::DirectoryName(name)],
+		excluded_subtrees : Vec::new(),
+	});
+	let (_, der) = issue(params, None);
+	let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+	let constraints = cert.name_constraints().unwrap().unwrap().value;
+	let permitted = constraints.permitted_subtrees.as_ref().unwrap();
+	match &permitted[0].base {
+		GeneralName::DirectoryName(name) => {
+			let org = name.iter_organization().next().unwrap();
+			assert_eq!(org.as_str().unwrap(), "Crab widgits SE");
+		},
+		base => panic!("unexpected subtree {:?}", base),
+	}
+}
+
+#[test]
+fn test_lint() {
+	use rcgen::{BasicConstraints, CertificateParams, DnType, DnValue, ExtendedKeyUsagePurpose, IsCa,
+		KeyUsagePurpose, Lint, LintSeverity, NameConstraints, GeneralSubtree, SanType};
+
+	let params = util::default_params();
+	assert_eq!(params.lint(), vec![Lint::CommonNameNotInSubjectAltNames]);
+
+	// Only TLS server certificates are limited to 398 days
+	let validity_too_long = |params :&CertificateParams| params.lint().iter()
+		.any(|lint| matches!(lint, Lint::ValidityTooLong(_)));
+	let mut params = util::default_params();
+	params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+	assert!(validity_too_long(&params));
+	assert!(!validity_too_long(&CertificateParams::tls_server(vec!["crabs.crabs".to_string()])));
+
+	let mut params = util::default_params();
+	params.not_before = rcgen::date_time_ymd(2022, 1, 1);
+	params.not_after = rcgen::date_time_ymd(2021, 1, 1);
+	params.subject_alt_names.push(SanType::DnsName("crabs.*.crabs".to_string()));
+	params.subject_alt_names.push(SanType::DnsName("localhost".to_string()));
+	params.subject_alt_names.push(SanType::Rfc822Name("crabs.crabs".to_string()));
+	params.distinguished_name.push(DnType::CommonName, DnValue::PrintableString("crabs & co".to_string()));
+	params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::KeyEncipherment];
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![GeneralSubtree::DnsName("crabs.crabs".to_string())],
+		excluded_subtrees : Vec::new(),
+	});
+	let lints = params.lint();
+	for lint in [
+		Lint::NotAfterBeforeNotBefore,
+		Lint::InvalidDnsName("crabs.*.crabs".to_string()),
+		Lint::InvalidRfc822Name("crabs.crabs".to_string()),
+		Lint::DuplicateSubjectAltName(SanType::DnsName("localhost".to_string())),
+		Lint::InvalidPrintableString(DnType::CommonName),
+		Lint::KeyCertSignWithoutCa,
+		Lint::KeyUsageIncompatibleWithKey(KeyUsagePurpose::KeyEncipherment),
+		Lint::NameConstraintsWithoutCa,
+	].iter() {
+		assert!(lints.contains(lint), "{:?} missing in {:?}", lint, lints);
+	}
+
+	let mut params = CertificateParams::default();
+	params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+	assert_eq!(params.lint(), vec![Lint::CaWithoutKeyUsage]);
+	assert_eq!(Lint::CaWithoutKeyUsage.severity(), LintSeverity::Warning);
+	params.key_usages = vec![KeyUsagePurpose::CrlSign];
+	assert_eq!(params.lint(), vec![Lint::CaWithoutKeyCertSign]);
+
+	params.strict = true;
+	assert_eq!(Certificate::from_params(params).err(),
+		Some(RcgenError::LintFailed(vec![Lint::CaWithoutKeyCertSign])));
+
+	// Warnings are fine in strict mode
+	let mut params = util::default_params();
+	params.strict = true;
+	Certificate::from_params(params).unwrap();
+}
+
+#[test]
+fn test_tls_alpn_01() {
+	use rcgen::{AcmeIdentifier, CustomExtension};
+
+	assert!(matches!(CustomExtension::try_new_acme_identifier(&[0; 31]),
+		Err(RcgenError::InvalidAcmeIdentifierDigest)));
+
+	let identifier = AcmeIdentifier::Ip("2001:db8::1".parse().unwrap());
+	assert_eq!(identifier.tls_alpn_01_server_name(),
+		"1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa");
+	let identifier = AcmeIdentifier::Dns("crabs.crabs".to_string());
+	assert_eq!(identifier.tls_alpn_01_server_name(), "crabs.crabs");
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_tls_alpn_01_certificate() {
+	use rcgen::AcmeIdentifier;
+	use x509_parser::extensions::GeneralName;
+
+	let key_authorization = "evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA.9jg46WB3rR_AHD-EBXdN7cBkH1WOu0tA3M9fm21mqTI";
+	let digest = ring::digest::digest(&ring::digest::SHA256, key_authorization.as_bytes());
+	let expected_content = yasna::construct_der(|writer| writer.write_bytes(digest.as_ref()));
+	for identifier in [
+		AcmeIdentifier::Dns("crabs.crabs".to_string()),
+		AcmeIdentifier::Ip("192.0.2.1".parse().unwrap()),
+	] {
+		let cert = rcgen::generate_tls_alpn_01_certificate(&identifier, key_authorization).unwrap();
+		let der = cert.serialize_der().unwrap();
+		let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+		let san = cert.subject_alternative_name().unwrap().unwrap().value;
+		match (&identifier, &san.general_names[..]) {
+			(AcmeIdentifier::Dns(name), [GeneralName::DNSName(san)]) => assert_eq!(name, san),
+			(AcmeIdentifier::Ip(_), [GeneralName::IPAddress(san)]) => assert_eq!(san, &[192, 0, 2, 1]),
+			_ => panic!("unexpected subject alternative names {:?}", san),
+		}
+		let acme = cert.extensions().iter()
+			.find(|ext| ext.oid.to_id_string() == "1.3.6.1.5.5.7.1.31")
+			.unwrap();
+		assert!(acme.critical);
+		assert_eq!(acme.value, expected_content);
+	}
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_csr_ip_address_san() {
+	use rcgen::{CertificateParams, CertificateSigningRequest, SanType};
+	use std::net::IpAddr;
+
+	let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+	let ip :IpAddr = "2001:db8::1".parse().unwrap();
+	params.subject_alt_names.push(SanType::IpAddress(ip));
+	let cert = Certificate::from_params(params).unwrap();
+	let csr = CertificateSigningRequest::from_der(&cert.serialize_request_der().unwrap()).unwrap();
+	assert_eq!(csr.params.subject_alt_names, vec![
+		SanType::DnsName("crabs.crabs".to_string()),
+		SanType::IpAddress(ip),
+	]);
+}
+
+#[test]
+fn test_validity_helpers() {
+	use rcgen::CertificateParams;
+	use time::{Duration, OffsetDateTime};
+
+	let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+	let before = OffsetDateTime::now_utc();
+	params.valid_from_now(30);
+	assert!(params.not_before >= before && params.not_before <= OffsetDateTime::now_utc());
+	assert_eq!(params.not_after - params.not_before, Duration::days(30));
+	params.backdate(Duration::minutes(5));
+	assert_eq!(params.not_after - params.not_before, Duration::days(30) + Duration::minutes(5));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_cap_validity_to_issuer() {
+	use rcgen::CertificateParams;
+
+	let mut params = CertificateParams::root_ca("Short lived CA");
+	params.valid_from_now(10);
+	let ca_not_after = params.not_after;
+	let (ca, ca_der) = issue(params, None);
+
+	let mut params = CertificateParams::tls_server(vec!["crabs.crabs".to_string()]);
+	params.valid_from_now(100);
+	let not_after = params.not_after;
+	let (_, der) = issue(params, Some(&ca));
+	let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+	assert_eq!(cert.validity().not_after.timestamp(), not_after.unix_timestamp());
+
+	let mut params = CertificateParams::tls_server(vec!["crabs.crabs".to_string()]);
+	params.valid_from_now(100);
+	params.cap_validity_to_issuer = true;
+	let (_, der) = issue(params, Some(&ca));
+	let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+	assert_eq!(cert.validity().not_after.timestamp(), ca_not_after.unix_timestamp());
+
+	// The validity of CA certificates loaded from DER is known as well
+	let key_pair = KeyPair::from_der(&ca.serialize_private_key_der()).unwrap();
+	let loaded_ca = Certificate::from_params(CertificateParams::from_ca_cert_der(&ca_der, key_pair).unwrap()).unwrap();
+	let mut params = CertificateParams::tls_server(vec!["crabs.crabs".to_string()]);
+	params.cap_validity_to_issuer = true;
+	let (_, der) = issue(params, Some(&loaded_ca));
+	let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+	let (_, ca_cert) = x509_parser::parse_x509_certificate(&ca_der).unwrap();
+	assert_eq!(cert.validity().not_after, ca_cert.validity().not_after);
+
+	// Capping to an issuer that has already expired would give an empty validity
+	let mut params = CertificateParams::root_ca("Expired CA");
+	params.not_before = rcgen::date_time_ymd(2000, 1, 1);
+	params.not_after = rcgen::date_time_ymd(2001, 1, 1);
+	let expired_ca = Certificate::from_params(params).unwrap();
+	let mut params = CertificateParams::tls_server(vec!["crabs.crabs".to_string()]);
+	params.valid_from_now(100);
+	params.cap_validity_to_issuer = true;
+	let cert = Certificate::from_params(params).unwrap();
+	match cert.serialize_der_with_signer(&expired_ca) {
+		Err(rcgen::RcgenError::IssuerValidityEndsBeforeNotBefore) => (),
+		other => panic!("unexpected result: {:?}", other.map(|_| ())),
+	}
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_enforce_name_constraints() {
+	use rcgen::{CertificateParams, CidrSubnet, DistinguishedName, DnType, GeneralSubtree,
+		NameConstraints, SanType};
+
+	let mut params = CertificateParams::root_ca("Crab CA");
+	let mut base = DistinguishedName::new();
+	base.push(DnType::OrganizationName, "Crabs Inc.");
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![
+			GeneralSubtree::DnsName("crabs.example".to_string()),
+			GeneralSubtree::IpAddress(CidrSubnet::from_str("10.0.0.0/8").unwrap()),
+			GeneralSubtree::DirectoryName(base),
+		],
+		excluded_subtrees : vec![GeneralSubtree::DnsName("secret.crabs.example".to_string())],
+	});
+	let (ca, ca_der) = issue(params, None);
+	let key_pair = KeyPair::from_der(&ca.serialize_private_key_der()).unwrap();
+	let loaded_ca = Certificate::from_params(CertificateParams::from_ca_cert_der(&ca_der, key_pair).unwrap()).unwrap();
+
+	let leaf = |names :&[&str], organization :&str| {
+		let mut params = CertificateParams::new(names.iter().map(|name| name.to_string()).collect::<Vec<_>>());
+		params.distinguished_name = DistinguishedName::new();
+		params.distinguished_name.push(DnType::OrganizationName, organization);
+		params.distinguished_name.push(DnType::CommonName, "Crab");
+		params.enforce_name_constraints = true;
+		Certificate::from_params(params).unwrap()
+	};
+	for ca in [&ca, &loaded_ca] {
+		let mut cert = leaf(&["www.crabs.example"], "Crabs Inc.");
+		let der = cert.serialize_der_with_signer(ca).unwrap();
+		rcgen::verify_certificate_chain(&[&der], &ca_der, &Default::default()).unwrap();
+
+		cert = leaf(&["www.crabs.example", "secret.crabs.example"], "Crabs Inc.");
+		assert_eq!(cert.serialize_der_with_signer(ca).err(),
+			Some(RcgenError::SubjectAltNameNotPermitted(SanType::DnsName("secret.crabs.example".to_string()))));
+		cert = leaf(&["www.lobsters.example"], "Crabs Inc.");
+		assert!(matches!(cert.serialize_der_with_signer(ca), Err(RcgenError::SubjectAltNameNotPermitted(_))));
+		let mut params = CertificateParams::new(vec![]);
+		params.subject_alt_names = vec![SanType::IpAddress("192.0.2.1".parse().unwrap())];
+		params.distinguished_name = DistinguishedName::new();
+		params.enforce_name_constraints = true;
+		assert_eq!(Certificate::from_params(params).unwrap().serialize_der_with_signer(ca).err(),
+			Some(RcgenError::SubjectAltNameNotPermitted(SanType::IpAddress("192.0.2.1".parse().unwrap()))));
+		cert = leaf(&["www.crabs.example"], "Lobsters Inc.");
+		assert!(matches!(cert.serialize_der_with_signer(ca), Err(RcgenError::SubjectNotPermitted(_))));
+	}
+
+	// Without enforcement, violating certificates are issued
+	let mut params = CertificateParams::new(vec!["www.lobsters.example".to_string()]);
+	assert!(!params.enforce_name_constraints);
+	params.distinguished_name = DistinguishedName::new();
+	Certificate::from_params(params).unwrap().serialize_der_with_signer(&ca).unwrap();
+
+	// CA certificates with URI subtrees can be loaded, but not enforced
+	let name_constraints = yasna::construct_der(|writer| writer.write_sequence(|writer| {
+		writer.next().write_tagged_implicit(yasna::Tag::context(0), |writer| writer.write_sequence(|writer| {
+			writer.next().write_sequence(|writer| {
+				writer.next().write_tagged_implicit(yasna::Tag::context(6), |writer| {
+					writer.write_ia5_string(".crabs.example")
+				});
+			});
+		}));
+	}));
+	let mut ext = rcgen::CustomExtension::from_oid_content(&[2, 5, 29, 30], name_constraints);
+	ext.set_criticality(true);
+	let mut params = CertificateParams::root_ca("URI constrained CA");
+	params.custom_extensions.push(ext);
+	let (ca, ca_der) = issue(params, None);
+	let key_pair = KeyPair::from_der(&ca.serialize_private_key_der()).unwrap();
+	let loaded_ca = Certificate::from_params(CertificateParams::from_ca_cert_der(&ca_der, key_pair).unwrap()).unwrap();
+	let params = CertificateParams::new(vec!["www.crabs.example".to_string()]);
+	Certificate::from_params(params).unwrap().serialize_der_with_signer(&loaded_ca).unwrap();
+	let mut params = CertificateParams::new(vec!["www.crabs.example".to_string()]);
+	params.enforce_name_constraints = true;
+	assert_eq!(Certificate::from_params(params).unwrap().serialize_der_with_signer(&loaded_ca).err(),
+		Some(RcgenError::UnsupportedIssuerNameConstraints));
+}
+
+#[test]
+fn test_profiles_lint_clean() {
+	use rcgen::{CertificateParams, SanType};
+
+	let server = CertificateParams::tls_server(vec!["crabs.crabs".to_string(), "127.0.0.1".to_string()]);
+	assert_eq!(server.subject_alt_names[1], SanType::IpAddress("127.0.0.1".parse().unwrap()));
+	let profiles = vec![
+		server,
+		CertificateParams::tls_client("crab@crabs.crabs"),
+		CertificateParams::tls_client("crab-client"),
+		CertificateParams::code_signing("Crab widgits SE"),
+		CertificateParams::smime("crab@crabs.crabs"),
+		CertificateParams::root_ca("Crab root CA"),
+		CertificateParams::intermediate_ca("Crab intermediate CA", 0),
+	];
+	for params in profiles {
+		assert_eq!(params.lint(), Vec::new());
+		assert!(params.not_after > params.not_before);
+	}
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_profiles_chain() {
+	use rcgen::{CertificateParams, ExtendedKeyUsagePurpose, PathValidationError, PathValidationOptions};
+
+	let (root, root_der) = issue(CertificateParams::root_ca("Crab root CA"), None);
+	let (intermediate, intermediate_der) = issue(CertificateParams::intermediate_ca("Crab intermediate CA", 0), Some(&root));
+	let (_, leaf_der) = issue(CertificateParams::tls_server(vec!["crabs.crabs".to_string()]), Some(&intermediate));
+	let mut options = PathValidationOptions::default();
+	options.extended_key_usage = Some(ExtendedKeyUsagePurpose::ServerAuth);
+	rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options).unwrap();
+
+	let (_, leaf_der) = issue(CertificateParams::code_signing("Crab widgits SE"), Some(&intermediate));
+	assert_eq!(rcgen::verify_certificate_chain(&[&leaf_der, &intermediate_der], &root_der, &options),
+		Err(PathValidationError::ExtendedKeyUsageNotAllowed(0, ExtendedKeyUsagePurpose::ServerAuth)));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_crl() {
+	use rcgen::{BasicConstraints, CertificateRevocationList, CertificateRevocationListParams,
+		KeyUsagePurpose, RevocationReason, RevokedCertParams};
+	use x509_parser::extensions::ParsedExtension;
+	use x509_parser::x509::ReasonCode;
+
+	let mut params = ca_params("CRL CA", BasicConstraints::Unconstrained);
+	params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+	params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+	let (ca, ca_der) = issue(params, None);
+
+	let now = time::OffsetDateTime::now_utc();
+	let mut params = CertificateRevocationListParams::new(now, now + time::Duration::days(7), 23);
+	params.revoked_certs.push(RevokedCertParams {
+		serial_number : 0x1234_5678_9abc,
+		revocation_time : now - time::Duration::days(1),
+		reason_code : Some(RevocationReason::KeyCompromise),
+	});
+	params.revoked_certs.push(RevokedCertParams {
+		serial_number : 42,
+		revocation_time : now,
+		reason_code : None,
+	});
+	let crl = CertificateRevocationList::from_params(params).unwrap();
+	let crl_der = crl.serialize_der_with_signer(&ca).unwrap();
+
+	let (_, parsed) = x509_parser::parse_x509_crl(&crl_der).unwrap();
+	let (_, ca_cert) = x509_parser::parse_x509_certificate(&ca_der).unwrap();
+	assert_eq!(parsed.tbs_cert_list.issuer.as_raw(), ca_cert.subject().as_raw());
+	let crl_number = parsed.extensions().iter()
+		.find_map(|ext| match ext.parsed_extension() {
+			ParsedExtension::CRLNumber(number) => Some(number.to_string()),
+			_ => None,
+		});
+	assert_eq!(crl_number.as_deref(), Some("23"));
+	let revoked = parsed.iter_revoked_certificates().collect::<Vec<_>>();
+	assert_eq!(revoked.len(), 2);
+	assert_eq!(revoked[0].raw_serial(), &[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc]);
+	assert_eq!(revoked[0].reason_code().unwrap().1, ReasonCode::KeyCompromise);
+	assert_eq!(revoked[1].raw_serial(), &[42]);
+	assert!(revoked[1].reason_code().is_none());
+
+	// Check the signature over the TBSCertList with the CA's key
+	let (tbs_cert_list, signature) = yasna::parse_der(&crl_der, |reader| {
+		reader.read_sequence(|reader| {
+			let tbs_cert_list = reader.next().read_der()?;
+			reader.next().read_der()?;
+			let (signature, _) = reader.next().read_bitvec_bytes()?;
+			Ok((tbs_cert_list, signature))
+		})
+	}).unwrap();
+	let public_key = ring::signature::UnparsedPublicKey::new(
+		&ring::signature::ECDSA_P256_SHA256_ASN1, ca.get_key_pair().public_key_raw());
+	public_key.verify(&tbs_cert_list, &signature).unwrap();
+
+	// The next update has to come after this update
+	let params = CertificateRevocationListParams::new(now, now - time::Duration::days(1), 1);
+	assert!(matches!(CertificateRevocationList::from_params(params),
+		Err(RcgenError::InvalidCrlNextUpdate)));
+
+	// CAs whose key usages don't allow signing CRLs are refused
+	let mut params = ca_params("Not a CRL signer", BasicConstraints::Unconstrained);
+	params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+	let (ca, _) = issue(params, None);
+	assert!(matches!(crl.serialize_der_with_signer(&ca), Err(RcgenError::IssuerNotCrlSigner)));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_dump() {
+	use rcgen::{BasicConstraints, CertificateParams, CertificateRevocationList,
+		CertificateRevocationListParams, CidrSubnet, DnType, ExtendedKeyUsagePurpose,
+		GeneralSubtree, NameConstraints, RevocationReason, RevokedCertParams, SanType, X509Dump};
+
+	let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+	params.alg = &rcgen::PKCS_ECDSA_P384_SHA384;
+	params.distinguished_name = rcgen::DistinguishedName::new();
+	params.distinguished_name.push(DnType::CountryName, "SE");
+	params.distinguished_name.push(DnType::OrganizationName, "Crabs, Inc.");
+	params.distinguished_name.push(DnType::CommonName, "Crab CA");
+	params.is_ca = rcgen::IsCa::Ca(BasicConstraints::Constrained(1));
+	params.subject_alt_names.push(SanType::IpAddress("192.0.2.1".parse().unwrap()));
+	params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::OcspSigning];
+	params.name_constraints = Some(NameConstraints {
+		permitted_subtrees : vec![
+			GeneralSubtree::DnsName("crabs.crabs".to_string()),
+			GeneralSubtree::IpAddress(CidrSubnet::from_str("192.0.2.0/24").unwrap()),
+		],
+		excluded_subtrees : Vec::new(),
+	});
+	let cert = Certificate::from_params(params).unwrap();
+	let der = cert.serialize_der().unwrap();
+
+	let text = X509Dump::from_der(&der).unwrap().to_string();
+	assert!(text.contains("Subject: CN=Crab CA,O=Crabs\\, Inc.,C=SE"), "{}", text);
+	assert!(text.contains("Issuer: CN=Crab CA,O=Crabs\\, Inc.,C=SE"), "{}", text);
+	assert!(text.contains("Signature algorithm: PKCS_ECDSA_P384_SHA384"), "{}", text);
+	assert!(text.contains("Public key: ECDSA P-384"), "{}", text);
+	assert!(text.contains("Subject alternative names: DNS:crabs.crabs, IP:192.0.2.1"), "{}", text);
+	assert!(text.contains("Basic constraints (critical): CA, path length 1"), "{}", text);
+	assert!(text.contains("Extended key usage: serverAuth, OCSPSigning"), "{}", text);
+	assert!(text.contains("Name constraints (critical): permitted DNS:crabs.crabs, IP:192.0.2.0/24"), "{}", text);
+	let fingerprint = ring::digest::digest(&ring::digest::SHA256, &der).as_ref().iter()
+		.map(|b| format!("{:02X}", b))
+		.collect::<Vec<_>>()
+		.join(":");
+	assert!(text.contains(&format!("SHA-256: {}", fingerprint)), "{}", text);
+
+	let csr_der = cert.serialize_request_der().unwrap();
+	let text = X509Dump::from_der(&csr_der).unwrap().to_string();
+	assert!(text.starts_with("Certificate signing request:"), "{}", text);
+	assert!(text.contains("Subject alternative names: DNS:crabs.crabs, IP:192.0.2.1"), "{}", text);
+
+	let now = time::OffsetDateTime::now_utc();
+	let mut params = CertificateRevocationListParams::new(now, now + time::Duration::days(7), 5);
+	params.revoked_certs.push(RevokedCertParams {
+		serial_number : 42,
+		revocation_time : now,
+		reason_code : Some(RevocationReason::Superseded),
+	});
+	let crl_der = CertificateRevocationList::from_params(params).unwrap()
+		.serialize_der_with_signer(&cert).unwrap();
+	let text = X509Dump::from_der(&crl_der).unwrap().to_string();
+	assert!(text.starts_with("Certificate revocation list:"), "{}", text);
+	assert!(text.contains("CRL number: 5"), "{}", text);
+	assert!(text.contains("Serial number: 2A"), "{}", text);
+	assert!(text.contains("Reason: Superseded"), "{}", text);
+
+	assert!(X509Dump::from_der(b"not DER").is_err());
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_renew_and_rekey() {
+	use rcgen::{BasicConstraints, CertificateParams, CustomExtension, ExtendedKeyUsagePurpose,
+		KeyUsagePurpose, SanType};
+
+	let mut params = ca_params("Renewal CA", BasicConstraints::Constrained(0));
+	params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+	let (ca, ca_der) = issue(params, None);
+
+	let mut params = CertificateParams::new(vec!["crabs.crabs".to_string()]);
+	params.subject_alt_names.push(SanType::IpAddress("192.0.2.1".parse().unwrap()));
+	params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+	params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth];
+	params.custom_extensions.push(CustomExtension::from_oid_content(&[1, 2, 3, 4], vec![0x05, 0x00]));
+	params.use_authority_key_identifier_extension = true;
+	let (_, leaf_der) = issue(params, Some(&ca));
+
+	let parsed = CertificateParams::from_certificate_der(&leaf_der).unwrap();
+	assert_eq!(parsed.subject_alt_names, vec![
+		SanType::DnsName("crabs.crabs".to_string()),
+		SanType::IpAddress("192.0.2.1".parse().unwrap()),
+	]);
+	assert_eq!(parsed.key_usages, vec![KeyUsagePurpose::DigitalSignature]);
+	assert_eq!(parsed.extended_key_usages, vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth]);
+	assert_eq!(parsed.custom_extensions.len(), 1);
+	assert_eq!(parsed.custom_extensions[0].oid_components().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+	assert!(parsed.use_authority_key_identifier_extension);
+
+	// Renewal keeps the key, but changes the serial number and validity
+	let not_before = rcgen::date_time_ymd(2030, 1, 1);
+	let not_after = rcgen::date_time_ymd(2031, 1, 1);
+	let renewed_der = rcgen::renew_certificate(&leaf_der, &ca, not_before, not_after).unwrap();
+	let (_, leaf) = x509_parser::parse_x509_certificate(&leaf_der).unwrap();
+	let (_, renewed) = x509_parser::parse_x509_certificate(&renewed_der).unwrap();
+	assert_eq!(renewed.public_key().raw, leaf.public_key().raw);
+	assert_ne!(renewed.raw_serial(), leaf.raw_serial());
+	assert_eq!(renewed.subject().as_raw(), leaf.subject().as_raw());
+	assert_eq!(renewed.validity().not_before.timestamp(), not_before.unix_timestamp());
+	assert_eq!(renewed.validity().not_after.timestamp(), not_after.unix_timestamp());
+	for ext in leaf.extensions() {
+		let renewed_ext = renewed.extensions().iter().find(|e| e.oid == ext.oid).unwrap();
+		assert_eq!(renewed_ext.critical, ext.critical);
+		assert_eq!(renewed_ext.value, ext.value);
+	}
+	let mut options = rcgen::PathValidationOptions::default();
+	options.time = not_before + time::Duration::days(1);
+	rcgen::verify_certificate_chain(&[&renewed_der], &ca_der, &options).unwrap();
+
+	// Re-keying replaces the key and the subject key identifier
+	let new_key = KeyPair::generate(&rcgen::PKCS_ED25519).unwrap();
+	let new_key_der = new_key.public_key_der();
+	let rekeyed = rcgen::rekey_certificate(&leaf_der, new_key).unwrap();
+	let rekeyed_der = rekeyed.serialize_der_with_signer(&ca).unwrap();
+	let (_, rekeyed_cert) = x509_parser::parse_x509_certificate(&rekeyed_der).unwrap();
+	assert_eq!(rekeyed_cert.public_key().raw, new_key_der);
+	assert_eq!(rekeyed_cert.subject_alternative_name().unwrap().unwrap().value,
+		leaf.subject_alternative_name().unwrap().unwrap().value);
+	let validity = rekeyed_cert.validity();
+	let leaf_validity = leaf.validity();
+	assert_eq!(validity.not_after.timestamp() - validity.not_before.timestamp(),
+		leaf_validity.not_after.timestamp() - leaf_validity.not_before.timestamp());
+	rcgen::verify_certificate_chain(&[&rekeyed_der], &ca_der, &Default::default()).unwrap();
+
+	// A renewed CA still verifies certificates issued before
+	let renewed_ca_der = rcgen::renew_certificate(&ca_der, &ca, not_before, not_after).unwrap();
+	let renewed_ca = CertificateParams::from_certificate_der(&renewed_ca_der).unwrap();
+	assert_eq!(renewed_ca.is_ca, rcgen::IsCa::Ca(BasicConstraints::Constrained(0)));
+	rcgen::verify_certificate_chain(&[&rekeyed_der], &renewed_ca_der, &Default::default()).unwrap();
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_cross_sign() {
+	use rcgen::CertificateParams;
+	use x509_parser::extensions::ParsedExtension;
+
+	fn key_identifiers(der :&[u8]) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+		let (_, cert) = x509_parser::parse_x509_certificate(der).unwrap();
+		let mut ski = None;
+		let mut aki = None;
+		for ext in cert.extensions() {
+			match ext.parsed_extension() {
+				ParsedExtension::SubjectKeyIdentifier(id) => ski = Some(id.0.to_vec()),
+				ParsedExtension::AuthorityKeyIdentifier(id) => aki = id.key_identifier.as_ref().map(|id| id.0.to_vec()),
+				_ => (),
+			}
+		}
+		(ski, aki)
+	}
+
+	let chain = rcgen::PkiBuilder::new()
+		.leaf_subject_alt_names(vec!["crabs.crabs".to_string()])
+		.build()
+		.unwrap();
+	let intermediate = &chain.intermediates()[0];
+	let mut params = CertificateParams::root_ca("Crab root CA 2");
+	params.key_identifier_method = rcgen::KeyIdMethod::Sha512;
+	let (new_root, new_root_der) = issue(params, None);
+
+	let now = time::OffsetDateTime::now_utc();
+	let cross_der = rcgen::cross_sign_certificate(intermediate.der(), &new_root,
+		now, now + time::Duration::days(365)).unwrap();
+	let (_, cross) = x509_parser::parse_x509_certificate(&cross_der).unwrap();
+	let (_, original) = x509_parser::parse_x509_certificate(intermediate.der()).unwrap();
+	assert_eq!(cross.subject().as_raw(), original.subject().as_raw());
+	assert_eq!(cross.public_key().raw, original.public_key().raw);
+	assert_eq!(cross.basic_constraints().unwrap().unwrap().value, original.basic_constraints().unwrap().unwrap().value);
+	let (cross_ski, cross_aki) = key_identifiers(&cross_der);
+	assert_eq!(cross_ski, key_identifiers(intermediate.der()).0);
+	assert_eq!(cross_aki, key_identifiers(&new_root_der).0);
+
+	// The leaf chains up to both roots
+	let leaf = chain.leaf().der();
+	rcgen::verify_certificate_chain(&[leaf, &cross_der], &new_root_der, &Default::default()).unwrap();
+	rcgen::verify_certificate_chain(&[leaf, intermediate.der()], chain.root().der(), &Default::default()).unwrap();
+
+	assert!(matches!(rcgen::cross_sign_certificate(leaf, &new_root, now, now),
+		Err(RcgenError::NotACaCertificate)));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_ct_precertificate() {
+	use rcgen::{CertificateParams, CtLog, CtLogEntry};
+
+	/// Splits concatenated DER elements
+	fn elements(mut data :&[u8]) -> Vec<&[u8]> {
+		let mut elements = Vec::new();
+		while !data.is_empty() {
+			let (header, len) = match data[1] {
+				n if n < 0x80 => (2, n as usize),
+				n => {
+					let bytes = (n & 0x7f) as usize;
+					(2 + bytes, data[2..2 + bytes].iter().fold(0, |len, b| len << 8 | *b as usize))
+				},
+			};
+			elements.push(&data[..header + len]);
+			data = &data[header + len..];
+		}
+		elements
+	}
+	fn content(element :&[u8]) -> &[u8] {
+		let header = if element[1] < 0x80 { 2 } else { 2 + (element[1] & 0x7f) as usize };
+		&element[header..]
+	}
+	fn wrap(tag :u8, content :&[u8]) -> Vec<u8> {
+		let mut out = vec![tag];
+		let len = content.len().to_be_bytes();
+		let len = &len[len.iter().position(|b| *b != 0).unwrap_or(len.len() - 1)..];
+		if content.len() >= 0x80 {
+			out.push(0x80 | len.len() as u8);
+		}
+		out.extend_from_slice(len);
+		out.extend_from_slice(content);
+		out
+	}
+	/// Removes the extension with the given OID from the certificate's TBSCertificate
+	fn tbs_without_extension(der :&[u8], oid :&[u64]) -> Vec<u8> {
+		let oid = yasna::construct_der(|writer| writer.write_oid(&yasna::models::ObjectIdentifier::from_slice(oid)));
+		let tbs = elements(content(der))[0];
+		let mut fields = elements(content(tbs)).into_iter().map(|f| f.to_vec()).collect::<Vec<_>>();
+		let extensions = fields.pop().unwrap();
+		assert_eq!(extensions[0], 0xa3);
+		let kept = elements(content(content(&extensions))).into_iter()
+			.filter(|ext| !content(ext).starts_with(&oid))
+			.flatten()
+			.copied()
+			.collect::<Vec<_>>();
+		fields.push(wrap(0xa3, &wrap(0x30, &kept)));
+		wrap(0x30, &fields.concat())
+	}
+	const POISON :&[u64] = &[1, 3, 6, 1, 4, 1, 11129, 2, 4, 3];
+	const SCT_LIST :&[u64] = &[1, 3, 6, 1, 4, 1, 11129, 2, 4, 2];
+
+	let (ca, ca_der) = issue(CertificateParams::root_ca("Crab CA"), None);
+	let mut params = util::default_params();
+	params.use_authority_key_identifier_extension = true;
+	let cert = Certificate::from_params(params).unwrap();
+	let log = CtLog::generate().unwrap();
+
+	let precert = cert.serialize_precertificate_der_with_signer(&ca).unwrap();
+	let (_, parsed) = x509_parser::parse_x509_certificate(&precert).unwrap();
+	let poison = parsed.extensions().iter()
+		.find(|ext| ext.oid.to_id_string() == "1.3.6.1.4.1.11129.2.4.3")
+		.unwrap();
+	assert!(poison.critical);
+	assert_eq!(poison.value, &[0x05, 0x00]);
+
+	let tbs = cert.precertificate_tbs_der(&ca).unwrap();
+	assert_eq!(tbs_without_extension(&precert, POISON), tbs);
+	let entry = CtLogEntry::precertificate(&ca, &tbs);
+	let sct = log.sign(&entry, time::OffsetDateTime::now_utc()).unwrap();
+	assert!(log.verify(&sct, &entry));
+	assert!(!log.verify(&sct, &CtLogEntry::X509(&precert)));
+	assert!(!CtLog::generate().unwrap().verify(&sct, &entry));
+
+	let final_der = cert.serialize_der_with_signer_and_scts(&ca, &[sct.clone(), sct.clone()]).unwrap();
+	let (_, final_cert) = x509_parser::parse_x509_certificate(&final_der).unwrap();
+	let sct_list = final_cert.extensions().iter()
+		.find(|ext| ext.oid.to_id_string() == "1.3.6.1.4.1.11129.2.4.2")
+		.unwrap();
+	assert!(!sct_list.critical);
+	let sct_bytes = sct.serialize();
+	let list = yasna::parse_der(sct_list.value, |reader| reader.read_bytes()).unwrap();
+	assert_eq!(list.len(), 2 + 2 * (2 + sct_bytes.len()));
+	assert_eq!(&list[4..4 + sct_bytes.len()], &sct_bytes[..]);
+	assert!(final_cert.extensions().iter().all(|ext| ext.oid.to_id_string() != "1.3.6.1.4.1.11129.2.4.3"));
+	rcgen::verify_certificate_chain(&[&final_der], &ca_der, &Default::default()).unwrap();
+
+	// Apart from the SCT list extension, the certificate has the TBSCertificate signed by the log
+	assert_eq!(tbs_without_extension(&final_der, SCT_LIST), tbs);
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_attribute_certificate() {
+	use rcgen::{AttributeCertificate, AttributeCertificateHolder, AttributeCertificateParams,
+		CertificateAttribute, CertificateParams, SanType};
+	use yasna::Tag;
+	use yasna::models::ObjectIdentifier;
+
+	let (ca, _) = issue(CertificateParams::root_ca("Crab CA"), None);
+	let mut params = util::default_params();
+	params.serial_number = Some(0x1234);
+	let (_, holder_der) = issue(params, Some(&ca));
+	let (authority, authority_der) = issue(util::default_params(), None);
+
+	let holder = AttributeCertificateHolder::from_certificate_der(&holder_der).unwrap();
+	let now = time::OffsetDateTime::now_utc();
+	let mut params = AttributeCertificateParams::new(holder, 42, now, now + time::Duration::hours(8));
+	params.attributes.push(CertificateAttribute::Role {
+		authority : vec![SanType::DnsName("crabs.crabs".to_string())],
+		name : SanType::URI("urn:crabs:admin".to_string()),
+	});
+	params.attributes.push(CertificateAttribute::Group(vec!["crabs".to_string(), "ferris".to_string()]));
+	let ac = AttributeCertificate::from_params(params.clone()).unwrap();
+	let der = ac.serialize_der_with_signer(&authority).unwrap();
+	let pem = ac.serialize_pem_with_signer(&authority).unwrap();
+	assert!(pem.starts_with("-----BEGIN ATTRIBUTE CERTIFICATE-----"));
+
+	let (acinfo, signature) = yasna::parse_der(&der, |reader| {
+		reader.read_sequence(|reader| {
+			let acinfo = reader.next().read_der()?;
+			reader.next().read_der()?;
+			let signature = reader.next().read_bitvec_bytes()?.0;
+			Ok((acinfo, signature))
+		})
+	}).unwrap();
+	let (_, authority_cert) = x509_parser::parse_x509_certificate(&authority_der).unwrap();
+	let (_, holder_cert) = x509_parser::parse_x509_certificate(&holder_der).unwrap();
+	ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_ASN1,
+		authority_cert.public_key().subject_public_key.data)
+		.verify(&acinfo, &signature)
+		.unwrap();
+
+	let read_directory_name = |reader :yasna::BERReader| reader.read_sequence(|reader| {
+		reader.next().read_tagged(Tag::context(4), |reader| reader.read_der())
+	});
+	yasna::parse_der(&acinfo, |reader| {
+		reader.read_sequence(|reader| {
+			assert_eq!(reader.next().read_u8()?, 1);
+			// Holder
+			let (issuer, serial) = reader.next().read_sequence(|reader| {
+				reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+					reader.read_sequence(|reader| {
+						Ok((read_directory_name(reader.next())?, reader.next().read_u64()?))
+					})
+				})
+			})?;
+			assert_eq!(issuer, holder_cert.issuer().as_raw());
+			assert_eq!(serial, 0x1234);
+			// Issuer
+			let issuer = reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+				reader.read_sequence(|reader| read_directory_name(reader.next()))
+			})?;
+			assert_eq!(issuer, authority_cert.subject().as_raw());
+			reader.next().read_der()?;
+			assert_eq!(reader.next().read_u64()?, 42);
+			reader.next().read_sequence(|reader| {
+				reader.next().read_generalized_time()?;
+				reader.next().read_generalized_time()
+			})?;
+			// Attributes
+			reader.next().read_sequence(|reader| {
+				reader.next().read_sequence(|reader| {
+					assert_eq!(reader.next().read_oid()?, ObjectIdentifier::from_slice(&[2, 5, 4, 72]));
+					reader.next().read_set_of(|reader| {
+						reader.read_sequence(|reader| {
+							let authority = reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+								reader.read_sequence(|reader| {
+									reader.next().read_tagged_implicit(Tag::context(2), |reader| reader.read_ia5_string())
+								})
+							})?;
+							assert_eq!(authority, "crabs.crabs");
+							let name = reader.next().read_tagged(Tag::context(1), |reader| {
+								reader.read_tagged_implicit(Tag::context(6), |reader| reader.read_ia5_string())
+							})?;
+							assert_eq!(name, "urn:crabs:admin");
+							Ok(())
+						})
+					})
+				})?;
+				reader.next().read_sequence(|reader| {
+					assert_eq!(reader.next().read_oid()?, ObjectIdentifier::from_slice(&[1, 3, 6, 1, 5, 5, 7, 10, 4]));
+					reader.next().read_set_of(|reader| {
+						let groups = reader.read_sequence(|reader| {
+							reader.next().read_sequence(|reader| {
+								Ok((reader.next().read_utf8string()?, reader.next().read_utf8string()?))
+							})
+						})?;
+						assert_eq!(groups, ("crabs".to_string(), "ferris".to_string()));
+						Ok(())
+					})
+				})
+			})?;
+			// The authority key identifier extension
+			reader.next().read_der()?;
+			Ok(())
+		})
+	}).unwrap();
+
+	params.not_after = now - time::Duration::hours(1);
+	assert!(matches!(AttributeCertificate::from_params(params),
+		Err(RcgenError::InvalidAttributeCertificateValidity)));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_cms_signed_data_der() {
+	use rcgen::CmsSignedDataParams;
+	use yasna::Tag;
+
+	let chain = rcgen::PkiBuilder::new().build().unwrap();
+	let (signer, signer_der) = issue(util::default_params(), Some(chain.intermediates()[0].certificate()));
+	let params = CmsSignedDataParams {
+		ca_certificates : chain.certificates().iter().map(|cert| cert.der().to_vec()).collect(),
+		.. Default::default()
+	};
+	let signed = signer.get_key_pair().sign_cms_der(&signer_der, b"Hello crabs", &params).unwrap();
+
+	// Parsing as DER checks that the certificates and the
+	// signed attributes are sorted as DER requires for SET OF
+	let (certificates, attributes) = yasna::parse_der(&signed, |reader| reader.read_sequence(|reader| {
+		reader.next().read_oid()?;
+		reader.next().read_tagged(Tag::context(0), |reader| reader.read_sequence(|reader| {
+			reader.next().read_u8()?;
+			reader.next().read_der()?;
+			reader.next().read_der()?;
+			let certificates = reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+				reader.collect_set_of(|reader| reader.read_der())
+			})?;
+			let attributes = reader.next().collect_set_of(|reader| reader.read_sequence(|reader| {
+				reader.next().read_u8()?;
+				reader.next().read_der()?;
+				reader.next().read_der()?;
+				let attributes = reader.next().read_tagged_implicit(Tag::context(0), |reader| {
+					reader.collect_set_of(|reader| reader.read_der())
+				})?;
+				reader.next().read_der()?;
+				reader.next().read_bytes()?;
+				Ok(attributes)
+			}))?;
+			Ok((certificates, attributes))
+		}))
+	})).unwrap();
+	assert_eq!(certificates.len(), 4);
+	// Content type, message digest and signing time
+	assert_eq!(attributes[0].len(), 3);
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_time_stamp_request() {
+	use rcgen::{CertificateParams, ExtendedKeyUsagePurpose, FingerprintAlgorithm, TimeStampAuthority,
+		TimeStampAuthorityParams, TimeStampRequest};
+	use x509_parser::extensions::ParsedExtension;
+
+	let mut request = TimeStampRequest::new(FingerprintAlgorithm::Sha384, b"Crabs are the best");
+	request.nonce = Some(vec![0x00, 0xc0, 0xff, 0xee]);
+	request.policy = Some(vec![1, 2, 3, 4]);
+	let der = request.serialize_der();
+	assert_eq!(TimeStampRequest::from_der(&der).unwrap(), request);
+	assert_eq!(TimeStampRequest::from_der(&der[1..]), Err(RcgenError::CouldNotParseTimeStampRequest));
+	let mut truncated = request.clone();
+	truncated.hashed_message.pop();
+	assert_eq!(TimeStampRequest::from_der(&truncated.serialize_der()),
+		Err(RcgenError::CouldNotParseTimeStampRequest));
+
+	// RFC 3161 requires the extended key usage extension to be critical
+	let (ca, _) = issue(CertificateParams::root_ca("Crab CA"), None);
+	let (tsa_cert, tsa_der) = issue(CertificateParams::time_stamping("Crab TSA"), Some(&ca));
+	let (_, cert) = x509_parser::parse_x509_certificate(&tsa_der).unwrap();
+	let eku = cert.extensions().iter()
+		.find(|ext| matches!(ext.parsed_extension(), ParsedExtension::ExtendedKeyUsage(_)))
+		.unwrap();
+	assert!(eku.critical);
+	// Other certificates with the same purpose get a non critical extension
+	let mut params = util::default_params();
+	params.extended_key_usages = vec![ExtendedKeyUsagePurpose::TimeStamping];
+	let (_, other_der) = issue(params, Some(&ca));
+	let (_, cert) = x509_parser::parse_x509_certificate(&other_der).unwrap();
+	let eku = cert.extensions().iter()
+		.find(|ext| matches!(ext.parsed_extension(), ParsedExtension::ExtendedKeyUsage(_)))
+		.unwrap();
+	assert!(!eku.critical);
+
+	let tsa = TimeStampAuthority::new(tsa_cert, tsa_der, TimeStampAuthorityParams::new(&[1, 2, 3, 5]));
+	let failure_info = |response :&[u8]| yasna::parse_der(response, |reader| {
+		reader.read_sequence(|reader| {
+			reader.next().read_sequence(|reader| {
+				assert_eq!(reader.next().read_u8()?, 2);
+				reader.next().read_bitvec_bytes()
+			})
+		})
+	}).unwrap();
+	// unacceptedPolicy
+	assert_eq!(failure_info(&tsa.respond_der(&der).unwrap()), (vec![0, 1], 16));
+	// badDataFormat
+	assert_eq!(failure_info(&tsa.respond_der(&der[1..]).unwrap()), (vec![0x04], 6));
+}
+
+#[test]
+#[cfg(feature = "x509-parser")]
+fn test_certificate_authority() {
+	use rcgen::{CertificateAuthority, CertificateParams, CertificateStore, DnType,
+		FileCertificateStore, MemoryCertificateStore, RevocationReason};
+
+	let path = std::env::temp_dir().join(format!("rcgen-ca-store-{}.txt", std::process::id()));
+	let _ = std::fs::remove_file(&path);
+	let stores :Vec<Box<dyn CertificateStore + Send + Sync>> = vec![
+		Box::new(MemoryCertificateStore::new()),
+		Box::new(FileCertificateStore::open(&path).unwrap()),
+	];
+	let now = time::OffsetDateTime::now_utc();
+	for store in stores {
+		let (ca, ca_der) = issue(CertificateParams::root_ca("Crab CA"), None);
+		let mut ca = CertificateAuthority::new(ca, store);
+
+		// Serial numbers are assigned if missing, and have to be unique
+		let mut serials = Vec::new();
+		for name in ["a.crabs", "b.crabs"] {
+			let (_, der) = ca.issue(CertificateParams::new(vec![name.to_string()])).unwrap();
+			let (_, parsed) = x509_parser::parse_x509_certificate(&der).unwrap();
+			let serial = parsed.raw_serial().iter().fold(0, |serial, b| (serial << 8) | *b as u64);
+			assert!(ca.store().get(serial).unwrap().is_some());
+			rcgen::verify_certificate_chain(&[&der], &ca_der, &Default::default()).unwrap();
+			serials.push(serial);
+		}
+		assert_ne!(serials[0], serials[1]);
+		let mut params = CertificateParams::new(vec!["c.crabs".to_string()]);
+		params.serial_number = Some(serials[0]);
+		assert_eq!(ca.issue(params).err(), Some(RcgenError::DuplicateSerialNumber(serials[0])));
+
+		// Expired certificates are left out of CRLs
+		let mut params = CertificateParams::new(vec!["expired.crabs".to_string()]);
+		params.distinguished_name.push(DnType::OrganizationName, "Crabs Inc.");
+		let csr = Certificate::from_params(params).unwrap().serialize_request_der().unwrap();
+		let mut csr = rcgen::CertificateSigningRequest::from_der(&csr).unwrap();
+		csr.params.serial_number = Some(42);
+		csr.params.not_after = now - time::Duration::days(1);
+		ca.sign_request(csr).unwrap();
+		let records = ca.store().certificates().unwrap();
+		assert_eq!(records.len(), 3);
+		let expired = records.iter().find(|record| record.serial_number == 42).unwrap();
+		assert_eq!(expired.subject.get(&DnType::OrganizationName), Some(&"Crabs Inc.".into()));
+		assert_eq!(expired.not_after.unix_timestamp(), (now - time::Duration::days(1)).unix_timestamp());
+
+		ca.revoke(serials[1], now, Some(RevocationReason::KeyCompromise)).unwrap();
+		ca.revoke(42, now, None).unwrap();
+		assert_eq!(ca.revoke(43, now, None), Err(RcgenError::UnknownSerialNumber(43)));
+		assert!(ca.store().get(serials[0]).unwrap().unwrap().revocation.is_none());
+
+		for crl_number in 1..=2 {
+			let crl_der = ca.serialize_crl_der(now, now + time::Duration::days(7)).unwrap();
+			let (_, crl) = x509_parser::parse_x509_crl(&crl_der).unwrap();
+			let number = crl.extensions().iter()
+				.find_map(|ext| match ext.parsed_extension() {
+					x509_parser::extensions::ParsedExtension::CRLNumber(number) => Some(number.to_string()),
+					_ => None,
+				});
+			assert_eq!(number, Some(crl_number.to_string()));
+			let revoked = crl.iter_revoked_certificates().collect::<Vec<_>>();
+			assert_eq!(revoked.len(), 1);
+			assert_eq!(revoked[0].raw_serial(), &serials[1].to_be_bytes()[8 - revoked[0].raw_serial().len()..]);
+		}
+	}
+
+	// The file store keeps the records
+	let store = FileCertificateStore::open(&path).unwrap();
+	let records = store.certificates().unwrap();
+	assert_eq!(records.len(), 3);
+	assert_eq!(records.iter().filter(|record| record.revocation.is_some()).count(), 2);
+	let mut store = store;
+	assert_eq!(store.next_crl_number().unwrap(), 3);
+	std::fs::write(&path, "crl_number 3\nnot a record\n").unwrap();
+	assert!(matches!(FileCertificateStore::open(&path), Err(RcgenError::CertificateStoreError(_))));
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_signing_policy() {
+	use rcgen::{BasicConstraints, CertificateParams, CidrSubnet, ExtendedKeyUsagePurpose, IsCa,
+		KeyUsagePurpose, PolicyViolation, SanType, SigningPolicy, SigningRules};
+
+	let mut rules = SigningRules::default();
+	rules.allowed_dns_names = Some(vec!["*.crabs.example".to_string(), "crabs.example".to_string()]);
+	rules.allowed_ip_addresses = Some(vec![CidrSubnet::from_str("10.0.0.0/8").unwrap()]);
+	rules.allow_other_names = false;
+	rules.key_usages = Some(vec![KeyUsagePurpose::DigitalSignature]);
+	rules.extended_key_usages = Some(vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth]);
+	rules.validity = Some(time::Duration::days(30));
+	let violation = |params :&mut CertificateParams, rules :&SigningRules| match rules.apply(params) {
+		Err(RcgenError::PolicyViolation(violation)) => violation,
+		r => panic!("expected a policy violation, got {:?}", r),
+	};
+
+	let mut params = CertificateParams::new(vec!["shop.crabs.example".to_string(), "crabs.example".to_string()]);
+	params.subject_alt_names.push(SanType::IpAddress("10.1.2.3".parse().unwrap()));
+	params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+	rules.apply(&mut params).unwrap();
+	assert_eq!(params.subject_alt_names.len(), 3);
+	// Missing usages are filled in, requested ones kept
+	assert_eq!(params.key_usages, vec![KeyUsagePurpose::DigitalSignature]);
+	assert_eq!(params.extended_key_usages, vec![ExtendedKeyUsagePurpose::ServerAuth]);
+	assert_eq!(params.not_after - params.not_before, time::Duration::days(30));
+
+	let mut params = CertificateParams::new(vec!["a.shop.crabs.example".to_string()]);
+	assert_eq!(violation(&mut params, &rules),
+		PolicyViolation::SubjectAltNameNotAllowed(SanType::DnsName("a.shop.crabs.example".to_string())));
+	let mut params = CertificateParams::new(vec![]);
+	params.subject_alt_names.push(SanType::Rfc822Name("crab@crabs.example".to_string()));
+	assert!(matches!(violation(&mut params, &rules), PolicyViolation::SubjectAltNameNotAllowed(_)));
+	let mut params = CertificateParams::new(vec!["crabs.example".to_string()]);
+	params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+	assert_eq!(violation(&mut params, &rules), PolicyViolation::KeyUsageNotAllowed(KeyUsagePurpose::KeyCertSign));
+	let mut params = CertificateParams::new(vec![]);
+	params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+	assert_eq!(violation(&mut params, &rules), PolicyViolation::CaNotAllowed);
+	// Names can't be smuggled in through the common name
+	let mut params = CertificateParams::new(vec![]);
+	params.distinguished_name.push(rcgen::DnType::CommonName, "shop.lobsters.example");
+	assert_eq!(violation(&mut params, &rules), PolicyViolation::SubjectAltNamesMissing);
+	let mut unrestricted = SigningRules::default();
+	unrestricted.allow_other_names = false;
+	unrestricted.apply(&mut CertificateParams::new(vec![])).unwrap();
+
+	// Filtering removes what isn't allowed
+	let mut filtering = rules.clone();
+	filtering.filter = true;
+	filtering.validity = None;
+	filtering.max_validity = Some(time::Duration::days(90));
+	let mut params = CertificateParams::new(vec!["shop.crabs.example".to_string(), "shop.lobsters.example".to_string()]);
+	params.subject_alt_names.push(SanType::IpAddress("192.0.2.1".parse().unwrap()));
+	params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::CodeSigning];
+	let before = time::OffsetDateTime::now_utc();
+	filtering.apply(&mut params).unwrap();
+	let after = time::OffsetDateTime::now_utc();
+	assert_eq!(params.subject_alt_names, vec![SanType::DnsName("shop.crabs.example".to_string())]);
+	assert_eq!(params.extended_key_usages, vec![ExtendedKeyUsagePurpose::ServerAuth]);
+	// The default validity starting in 1975 is shortened to start now
+	assert!(before <= params.not_before && params.not_before <= after);
+	assert_eq!(params.not_after - params.not_before, time::Duration::days(90));
+	// A validity starting in the future keeps its start
+	let mut params = CertificateParams::new(vec!["shop.crabs.example".to_string()]);
+	params.not_before = rcgen::date_time_ymd(3000, 1, 1);
+	filtering.apply(&mut params).unwrap();
+	assert_eq!(params.not_before, rcgen::date_time_ymd(3000, 1, 1));
+	assert_eq!(params.not_after, rcgen::date_time_ymd(3000, 4, 1));
+	let mut params = CertificateParams::new(vec!["shop.lobsters.example".to_string()]);
+	assert_eq!(violation(&mut params, &filtering), PolicyViolation::NoAllowedSubjectAltNames);
+	filtering.filter = false;
+	let mut params = CertificateParams::new(vec!["shop.crabs.example".to_string()]);
+	assert!(matches!(violation(&mut params, &filtering), PolicyViolation::ValidityTooLong(_)));
+
+	// The CA applies the policy to CSRs
+	#[cfg(feature = "x509-parser")]
+	{
+		use rcgen::{CertificateAuthority, CertificateSigningRequest, MemoryCertificateStore};
+
+		let (ca, ca_der) = issue(CertificateParams::root_ca("Crab CA"), None);
+		let mut ca = CertificateAuthority::new(ca, Box::new(MemoryCertificateStore::new()));
+		ca.set_signing_policy(Box::new(rules));
+		let csr = |name :&str| {
+			let params = CertificateParams::new(vec![name.to_string()]);
+			let der = Certificate::from_params(params).unwrap().serialize_request_der().unwrap();
+			CertificateSigningRequest::from_der(&der).unwrap()
+		};
+		let der = ca.sign_request(csr("shop.crabs.example")).unwrap();
+		rcgen::verify_certificate_chain(&[&der], &ca_der, &Default::default()).unwrap();
+		let (_, cert) = x509_parser::parse_x509_certificate(&der).unwrap();
+		let validity = cert.validity();
+		assert_eq!(validity.not_after.timestamp() - validity.not_before.timestamp(), 30 * 24 * 60 * 60);
+		assert!(matches!(ca.sign_request(csr("shop.lobsters.example")),
+			Err(RcgenError::PolicyViolation(PolicyViolation::SubjectAltNameNotAllowed(_)))));
+		assert_eq!(ca.store().certificates().unwrap().len(), 1);
+	}
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_pki_config() {
+	use rcgen::{BasicConstraints, CertificateParams, DnType, DnValue, IsCa, PkiConfig, SanType};
+
+	let config = r#"
+		[[certificates]]
+		name = "root"
+		is_ca = { Ca = "Unconstrained" }
+		key_usages = ["KeyCertSign", "CrlSign"]
+		distinguished_name = { CommonName = "Crab root CA", "2.5.4.5" = { PrintableString = "42" } }
+		name_constraints = { permitted_subtrees = [{ DnsName = "crabs.crabs" }, { IpAddress = "10.0.0.0/8" }], excluded_subtrees = [] }
+
+		[[certificates]]
+		name = "server"
+		issuer = "root"
+		alg = "PKCS_ED25519"
+		not_before = "2022-01-01T00:00:00Z"
+		not_after = "2023-01-01T00:00:00Z"
+		serial_number = 23
+		subject_alt_names = [{ DnsName = "www.crabs.crabs" }, { IpAddress = "10.1.2.3" }]
+		extended_key_usages = ["ServerAuth"]
+		use_authority_key_identifier_extension = true
+	"#;
+	let config :PkiConfig = toml::from_str(config).unwrap();
+	let root = &config.certificates[0].params;
+	assert_eq!(root.is_ca, IsCa::Ca(BasicConstraints::Unconstrained));
+	assert_eq!(root.distinguished_name.get(&DnType::CustomDnType(vec![2, 5, 4, 5])),
+		Some(&DnValue::PrintableString("42".to_string())));
+	let server = &config.certificates[1].params;
+	assert_eq!(server.alg, &rcgen::PKCS_ED25519);
+	assert_eq!(server.not_after, rcgen::date_time_ymd(2023, 1, 1));
+	assert_eq!(server.serial_number, Some(23));
+	assert_eq!(server.subject_alt_names[1], SanType::IpAddress("10.1.2.3".parse().unwrap()));
+	// Fields that aren't given keep their defaults
+	assert_eq!(server.distinguished_name, CertificateParams::default().distinguished_name);
+	assert!(server.key_pair.is_none());
+
+	// Serializing and deserializing again gives the same parameters
+	let json = serde_json::to_string(&config).unwrap();
+	let config_json :PkiConfig = serde_json::from_str(&json).unwrap();
+	assert_eq!(serde_json::to_string(&config_json).unwrap(), json);
+	assert_eq!(config_json.certificates[0].params.name_constraints, root.name_constraints);
+
+	let pki = config.generate().unwrap();
+	assert_eq!(pki.iter().map(|(name, _)| name).collect::<Vec<_>>(), vec!["root", "server"]);
+	#[cfg(feature = "x509-parser")]
+	{
+		let mut options = rcgen::PathValidationOptions::default();
+		options.time = rcgen::date_time_ymd(2022, 6, 1);
+		rcgen::verify_certificate_chain(&[pki.get("server").unwrap().der()],
+			pki.get("root").unwrap().der(), &options).unwrap();
+	}
+
+	let config = r#"
+		[[certificates]]
+		name = "server"
+		issuer = "root"
+	"#;
+	let config :PkiConfig = toml::from_str(config).unwrap();
+	assert!(matches!(config.generate(), Err(RcgenError::UnknownIssuer(name)) if name == "root"));
+
+	for alg in ["PKCS_DSA_SHA1", "PKCS_RSA_PSS_SHA256"].iter() {
+		let config = format!(r#"
+			[[certificates]]
+			name = "root"
+			alg = "{}"
+		"#, alg);
+		assert!(toml::from_str::<PkiConfig>(&config).is_err(), "{}", alg);
+	}
+}
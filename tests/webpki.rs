@@ -1,6 +1,8 @@
+#![allow(clippy::complexity, clippy::style, clippy::pedantic)]
+
 #[cfg(feature = "x509-parser")]
 use rcgen::{CertificateSigningRequest, DnValue};
-use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair, RemoteKeyPair};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair, PkiBuilder, RemoteKeyPair};
 use webpki::{EndEntityCert, TlsServerTrustAnchors, TrustAnchor};
 use webpki::SignatureAlgorithm;
 use webpki::{Time, DnsNameRef};
@@ -406,3 +408,31 @@ fn test_certificate_from_csr() {
 	check_cert_ca(&cert_der, &cert, &ca_cert_der,
 		&webpki::ECDSA_P256_SHA256, &webpki::ECDSA_P256_SHA256, sign_fn);
 }
+
+#[test]
+fn test_webpki_pki_builder() {
+	let chain = PkiBuilder::new()
+		.intermediates(2)
+		.leaf_subject_alt_names(vec!["crabs.crabs".to_string()])
+		.build()
+		.unwrap();
+
+	let trust_anchor = TrustAnchor::try_from_cert_der(chain.root().der()).unwrap();
+	let trust_anchor_list = &[trust_anchor];
+	let trust_anchors = TlsServerTrustAnchors(trust_anchor_list);
+	let intermediates = chain.intermediates().iter()
+		.map(|intermediate| intermediate.der())
+		.collect::<Vec<_>>();
+	let end_entity_cert = EndEntityCert::try_from(chain.leaf().der()).unwrap();
+
+	let time = Time::from_seconds_since_unix_epoch(0x40_00_00_00);
+	end_entity_cert.verify_is_valid_tls_server_cert(
+		&[&webpki::ECDSA_P256_SHA256],
+		&trust_anchors,
+		&intermediates,
+		time,
+	).expect("valid TLS server cert");
+	let dns_name = DnsNameRef::try_from_ascii_str("crabs.crabs").unwrap();
+	end_entity_cert.verify_is_valid_for_dns_name(dns_name)
+		.expect("valid for DNS name");
+}